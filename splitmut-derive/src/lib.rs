@@ -0,0 +1,145 @@
+//! The `#[checked]` attribute of the splitmut crate. Use it through `splitmut::checked`,
+//! with the `checked` feature of splitmut enabled.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::visit_mut::{self, VisitMut};
+use syn::{parse_macro_input, parse_quote, Block, Expr, ExprBinary, ExprClosure, ExprMatch, Ident, Item, ItemFn, Stmt, BinOp};
+
+/// Rewrites statements that take several `&mut c[k]` from the same collection into
+/// one lookup with `splitmut!`. See the documentation of `splitmut::checked`.
+#[proc_macro_attribute]
+pub fn checked(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mode = parse_macro_input!(attr as Option<Ident>);
+    let result = match mode {
+        None => false,
+        Some(ref m) if m == "result" => true,
+        Some(m) => return syn::Error::new(m.span(), "expected `result` or nothing").to_compile_error().into(),
+    };
+    let mut f = parse_macro_input!(item as ItemFn);
+    Rewriter { result, next: 0 }.visit_block_mut(&mut f.block);
+    quote!(#f).into()
+}
+
+// The collection and index of `&mut c[k]`.
+fn index_mut(e: &Expr) -> Option<(&Expr, &Expr)> {
+    if let Expr::Reference(r) = e {
+        if let (Some(_), Expr::Index(i)) = (&r.mutability, &*r.expr) { return Some((&i.expr, &i.index)) }
+    }
+    None
+}
+
+fn tokens(e: &Expr) -> String { quote!(#e).to_string() }
+
+// Visits the expressions evaluated unconditionally as part of a statement, i e,
+// everything except blocks, closures, match arms, items and the right side of `&&` and `||`.
+macro_rules! statement_only {
+    () => {
+        fn visit_block_mut(&mut self, _: &mut Block) {}
+        fn visit_expr_closure_mut(&mut self, _: &mut ExprClosure) {}
+        fn visit_item_mut(&mut self, _: &mut Item) {}
+        fn visit_expr_match_mut(&mut self, m: &mut ExprMatch) { self.visit_expr_mut(&mut m.expr) }
+        fn visit_expr_binary_mut(&mut self, b: &mut ExprBinary) {
+            self.visit_expr_mut(&mut b.left);
+            if !matches!(b.op, BinOp::And(_) | BinOp::Or(_)) { self.visit_expr_mut(&mut b.right) }
+        }
+    }
+}
+
+// Counts the `&mut c[k]` of every collection `c`.
+struct Count(Vec<(String, usize)>);
+
+impl VisitMut for Count {
+    statement_only!();
+    fn visit_expr_mut(&mut self, e: &mut Expr) {
+        if let Some((c, _)) = index_mut(e) {
+            let c = tokens(c);
+            match self.0.iter_mut().find(|x| x.0 == c) {
+                Some(x) => x.1 += 1,
+                None => self.0.push((c, 1)),
+            }
+        }
+        visit_mut::visit_expr_mut(self, e)
+    }
+}
+
+struct Group { coll: Expr, name: String, keys: Vec<Expr>, ids: Vec<Ident> }
+
+// Replaces `&mut c[k]` with a variable, for collections with more than one of them.
+struct Replace<'a> { count: &'a Count, groups: Vec<Group>, next: &'a mut usize }
+
+impl<'a> VisitMut for Replace<'a> {
+    statement_only!();
+    fn visit_expr_mut(&mut self, e: &mut Expr) {
+        let found = index_mut(e).map(|(c, k)| (c.clone(), k.clone()));
+        if let Some((c, k)) = found {
+            let name = tokens(&c);
+            if self.count.0.iter().any(|x| x.0 == name && x.1 > 1) {
+                let id = Ident::new(&format!("__splitmut_{}", self.next), Span::call_site());
+                *self.next += 1;
+                let g = match self.groups.iter().position(|g| g.name == name) {
+                    Some(i) => &mut self.groups[i],
+                    None => {
+                        self.groups.push(Group { coll: c, name, keys: vec![], ids: vec![] });
+                        self.groups.last_mut().unwrap()
+                    }
+                };
+                g.keys.push(k);
+                g.ids.push(id.clone());
+                *e = parse_quote!(#id);
+                return;
+            }
+        }
+        visit_mut::visit_expr_mut(self, e)
+    }
+}
+
+struct Rewriter { result: bool, next: usize }
+
+impl Rewriter {
+    // Only prelude names and `::splitmut` are used, so this also works in 2015 edition crates.
+    fn lookup(&self, g: &Group) -> Stmt {
+        let Group { coll, name, keys, ids } = g;
+        let rs: Vec<Ident> = ids.iter().map(|i| Ident::new(&format!("{}_r", i), Span::call_site())).collect();
+        let exprs: Vec<String> = keys.iter().map(|k| format!("{}[{}]", name, tokens(k))).collect();
+        let fail = if self.result {
+            quote!(return Err(From::from(e)))
+        } else {
+            quote!(panic!("`{}`: {}", [#(#exprs),*][e.position], e.error))
+        };
+        parse_quote! {
+            let (#(#ids,)*) = match ::splitmut::splitmut!(#coll; #(#keys),*) {
+                (#(Ok(#ids),)*) => (#(#ids,)*),
+                (#(#rs,)*) => {
+                    let e = ::splitmut::__private::key_error(&[#(#rs.err()),*]);
+                    #fail
+                }
+            };
+        }
+    }
+}
+
+impl VisitMut for Rewriter {
+    fn visit_block_mut(&mut self, b: &mut Block) {
+        visit_mut::visit_block_mut(self, b);
+        let mut stmts = Vec::with_capacity(b.stmts.len());
+        for mut s in b.stmts.drain(..) {
+            let mut count = Count(vec![]);
+            count.visit_stmt_mut(&mut s.clone());
+            let groups = {
+                let mut r = Replace { count: &count, groups: vec![], next: &mut self.next };
+                r.visit_stmt_mut(&mut s);
+                r.groups
+            };
+            stmts.extend(groups.iter().map(|g| self.lookup(g)));
+            stmts.push(s);
+        }
+        b.stmts = stmts;
+    }
+}
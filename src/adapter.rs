@@ -0,0 +1,285 @@
+//! Wrappers that give other collections the SplitMut API.
+
+use core::ops::IndexMut;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use {SplitMutBase, GetRef};
+
+/// Implements `SplitMut` for any `IndexMut` collection, given a function
+/// that tells whether a key is present.
+///
+/// Creating one is unsafe, and there is no safe way to do it. Values returned for
+/// earlier keys are kept while `index_mut` is called for the later ones, and safe code
+/// in `index_mut` may move the values in between, e g by growing a `Vec`. Comparing
+/// addresses afterwards, as is done to find keys giving the same value, cannot detect
+/// that: a moved value may end up at its old address again. So the caller has to
+/// promise that the collection does not do this.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, SplitMutError, IndexSplit};
+///
+/// let mut s = unsafe { IndexSplit::new(vec![1, 2, 3], |v: &Vec<i32>, &k: &usize| k < v.len()) };
+/// assert_eq!(s.get2_mut(0, 2), (Ok(&mut 1), Ok(&mut 3)));
+/// assert_eq!(s.get2_mut(3, 2), (Err(SplitMutError::NoValue), Ok(&mut 3)));
+/// ```
+pub struct IndexSplit<T, F> { inner: T, contains: F }
+
+impl<T, F> IndexSplit<T, F> {
+    /// Wraps a collection, using `contains` to check whether a key is present
+    /// before indexing.
+    ///
+    /// # Safety
+    /// `index_mut` must not move or drop existing values of the collection, and must
+    /// return references to the same value for the same key; `contains` must
+    /// return true only for keys that `index_mut` accepts without panicking.
+    /// This holds for all well-behaved collections.
+    pub unsafe fn new(inner: T, contains: F) -> Self { IndexSplit { inner, contains } }
+
+    /// Returns a shared reference to the wrapped collection.
+    pub fn get_ref(&self) -> &T { &self.inner }
+
+    /// Returns the wrapped collection.
+    pub fn into_inner(self) -> T { self.inner }
+}
+
+unsafe impl<K, V, T: IndexMut<K, Output=V>, F: Fn(&T, &K) -> bool> SplitMutBase<K, V> for IndexSplit<T, F> {
+    #[inline]
+    fn get1_mut(&mut self, k: K) -> Option<&mut V> {
+        if (self.contains)(&self.inner, &k) { Some(&mut self.inner[k]) } else { None }
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: K) -> &mut V { &mut self.inner[k] }
+}
+
+/// An index into a sequence, where negative values count from the end:
+/// -1 is the last element, -2 the second to last, and so on.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, RelativeIndex};
+///
+/// let mut v = vec![1, 2, 3, 4];
+/// {
+///     let (last, first) = v.get2_mut(RelativeIndex(-1), RelativeIndex(0));
+///     std::mem::swap(last.unwrap(), first.unwrap());
+/// }
+/// assert_eq!(v, [4, 2, 3, 1]);
+/// ```
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, Debug)]
+pub struct RelativeIndex(pub isize);
+
+impl RelativeIndex {
+    /// Resolves the index against a sequence of length `len`.
+    pub fn resolve(self, len: usize) -> Option<usize> {
+        let i = if self.0 < 0 { len.checked_sub(self.0.unsigned_abs())? } else { self.0 as usize };
+        if i < len { Some(i) } else { None }
+    }
+}
+
+macro_rules! index_adapter {
+    ($key: ident, $($t: ty),+) => {$(
+        unsafe impl<V> SplitMutBase<$key, V> for $t {
+            #[inline]
+            fn value_range(&self) -> Option<(usize, usize)> { SplitMutBase::<usize, V>::value_range(self) }
+            #[inline]
+            fn get1_mut(&mut self, k: $key) -> Option<&mut V> { let i = k.resolve(self.len())?; self.get_mut(i) }
+            #[inline]
+            unsafe fn get1_unchecked_mut(&mut self, k: $key) -> &mut V {
+                let i = k.resolve(self.len());
+                self.get1_unchecked_mut(i.unwrap_or(usize::MAX))
+            }
+        }
+
+        impl<V> GetRef<$key, V> for $t {
+            #[inline]
+            fn get1_ref(&self, k: $key) -> Option<&V> { let i = k.resolve(self.len())?; self.get(i) }
+        }
+    )+}
+}
+
+/// An index into a sequence, taken modulo the length of the sequence, for
+/// ring buffer style algorithms. Indices that wrap around to the same element
+/// still give `Err(SplitMutError::SameValue)`.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, SplitMutError, WrappingIndex};
+///
+/// let mut v = vec![1, 2, 3];
+/// let head = 2;
+/// assert_eq!(v.get2_mut(WrappingIndex(head), WrappingIndex(head + 1)), (Ok(&mut 3), Ok(&mut 1)));
+/// assert_eq!(v.get2_mut(WrappingIndex(head), WrappingIndex(head + 3)), (Ok(&mut 3), Err(SplitMutError::SameValue)));
+/// ```
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, Debug)]
+pub struct WrappingIndex(pub usize);
+
+impl WrappingIndex {
+    /// Resolves the index against a sequence of length `len`.
+    /// Returns `None` only if the sequence is empty.
+    pub fn resolve(self, len: usize) -> Option<usize> { self.0.checked_rem(len) }
+}
+
+index_adapter!(RelativeIndex, &mut [V], Vec<V>, VecDeque<V>);
+index_adapter!(WrappingIndex, &mut [V], Vec<V>, VecDeque<V>);
+
+/// Newtype indices into sequences, so that e g a `Vec<Node>` can be split
+/// with `NodeId` keys instead of plain `usize`.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, TypedIndex};
+///
+/// #[derive(Clone, Copy)]
+/// struct NodeId(u32);
+/// impl TypedIndex for NodeId { fn to_index(self) -> usize { self.0 as usize } }
+///
+/// let mut nodes = vec!["a", "b", "c"];
+/// let (x, y) = nodes.get2_mut(NodeId(2), NodeId(0));
+/// assert_eq!((x, y), (Ok(&mut "c"), Ok(&mut "a")));
+/// ```
+pub trait TypedIndex {
+    /// The position in the sequence.
+    fn to_index(self) -> usize;
+}
+
+macro_rules! typed_index_adapter {
+    ($($t: ty),+) => {$(
+        unsafe impl<V, I: TypedIndex> SplitMutBase<I, V> for $t {
+            #[inline]
+            fn value_range(&self) -> Option<(usize, usize)> { SplitMutBase::<usize, V>::value_range(self) }
+            #[inline]
+            fn get1_mut(&mut self, k: I) -> Option<&mut V> { self.get_mut(k.to_index()) }
+            #[inline]
+            unsafe fn get1_unchecked_mut(&mut self, k: I) -> &mut V { SplitMutBase::<usize, V>::get1_unchecked_mut(self, k.to_index()) }
+        }
+
+        impl<V, I: TypedIndex> GetRef<I, V> for $t {
+            #[inline]
+            fn get1_ref(&self, k: I) -> Option<&V> { self.get(k.to_index()) }
+        }
+    )+}
+}
+
+typed_index_adapter!(&mut [V], Vec<V>, VecDeque<V>);
+
+/// Maps keys through a function before looking them up in the wrapped collection,
+/// so keys that map to the same canonical key refer to the same value.
+///
+/// The wrapped collection is given a reference to the canonical key, so this works
+/// with maps, e g a `HashMap<String, V>` with a function returning `String`. For
+/// sequences with newtype indices, implement `TypedIndex` instead.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use splitmut::{SplitMut, SplitMutError, KeyAdapter};
+///
+/// let h: HashMap<String, u32> = vec![("alice".into(), 1), ("bob".into(), 2)].into_iter().collect();
+/// let mut s = KeyAdapter::new(h, |k: &str| k.to_lowercase());
+/// assert_eq!(s.get2_mut("Alice", "BOB"), (Ok(&mut 1), Ok(&mut 2)));
+/// assert_eq!(s.get2_mut("Alice", "alice").1, Err(SplitMutError::SameValue));
+/// ```
+pub struct KeyAdapter<C, F> { inner: C, f: F }
+
+impl<C, F> KeyAdapter<C, F> {
+    /// Wraps a collection, with `f` giving the canonical key for every key.
+    ///
+    /// `f` should give the same canonical key every time it is called with the same key.
+    pub fn new(inner: C, f: F) -> Self { KeyAdapter { inner, f } }
+
+    /// Returns a shared reference to the wrapped collection.
+    pub fn get_ref(&self) -> &C { &self.inner }
+
+    /// Returns a mutable reference to the wrapped collection.
+    pub fn get_mut(&mut self) -> &mut C { &mut self.inner }
+
+    /// Returns the wrapped collection.
+    pub fn into_inner(self) -> C { self.inner }
+}
+
+unsafe impl<K, V, O, C: for<'q> SplitMutBase<&'q O, V>, F: Fn(K) -> O> SplitMutBase<K, V> for KeyAdapter<C, F> {
+    #[inline]
+    fn value_range(&self) -> Option<(usize, usize)> { SplitMutBase::<&O, V>::value_range(&self.inner) }
+    #[inline]
+    fn get1_mut(&mut self, k: K) -> Option<&mut V> { self.inner.get1_mut(&(self.f)(k)) }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: K) -> &mut V {
+        // Checked anyway, since nothing stops `f` from giving another key the second time.
+        self.get1_mut(k).expect("key has no value")
+    }
+}
+
+impl<K, V, O, C: for<'q> GetRef<&'q O, V>, F: Fn(K) -> O> GetRef<K, V> for KeyAdapter<C, F> {
+    #[inline]
+    fn get1_ref(&self, k: K) -> Option<&V> { self.inner.get1_ref(&(self.f)(k)) }
+}
+
+#[test]
+fn index_split() {
+    use {SplitMut, SplitMutError};
+    use std::collections::HashMap;
+    use std::ops::Index;
+    struct Sparse(HashMap<u32, u8>);
+    impl Index<u32> for Sparse { type Output = u8; fn index(&self, k: u32) -> &u8 { &self.0[&k] } }
+    impl IndexMut<u32> for Sparse { fn index_mut(&mut self, k: u32) -> &mut u8 { self.0.get_mut(&k).unwrap() } }
+
+    let mut h = HashMap::new();
+    h.insert(7, 1);
+    h.insert(9, 2);
+    let mut s = unsafe { IndexSplit::new(Sparse(h), |s: &Sparse, k: &u32| s.0.contains_key(k)) };
+    assert_eq!(s.get3_mut(9, 7, 9), (Ok(&mut 2), Ok(&mut 1), Err(SplitMutError::SameValue)));
+    assert_eq!(s.get2_mut(8, 7), (Err(SplitMutError::NoValue), Ok(&mut 1)));
+    assert_eq!(s.get_ref().0.len(), 2);
+}
+
+#[test]
+fn relative() {
+    use {SplitMut, SplitMutError};
+    let mut d: VecDeque<_> = (0..4).collect();
+    assert_eq!(d.get3_mut(RelativeIndex(-4), RelativeIndex(3), RelativeIndex(-1)), (Ok(&mut 0), Ok(&mut 3), Err(SplitMutError::SameValue)));
+    assert_eq!(d.get2_mut(RelativeIndex(-5), RelativeIndex(4)), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+    assert_eq!(RelativeIndex(isize::MIN).resolve(3), None);
+    assert_eq!(RelativeIndex(-1).resolve(0), None);
+    let v = vec![0u16; 3];
+    assert_eq!(SplitMutBase::<RelativeIndex, u16>::value_range(&v), SplitMutBase::<usize, u16>::value_range(&v));
+}
+
+#[test]
+fn wrapping() {
+    use {SplitMut, SplitMutError};
+    let mut d: VecDeque<u8> = VecDeque::new();
+    assert_eq!(d.get2_mut(WrappingIndex(0), WrappingIndex(1)), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+    d.extend(0..5);
+    assert_eq!(d.get3_mut(WrappingIndex(4), WrappingIndex(5), WrappingIndex(14)), (Ok(&mut 4), Ok(&mut 0), Err(SplitMutError::SameValue)));
+    assert_eq!(SplitMutBase::<WrappingIndex, u8>::value_range(&d), SplitMutBase::<usize, u8>::value_range(&d));
+    assert!(SplitMutBase::<WrappingIndex, u8>::value_range(&d).is_some());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn typed_index() {
+    use {SplitMut, SplitMutError};
+    #[derive(Clone, Copy, Debug)]
+    struct Id(u8);
+    impl TypedIndex for Id { fn to_index(self) -> usize { self.0 as usize } }
+    let mut d: VecDeque<_> = (0..4).collect();
+    assert_eq!(d.get3_mut(Id(3), Id(1), Id(3)), (Ok(&mut 3), Ok(&mut 1), Err(SplitMutError::SameValue)));
+    assert_eq!(d.get1_ref(Id(4)), None);
+    let mut v = vec![1, 2, 3];
+    assert_eq!(v.get_mut_iter(vec![Id(0), Id(2)]).count(), 2);
+    ::testing::assert_splitmut_sound(&mut v, &[Id(0), Id(2)], &[Id(3)]);
+}
+
+#[test]
+fn key_adapter() {
+    use {SplitMut, SplitMutError};
+    use std::collections::BTreeMap;
+    let m: BTreeMap<u32, &str> = vec![(1, "a"), (2, "b")].into_iter().collect();
+    let mut s = KeyAdapter::new(m, |k: i64| k.unsigned_abs() as u32);
+    assert_eq!(s.get3_mut(-2, 1, 2), (Ok(&mut "b"), Ok(&mut "a"), Err(SplitMutError::SameValue)));
+    assert_eq!(s.get1_ref(-3), None);
+    s.get_mut().insert(3, "c");
+    ::testing::assert_splitmut_sound(&mut s, &[-1, 2, 3], &[0, 4]);
+    assert_eq!(s.into_inner().len(), 3);
+}
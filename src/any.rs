@@ -0,0 +1,94 @@
+//! Splitting collections of `Box<dyn Any>` values into values of concrete types.
+
+use core::any::Any;
+use alloc::boxed::Box;
+use {SplitMut, SplitMutError, err};
+
+mod sealed {
+    use core::any::Any;
+
+    pub trait Downcast {
+        fn downcast_mut<T: Any>(&mut self) -> Option<&mut T>;
+    }
+
+    impl Downcast for dyn Any {
+        fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> { self.downcast_mut() }
+    }
+
+    impl Downcast for dyn Any + Send {
+        fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> { self.downcast_mut() }
+    }
+
+    impl Downcast for dyn Any + Send + Sync {
+        fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> { self.downcast_mut() }
+    }
+}
+
+#[inline]
+fn downcast<A: Any, T: ?Sized + sealed::Downcast + 'static>(r: Result<&mut Box<T>, SplitMutError>) -> Result<&mut A, SplitMutError> {
+    (**r?).downcast_mut().ok_or_else(|| err(SplitMutError::WrongType))
+}
+
+/// Just add `use splitmut::SplitMutAny;` to have these methods working on
+/// all `SplitMut` collections with `Box<dyn Any>` values (optionally `+ Send` or `+ Send + Sync`).
+///
+/// # Example
+/// ```
+/// use std::any::Any;
+/// use std::collections::HashMap;
+/// use splitmut::{SplitMutAny, SplitMutError};
+///
+/// let mut h: HashMap<&str, Box<dyn Any>> = HashMap::new();
+/// h.insert("count", Box::new(5u32));
+/// h.insert("name", Box::new(String::from("five")));
+/// {
+///     let (count, name) = h.get2_downcast_mut::<u32, String>(&"count", &"name");
+///     *count.unwrap() += 1;
+///     name.unwrap().push('!');
+/// }
+/// let (c, n) = h.get2_downcast_mut::<String, String>(&"count", &"name");
+/// assert_eq!(c, Err(SplitMutError::WrongType));
+/// assert_eq!(n.unwrap(), "five!");
+/// ```
+pub trait SplitMutAny<K, T: ?Sized> {
+    /// Returns two mutable references to two distinct values within
+    /// the same collection, downcast to `A` and `B` respectively.
+    ///
+    /// Returns `Err(SplitMutError::WrongType)` for a value of another type.
+    fn get2_downcast_mut<A: Any, B: Any>(&mut self, k1: K, k2: K) -> (Result<&mut A, SplitMutError>, Result<&mut B, SplitMutError>);
+
+    /// Returns three mutable references to three distinct values within
+    /// the same collection, downcast to `A`, `B` and `C` respectively.
+    ///
+    /// Returns `Err(SplitMutError::WrongType)` for a value of another type.
+    fn get3_downcast_mut<A: Any, B: Any, C: Any>(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut A, SplitMutError>,
+        Result<&mut B, SplitMutError>, Result<&mut C, SplitMutError>);
+}
+
+impl<K, T: ?Sized + sealed::Downcast + 'static, S: SplitMut<K, Box<T>> + ?Sized> SplitMutAny<K, T> for S {
+    fn get2_downcast_mut<A: Any, B: Any>(&mut self, k1: K, k2: K) -> (Result<&mut A, SplitMutError>, Result<&mut B, SplitMutError>) {
+        trace_method!("get2_downcast_mut");
+        let (a, b) = self.get2_mut(k1, k2);
+        (downcast(a), downcast(b))
+    }
+
+    fn get3_downcast_mut<A: Any, B: Any, C: Any>(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut A, SplitMutError>,
+        Result<&mut B, SplitMutError>, Result<&mut C, SplitMutError>) {
+        trace_method!("get3_downcast_mut");
+        let (a, b, c) = self.get3_mut(k1, k2, k3);
+        (downcast(a), downcast(b), downcast(c))
+    }
+}
+
+#[test]
+fn downcast_vec() {
+    use alloc::vec::Vec;
+    let mut v: Vec<Box<dyn Any + Send>> = vec![Box::new(1u8), Box::new("two"), Box::new(3.0f32)];
+    let (a, b, c) = v.get3_downcast_mut::<u8, &str, u8>(0, 1, 2);
+    assert_eq!(a, Ok(&mut 1));
+    assert_eq!(b, Ok(&mut "two"));
+    assert_eq!(c, Err(SplitMutError::WrongType));
+    let (a, b) = v.get2_downcast_mut::<u8, u8>(0, 0);
+    assert_eq!((a, b), (Ok(&mut 1), Err(SplitMutError::SameValue)));
+    assert_eq!(v.get2_downcast_mut::<u8, u8>(3, 0).0, Err(SplitMutError::NoValue));
+}
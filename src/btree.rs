@@ -0,0 +1,398 @@
+//! Splitting helpers that make use of the ordering of a BTreeMap.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::ops::{Bound, Index, IndexMut};
+use {SplitMutBase, GetRef, SplitMutError, err};
+
+/// Just add `use splitmut::SplitMutBTree;` to have these methods working on BTreeMap.
+pub trait SplitMutBTree<K, V> {
+    /// Returns the entries with the smallest and largest keys, both with
+    /// mutable values.
+    ///
+    /// For an empty map, both are `Err(SplitMutError::NoValue)`. For a map
+    /// with a single entry, the second one is `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use splitmut::SplitMutBTree;
+    ///
+    /// let mut h: BTreeMap<_, _> = vec![(3, 'c'), (1, 'a'), (2, 'b')].into_iter().collect();
+    /// {
+    ///     let (min, max) = h.first_last_mut();
+    ///     std::mem::swap(min.unwrap().1, max.unwrap().1);
+    /// }
+    /// assert_eq!(h[&1], 'c');
+    /// ```
+    fn first_last_mut(&mut self) -> (Result<(&K, &mut V), SplitMutError>, Result<(&K, &mut V), SplitMutError>);
+
+    /// Returns two cursors into the map, which can be moved independently
+    /// and give mutable access to the values they point to at the same time.
+    ///
+    /// The cursors are keys, not `std::collections::btree_map::CursorMut`, which is
+    /// unstable: every move looks up the next key in the map, in O(log n) time.
+    ///
+    /// Each cursor starts at the first entry within its bound, i e, `Included(k)` starts
+    /// at the first key >= k, `Excluded(k)` at the first key > k and `Unbounded` at the first key.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound;
+    /// use splitmut::{SplitMutError, SplitMutBTree};
+    ///
+    /// let mut h: BTreeMap<_, _> = vec![(1, 10), (2, 20), (3, 30)].into_iter().collect();
+    /// let mut c = h.key_cursors_mut(Bound::Unbounded, Bound::Excluded(&1));
+    /// // Merge each bucket into the next one
+    /// while let (Ok((_, a)), Ok((_, b))) = c.get_mut() {
+    ///     *b += std::mem::replace(a, 0);
+    ///     c.move_next(0);
+    ///     c.move_next(1);
+    /// }
+    /// assert_eq!(c.get_mut(), (Ok((&3, &mut 60)), Err(SplitMutError::NoValue)));
+    /// ```
+    fn key_cursors_mut(&mut self, first: Bound<&K>, second: Bound<&K>) -> BTreeKeyCursors<'_, K, V> where K: Clone;
+
+    /// Returns mutable views of the entries whose keys start with `a` and `b`
+    /// respectively, for maps with string keys.
+    ///
+    /// Returns `Err(SplitMutError::SameValue)` if one prefix starts with the other.
+    /// Entries between the two prefixes are not visited.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use splitmut::{SplitMut, SplitMutBTree, SplitMutError};
+    ///
+    /// let mut m = BTreeMap::new();
+    /// for k in ["groups/admin", "users/ann", "users/bob", "x"] { m.insert(k.to_string(), 0); }
+    /// {
+    ///     let (mut users, mut groups) = m.split_prefix2_mut("users/", "groups/").unwrap();
+    ///     assert_eq!(users.len(), 2);
+    ///     let (ann, admin) = (users.get_muts().at("users/ann").unwrap(), groups.get_muts().at("groups/admin").unwrap());
+    ///     *admin += 1;
+    ///     *ann = *admin;
+    /// }
+    /// assert_eq!(m["users/ann"], 1);
+    /// assert_eq!(m.split_prefix2_mut("users/", "users/a").err(), Some(SplitMutError::SameValue));
+    /// ```
+    fn split_prefix2_mut(&mut self, a: &str, b: &str) -> Result<(BTreePrefixMut<'_, K, V>, BTreePrefixMut<'_, K, V>), SplitMutError>
+    where K: Borrow<str>;
+
+    /// Returns the entries with the largest keys at or below `a` and `b`
+    /// respectively, both with mutable values.
+    ///
+    /// A query without such a key gives `Err(SplitMutError::NoValue)`, and if both queries
+    /// resolve to the same entry, the second one is `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use splitmut::{SplitMutBTree, SplitMutError};
+    ///
+    /// // Balances by the time they were recorded
+    /// let mut h: BTreeMap<u32, i32> = vec![(100, 5), (200, 8), (300, 1)].into_iter().collect();
+    /// {
+    ///     let (a, b) = h.get2_floor_mut(&250, &120);
+    ///     let (a, b) = (a.unwrap(), b.unwrap());
+    ///     assert_eq!((a.0, b.0), (&200, &100));
+    ///     *a.1 -= 3;
+    ///     *b.1 += 3;
+    /// }
+    /// assert_eq!(h[&100], 8);
+    /// assert_eq!(h.get2_floor_mut(&99, &300).0, Err(SplitMutError::NoValue));
+    /// assert_eq!(h.get2_floor_mut(&300, &301).1, Err(SplitMutError::SameValue));
+    /// ```
+    fn get2_floor_mut<Q: Ord + ?Sized>(&mut self, a: &Q, b: &Q) -> (Result<(&K, &mut V), SplitMutError>, Result<(&K, &mut V), SplitMutError>)
+    where K: Borrow<Q>;
+
+    /// Like `get2_floor_mut`, but returns the entries with the smallest keys at or above
+    /// `a` and `b` respectively.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use splitmut::{SplitMutBTree, SplitMutError};
+    ///
+    /// let mut h: BTreeMap<u32, char> = vec![(10, 'a'), (20, 'b')].into_iter().collect();
+    /// assert_eq!(h.get2_ceiling_mut(&0, &11), (Ok((&10, &mut 'a')), Ok((&20, &mut 'b'))));
+    /// assert_eq!(h.get2_ceiling_mut(&12, &20).1, Err(SplitMutError::SameValue));
+    /// assert_eq!(h.get2_ceiling_mut(&21, &5).0, Err(SplitMutError::NoValue));
+    /// ```
+    fn get2_ceiling_mut<Q: Ord + ?Sized>(&mut self, a: &Q, b: &Q) -> (Result<(&K, &mut V), SplitMutError>, Result<(&K, &mut V), SplitMutError>)
+    where K: Borrow<Q>;
+}
+
+// The entry with the largest key <= k (or the smallest key >= k, if not `floor`).
+fn nearest<K: Ord + Borrow<Q>, Q: Ord + ?Sized, V>(m: &mut BTreeMap<K, V>, k: &Q, floor: bool) -> Result<(*const K, *mut V), SplitMutError> {
+    let e = if floor { m.range_mut::<Q, _>((Bound::Unbounded, Bound::Included(k))).next_back() }
+        else { m.range_mut::<Q, _>((Bound::Included(k), Bound::Unbounded)).next() };
+    e.map(|(k, v)| (k as *const K, v as *mut V)).ok_or_else(|| err(SplitMutError::NoValue))
+}
+
+fn nearest2<'a, K: Ord + Borrow<Q>, Q: Ord + ?Sized, V>(m: &'a mut BTreeMap<K, V>, a: &Q, b: &Q, floor: bool)
+    -> (Result<(&'a K, &'a mut V), SplitMutError>, Result<(&'a K, &'a mut V), SplitMutError>) {
+    let p1 = nearest(m, a, floor);
+    let p2 = nearest(m, b, floor);
+    // Compare keys rather than values, since values can be zero-sized.
+    let p2 = match (&p1, p2) {
+        (Ok(x), Ok(y)) if x.0 == y.0 => Err(err(SplitMutError::SameValue)),
+        (_, y) => y,
+    };
+    // The map is not modified by the lookups, so the entries stay put, and they are distinct.
+    unsafe { (p1.map(|(k, v)| (&*k, &mut *v)), p2.map(|(k, v)| (&*k, &mut *v))) }
+}
+
+fn entry<'a, K: Ord, V>(m: &'a mut BTreeMap<K, V>, k: &K) -> Result<(&'a K, &'a mut V), SplitMutError> {
+    m.range_mut((Bound::Included(k), Bound::Included(k))).next().ok_or_else(|| err(SplitMutError::NoValue))
+}
+
+/// The smallest string greater than every string starting with `p`, if there is one.
+fn prefix_end(p: &str) -> Option<String> {
+    let mut s = String::from(p);
+    while let Some(c) = s.pop() {
+        let next = (c as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+        if let Some(n) = next { s.push(n); return Some(s) }
+    }
+    None
+}
+
+/// Mutable view of the entries of a BTreeMap with keys starting with a prefix,
+/// returned from the split_prefix2_mut function.
+///
+/// The view implements `SplitMut` with string keys.
+pub struct BTreePrefixMut<'a, K: 'a, V: 'a>(Vec<(&'a K, &'a mut V)>);
+
+impl<'a, K: Borrow<str>, V> BTreePrefixMut<'a, K, V> {
+    /// Number of entries in the view.
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Returns true if the view has no entries.
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Iterates over the entries, in key order, with mutable values.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(&K, &mut V)> + use<'_, 'a, K, V> { self.0.iter_mut().map(|e| (e.0, &mut *e.1)) }
+
+    fn find(&self, k: &str) -> Option<usize> { self.0.binary_search_by(|e| e.0.borrow().cmp(k)).ok() }
+}
+
+unsafe impl<'a, 'b, K: Borrow<str>, V> SplitMutBase<&'b str, V> for BTreePrefixMut<'a, K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: &'b str) -> Option<&mut V> {
+        let i = self.find(k)?;
+        Some(&mut *self.0[i].1)
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: &'b str) -> &mut V { core::mem::transmute(self.get1_mut(k)) }
+}
+
+impl<'a, 'b, K: Borrow<str>, V> GetRef<&'b str, V> for BTreePrefixMut<'a, K, V> {
+    #[inline]
+    fn get1_ref(&self, k: &'b str) -> Option<&V> { self.find(k).map(|i| &*self.0[i].1) }
+}
+
+impl<'a, 'b, K: Borrow<str>, V> Index<&'b str> for BTreePrefixMut<'a, K, V> {
+    type Output = V;
+    fn index(&self, k: &'b str) -> &V { self.get1_ref(k).expect("no value for key") }
+}
+
+impl<'a, 'b, K: Borrow<str>, V> IndexMut<&'b str> for BTreePrefixMut<'a, K, V> {
+    fn index_mut(&mut self, k: &'b str) -> &mut V { self.get1_mut(k).expect("no value for key") }
+}
+
+/// Two cursors into a `BTreeMap`, created by `SplitMutBTree::key_cursors_mut`.
+///
+/// Each cursor is a clone of the key it points to, and is moved by looking up
+/// the next or previous key.
+///
+/// Cursor 0 and 1 each point to an entry, or to nothing, if they have been moved
+/// past either end of the map. If both point to the same entry, the second
+/// one gives `Err(SplitMutError::SameValue)`.
+#[derive(Debug)]
+pub struct BTreeKeyCursors<'a, K: 'a, V: 'a> {
+    map: &'a mut BTreeMap<K, V>,
+    pos: [Option<K>; 2],
+}
+
+impl<'a, K: Ord + Clone, V> BTreeKeyCursors<'a, K, V> {
+    /// The key that the cursor points to.
+    ///
+    /// # Panics
+    /// If the cursor is not 0 or 1.
+    pub fn key(&self, cursor: usize) -> Option<&K> { self.pos[cursor].as_ref() }
+
+    /// Moves the cursor to the next entry. Returns false, and points to nothing,
+    /// if there is no next entry. A cursor that points to nothing stays there.
+    ///
+    /// # Panics
+    /// If the cursor is not 0 or 1.
+    pub fn move_next(&mut self, cursor: usize) -> bool {
+        let n = self.pos[cursor].take().and_then(|k| self.map.range((Bound::Excluded(k), Bound::Unbounded)).next().map(|e| e.0.clone()));
+        self.pos[cursor] = n;
+        self.pos[cursor].is_some()
+    }
+
+    /// Moves the cursor to the previous entry. Returns false, and points to nothing,
+    /// if there is no previous entry. A cursor that points to nothing stays there.
+    ///
+    /// # Panics
+    /// If the cursor is not 0 or 1.
+    pub fn move_prev(&mut self, cursor: usize) -> bool {
+        let n = self.pos[cursor].take().and_then(|k| self.map.range((Bound::Unbounded, Bound::Excluded(k))).next_back().map(|e| e.0.clone()));
+        self.pos[cursor] = n;
+        self.pos[cursor].is_some()
+    }
+
+    /// The entries both cursors point to. A cursor pointing to nothing gives
+    /// `Err(SplitMutError::NoValue)`.
+    pub fn get_mut(&mut self) -> (Result<(&K, &mut V), SplitMutError>, Result<(&K, &mut V), SplitMutError>) {
+        trace_method!("get_mut");
+        let no = || err(SplitMutError::NoValue);
+        // Compare keys rather than values, since values can be zero-sized.
+        match (self.pos[0].as_ref(), self.pos[1].as_ref()) {
+            (Some(k0), Some(k1)) => match k0.cmp(k1) {
+                Ordering::Equal => (entry(self.map, k0), Err(err(SplitMutError::SameValue))),
+                o => {
+                    let (lo, hi) = if o == Ordering::Less { (k0, k1) } else { (k1, k0) };
+                    let mut r = self.map.range_mut((Bound::Included(lo), Bound::Included(hi)));
+                    let (a, b) = (r.next().ok_or_else(no), r.next_back().ok_or_else(no));
+                    if o == Ordering::Less { (a, b) } else { (b, a) }
+                }
+            },
+            (Some(k0), None) => (entry(self.map, k0), Err(no())),
+            (None, Some(k1)) => (Err(no()), entry(self.map, k1)),
+            (None, None) => (Err(no()), Err(no())),
+        }
+    }
+}
+
+impl<K: Ord, V> SplitMutBTree<K, V> for BTreeMap<K, V> {
+    fn first_last_mut(&mut self) -> (Result<(&K, &mut V), SplitMutError>, Result<(&K, &mut V), SplitMutError>) {
+        trace_method!("first_last_mut");
+        let mut i = self.iter_mut();
+        match i.next() {
+            None => (Err(err(SplitMutError::NoValue)), Err(err(SplitMutError::NoValue))),
+            Some(first) => (Ok(first), i.next_back().ok_or_else(|| err(SplitMutError::SameValue))),
+        }
+    }
+
+    fn key_cursors_mut(&mut self, first: Bound<&K>, second: Bound<&K>) -> BTreeKeyCursors<'_, K, V> where K: Clone {
+        let start = |b: Bound<&K>| self.range((b, Bound::Unbounded)).next().map(|e| e.0.clone());
+        let pos = [start(first), start(second)];
+        BTreeKeyCursors { map: self, pos }
+    }
+
+    fn split_prefix2_mut(&mut self, a: &str, b: &str) -> Result<(BTreePrefixMut<'_, K, V>, BTreePrefixMut<'_, K, V>), SplitMutError>
+    where K: Borrow<str> {
+        trace_method!("split_prefix2_mut");
+        if a.starts_with(b) || b.starts_with(a) { return Err(err(SplitMutError::SameValue)) }
+        let swap = a > b;
+        let (lo, hi) = if swap { (b, a) } else { (a, b) };
+        let end = prefix_end(hi);
+        let end = end.as_ref().map_or(Bound::Unbounded, |e| Bound::Excluded(&**e));
+        // Keys with each prefix are contiguous, one block at each end of the range.
+        let mut i = self.range_mut::<str, _>((Bound::Included(lo), end));
+        let mut vlo = Vec::new();
+        let mut first_hi = None;
+        for e in i.by_ref() {
+            if e.0.borrow().starts_with(lo) { vlo.push(e) }
+            else { first_hi = Some(e).filter(|e| e.0.borrow().starts_with(hi)); break }
+        }
+        let mut vhi = Vec::new();
+        while let Some(e) = i.next_back() {
+            if !e.0.borrow().starts_with(hi) { break }
+            vhi.push(e);
+        }
+        vhi.extend(first_hi);
+        vhi.reverse();
+        let (vlo, vhi) = (BTreePrefixMut(vlo), BTreePrefixMut(vhi));
+        Ok(if swap { (vhi, vlo) } else { (vlo, vhi) })
+    }
+
+    fn get2_floor_mut<Q: Ord + ?Sized>(&mut self, a: &Q, b: &Q) -> (Result<(&K, &mut V), SplitMutError>, Result<(&K, &mut V), SplitMutError>)
+    where K: Borrow<Q> {
+        trace_method!("get2_floor_mut");
+        nearest2(self, a, b, true)
+    }
+
+    fn get2_ceiling_mut<Q: Ord + ?Sized>(&mut self, a: &Q, b: &Q) -> (Result<(&K, &mut V), SplitMutError>, Result<(&K, &mut V), SplitMutError>)
+    where K: Borrow<Q> {
+        trace_method!("get2_ceiling_mut");
+        nearest2(self, a, b, false)
+    }
+}
+
+#[test]
+fn first_last() {
+    let mut h = BTreeMap::new();
+    assert_eq!(h.first_last_mut(), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+    h.insert(5, 50);
+    assert_eq!(h.first_last_mut(), (Ok((&5, &mut 50)), Err(SplitMutError::SameValue)));
+    h.insert(9, 90);
+    h.insert(7, 70);
+    assert_eq!(h.first_last_mut(), (Ok((&5, &mut 50)), Ok((&9, &mut 90))));
+}
+
+#[test]
+fn key_cursors() {
+    let mut h: BTreeMap<_, _> = (1..5).map(|i| (i, i * 10)).collect();
+    let mut c = h.key_cursors_mut(Bound::Included(&2), Bound::Excluded(&3));
+    assert_eq!(c.get_mut(), (Ok((&2, &mut 20)), Ok((&4, &mut 40))));
+    assert!(c.move_prev(1));
+    assert!(c.move_next(0));
+    assert_eq!(c.key(0), Some(&3));
+    assert_eq!(c.get_mut(), (Ok((&3, &mut 30)), Err(SplitMutError::SameValue)));
+    assert!(c.move_next(1));
+    assert!(!c.move_next(1));
+    assert_eq!(c.get_mut().1, Err(SplitMutError::NoValue));
+    assert!(!c.move_prev(1));
+    assert_eq!(c.key(1), None);
+    let mut z: BTreeMap<_, ()> = (1..4).map(|i| (i, ())).collect();
+    let mut c = z.key_cursors_mut(Bound::Excluded(&1), Bound::Unbounded);
+    assert_eq!(c.get_mut(), (Ok((&2, &mut ())), Ok((&1, &mut ()))));
+    c.move_next(1);
+    assert_eq!(c.get_mut(), (Ok((&2, &mut ())), Err(SplitMutError::SameValue)));
+}
+
+#[test]
+fn prefix_split() {
+    use alloc::string::ToString;
+    use SplitMut;
+    let mut m: BTreeMap<String, u32> = BTreeMap::new();
+    for (i, k) in ["a", "b/1", "b/2", "b0", "c", "c\u{10FFFF}", "c\u{10FFFF}\u{10FFFF}x", "d"].iter().enumerate() { m.insert(k.to_string(), i as u32); }
+    {
+        let (mut c, mut b) = m.split_prefix2_mut("c\u{10FFFF}", "b/").unwrap();
+        assert_eq!(c.iter_mut().map(|e| *e.1).collect::<Vec<_>>(), [5, 6]);
+        assert_eq!(b.iter_mut().map(|e| *e.1).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(b.get2_mut("b/2", "b0"), (Ok(&mut 2), Err(SplitMutError::NoValue)));
+        assert_eq!(b.get1_ref("b/1"), Some(&1));
+        b["b/1"] += c["c\u{10FFFF}"];
+        assert_eq!(b["b/1"], 6);
+    }
+    let (x, d) = m.split_prefix2_mut("x", "d").unwrap();
+    assert!(x.is_empty());
+    assert_eq!(d.len(), 1);
+    assert_eq!(m.split_prefix2_mut("", "a").err(), Some(SplitMutError::SameValue));
+    assert_eq!(prefix_end("a\u{10FFFF}"), Some("b".into()));
+    assert_eq!(prefix_end("\u{D7FF}"), Some("\u{E000}".into()));
+    assert_eq!(prefix_end("\u{10FFFF}"), None);
+}
+
+#[test]
+fn floor_ceiling() {
+    use alloc::string::ToString;
+    let mut h: BTreeMap<String, ()> = ["b", "d", "f"].iter().map(|k| (k.to_string(), ())).collect();
+    let (a, b) = h.get2_floor_mut("c", "z");
+    assert_eq!((a.unwrap().0.as_str(), b.unwrap().0.as_str()), ("b", "f"));
+    let (a, b) = h.get2_ceiling_mut("c", "d");
+    assert_eq!(a.unwrap().0, "d");
+    assert_eq!(b, Err(SplitMutError::SameValue));
+    assert_eq!(h.get2_floor_mut("a", "a"), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+    assert_eq!(h.get2_ceiling_mut("g", "f").1.map(|e| e.0.len()), Ok(1));
+    let mut e: BTreeMap<u8, u8> = BTreeMap::new();
+    assert_eq!(e.get2_ceiling_mut(&0, &0).0, Err(SplitMutError::NoValue));
+}
@@ -0,0 +1,147 @@
+//! Token-based splitting for collections of `QCell` values.
+//!
+//! Instead of tracking returned pointers, access is granted through a
+//! `QCellOwner`, so the collection itself only needs to be borrowed immutably.
+
+use qcell::{QCell, QCellOwner};
+use std::collections::{HashMap, BTreeMap, VecDeque};
+use std::{hash, borrow};
+use std::vec::Vec;
+use {SplitMutError, err};
+
+/// Just add `use splitmut::SplitCell;` to have these methods working on
+/// slices, Vec, VecDeque, HashMap and BTreeMap containing `QCell<V>` values.
+///
+/// In case you want to implement `SplitCell` for your own collection, just
+/// implement `get_cell`. Unlike `SplitMutBase`, this trait is safe to implement,
+/// because `QCellOwner` enforces exclusive access to the cells.
+///
+/// # Example
+/// ```
+/// extern crate qcell;
+/// extern crate splitmut;
+///
+/// use qcell::{QCell, QCellOwner};
+/// use splitmut::{SplitCell, SplitMutError};
+///
+/// # fn main() {
+/// let mut owner = QCellOwner::new();
+/// let v = vec![QCell::new(&owner, 1), QCell::new(&owner, 2)];
+/// {
+///     let (a, b) = v.rw2(&mut owner, 0, 1);
+///     std::mem::swap(a.unwrap(), b.unwrap());
+/// }
+/// assert_eq!(*v[0].ro(&owner), 2);
+/// assert_eq!(v.rw2(&mut owner, 1, 1), (Ok(&mut 1), Err(SplitMutError::SameValue)));
+/// # }
+/// ```
+pub trait SplitCell<K, V> {
+    /// Looks up the cell for a key, used internally.
+    fn get_cell(&self, k: K) -> Option<&QCell<V>>;
+
+    /// Returns two mutable references to two distinct values within
+    /// the same collection, using `owner` to grant access.
+    ///
+    /// # Panics
+    /// Panics if a cell does not belong to `owner`.
+    fn rw2<'a>(&'a self, owner: &'a mut QCellOwner, k1: K, k2: K) -> (Result<&'a mut V, SplitMutError>, Result<&'a mut V, SplitMutError>) {
+        trace_method!("rw2");
+        match (self.get_cell(k1), self.get_cell(k2)) {
+            (Some(c1), Some(c2)) => if std::ptr::eq(c1, c2) {
+                (Ok(owner.rw(c1)), Err(err(SplitMutError::SameValue)))
+            } else {
+                let (v1, v2) = owner.rw2(c1, c2);
+                (Ok(v1), Ok(v2))
+            },
+            (Some(c1), None) => (Ok(owner.rw(c1)), Err(err(SplitMutError::NoValue))),
+            (None, Some(c2)) => (Err(err(SplitMutError::NoValue)), Ok(owner.rw(c2))),
+            (None, None) => (Err(err(SplitMutError::NoValue)), Err(err(SplitMutError::NoValue))),
+        }
+    }
+
+    /// Returns three mutable references to three distinct values within
+    /// the same collection, using `owner` to grant access.
+    ///
+    /// # Panics
+    /// Panics if a cell does not belong to `owner`.
+    fn rw3<'a>(&'a self, owner: &'a mut QCellOwner, k1: K, k2: K, k3: K) -> (Result<&'a mut V, SplitMutError>,
+        Result<&'a mut V, SplitMutError>, Result<&'a mut V, SplitMutError>) {
+        trace_method!("rw3");
+        let c1 = self.get_cell(k1).ok_or_else(|| err(SplitMutError::NoValue));
+        let c2 = self.get_cell(k2).ok_or_else(|| err(SplitMutError::NoValue));
+        let c3 = self.get_cell(k3).ok_or_else(|| err(SplitMutError::NoValue));
+        let c2 = check_c(&c1, c2);
+        let c3 = check_c(&c1, c3);
+        let c3 = check_c(&c2, c3);
+        match (c1, c2, c3) {
+            (Ok(c1), Ok(c2), Ok(c3)) => {
+                let (v1, v2, v3) = owner.rw3(c1, c2, c3);
+                (Ok(v1), Ok(v2), Ok(v3))
+            },
+            (Ok(c1), Ok(c2), Err(e3)) => { let (v1, v2) = owner.rw2(c1, c2); (Ok(v1), Ok(v2), Err(e3)) },
+            (Ok(c1), Err(e2), Ok(c3)) => { let (v1, v3) = owner.rw2(c1, c3); (Ok(v1), Err(e2), Ok(v3)) },
+            (Err(e1), Ok(c2), Ok(c3)) => { let (v2, v3) = owner.rw2(c2, c3); (Err(e1), Ok(v2), Ok(v3)) },
+            (Ok(c1), Err(e2), Err(e3)) => (Ok(owner.rw(c1)), Err(e2), Err(e3)),
+            (Err(e1), Ok(c2), Err(e3)) => (Err(e1), Ok(owner.rw(c2)), Err(e3)),
+            (Err(e1), Err(e2), Ok(c3)) => (Err(e1), Err(e2), Ok(owner.rw(c3))),
+            (Err(e1), Err(e2), Err(e3)) => (Err(e1), Err(e2), Err(e3)),
+        }
+    }
+}
+
+#[inline]
+fn check_c<'a, V>(a: &Result<&'a QCell<V>, SplitMutError>, b: Result<&'a QCell<V>, SplitMutError>) -> Result<&'a QCell<V>, SplitMutError> {
+    if let (Ok(aa), Ok(bb)) = (a, &b) {
+        if std::ptr::eq(*aa, *bb) { return Err(err(SplitMutError::SameValue)) }
+    }
+    b
+}
+
+impl<V> SplitCell<usize, V> for [QCell<V>] {
+    #[inline]
+    fn get_cell(&self, k: usize) -> Option<&QCell<V>> { self.get(k) }
+}
+
+impl<V> SplitCell<usize, V> for Vec<QCell<V>> {
+    #[inline]
+    fn get_cell(&self, k: usize) -> Option<&QCell<V>> { self.get(k) }
+}
+
+impl<V> SplitCell<usize, V> for VecDeque<QCell<V>> {
+    #[inline]
+    fn get_cell(&self, k: usize) -> Option<&QCell<V>> { self.get(k) }
+}
+
+impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> SplitCell<&'a Q, V> for HashMap<K, QCell<V>, S> {
+    #[inline]
+    fn get_cell(&self, k: &'a Q) -> Option<&QCell<V>> { self.get(k) }
+}
+
+impl<'a, K: Ord + borrow::Borrow<Q>, Q: Ord + ?Sized, V> SplitCell<&'a Q, V> for BTreeMap<K, QCell<V>> {
+    #[inline]
+    fn get_cell(&self, k: &'a Q) -> Option<&QCell<V>> { self.get(k) }
+}
+
+#[test]
+fn cell_hash() {
+    let mut owner = QCellOwner::new();
+    let mut h = HashMap::new();
+    h.insert(1u8, QCell::new(&owner, 5u16));
+    h.insert(2u8, QCell::new(&owner, 9u16));
+    assert_eq!(h.rw2(&mut owner, &1, &2), (Ok(&mut 5), Ok(&mut 9)));
+    assert_eq!(h.rw2(&mut owner, &3, &2), (Err(SplitMutError::NoValue), Ok(&mut 9)));
+    assert_eq!(h.rw3(&mut owner, &1, &3, &1), (Ok(&mut 5), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue)));
+}
+
+#[test]
+fn cell_vec() {
+    let mut owner = QCellOwner::new();
+    let v: Vec<_> = (0..3).map(|i| QCell::new(&owner, i)).collect();
+    {
+        let (a, b, c) = v.rw3(&mut owner, 2, 0, 1);
+        std::mem::swap(a.unwrap(), b.unwrap());
+        *c.unwrap() = 7;
+    }
+    assert_eq!(v.iter().map(|c| *c.ro(&owner)).collect::<Vec<_>>(), [2, 7, 0]);
+    assert_eq!(v.rw3(&mut owner, 1, 1, 1), (Ok(&mut 7), Err(SplitMutError::SameValue), Err(SplitMutError::SameValue)));
+}
@@ -0,0 +1,80 @@
+//! Tuple getters for slices that can be called in const contexts.
+
+use SplitMutError;
+
+// Checks key `n` against the slice length and the keys before it.
+const fn check(len: usize, keys: &[usize], n: usize) -> Result<usize, SplitMutError> {
+    let k = keys[n];
+    if k >= len { return Err(SplitMutError::NoValue) }
+    let mut i = 0;
+    while i < n {
+        if keys[i] == k { return Err(SplitMutError::SameValue) }
+        i += 1;
+    }
+    Ok(k)
+}
+
+const fn at<'a, V>(p: *mut V, r: Result<usize, SplitMutError>) -> Result<&'a mut V, SplitMutError> {
+    match r {
+        // The index is in range and distinct from the ones returned before it.
+        Ok(i) => Ok(unsafe { &mut *p.add(i) }),
+        Err(e) => Err(e),
+    }
+}
+
+macro_rules! const_getter {
+    ($(#[$attr: meta])* $fname: ident, $($n: tt $k: ident),+) => {
+        $(#[$attr])*
+        pub const fn $fname<V>(s: &mut [V], $($k: usize),+) -> ($(const_getter!(@r $k, V)),+) {
+            let keys = [$($k),+];
+            let (len, p) = (s.len(), s.as_mut_ptr());
+            ($(at(p, check(len, &keys, $n))),+)
+        }
+    };
+    (@r $k: ident, $v: ident) => { Result<&mut $v, SplitMutError> };
+}
+
+const_getter!(
+    /// Works like `get2_mut` on a slice (or an array, through coercion), but is a
+    /// `const fn`, so it can be used when building tables at compile time.
+    ///
+    /// Errors are not passed to the `stats` and `tracing` features.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::const_get2_mut;
+    ///
+    /// const SWAPPED: [u8; 3] = {
+    ///     let mut t = [1, 2, 3];
+    ///     if let (Ok(a), Ok(b)) = const_get2_mut(&mut t, 0, 2) {
+    ///         let x = *a;
+    ///         *a = *b;
+    ///         *b = x;
+    ///     }
+    ///     t
+    /// };
+    /// assert_eq!(SWAPPED, [3, 2, 1]);
+    /// ```
+    const_get2_mut, 0 a, 1 b);
+const_getter!(
+    /// Works like `get3_mut` on a slice, but is a `const fn`. See `const_get2_mut`.
+    const_get3_mut, 0 a, 1 b, 2 c);
+const_getter!(
+    /// Works like `get4_mut` on a slice, but is a `const fn`. See `const_get2_mut`.
+    const_get4_mut, 0 a, 1 b, 2 c, 3 d);
+
+#[test]
+fn const_getters() {
+    const T: [u32; 4] = {
+        let mut t = [0, 1, 2, 3];
+        let (a, b, c, d) = const_get4_mut(&mut t, 3, 0, 3, 9);
+        if let (Ok(a), Ok(b), Err(SplitMutError::SameValue), Err(SplitMutError::NoValue)) = (a, b, c, d) {
+            *a += 10;
+            *b += 20;
+        }
+        t
+    };
+    assert_eq!(T, [20, 1, 2, 13]);
+    let mut v = [1, 2, 3];
+    assert_eq!(const_get3_mut(&mut v, 2, 2, 1), (Ok(&mut 3), Err(SplitMutError::SameValue), Ok(&mut 2)));
+}
@@ -0,0 +1,121 @@
+//! A sparse matrix in compressed sparse row (CSR) layout, with mutable access to pairs of rows.
+
+use core::ops::Range;
+use alloc::vec::Vec;
+use {SplitMutError, err};
+
+/// A sparse matrix stored in compressed sparse row layout: row `r` consists of
+/// the column indices `indices[indptr[r]..indptr[r + 1]]` and the values at the
+/// same positions in `data`.
+///
+/// This is the layout used by `sprs` and `scipy`, so the arrays of such a matrix
+/// can be moved in and out with `new` and `into_inner`.
+///
+/// # Example
+/// ```
+/// use splitmut::CsrMatrix;
+///
+/// // [[1, 0, 2],
+/// //  [0, 3, 0]]
+/// let mut m = CsrMatrix::new(vec![0, 2, 3], vec![0, 2, 1], vec![1, 2, 3]);
+/// {
+///     let (a, b) = m.row2_mut(1, 0);
+///     let (a, b) = (a.unwrap(), b.unwrap());
+///     // Scale the second row by the first value of the first row
+///     for x in a.data.iter_mut() { *x *= b.data[0] * 10 }
+///     b.indices[1] = 1;
+/// }
+/// assert_eq!(m.into_inner(), (vec![0, 2, 3], vec![0, 1, 1], vec![1, 2, 30]));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsrMatrix<V> { indptr: Vec<usize>, indices: Vec<usize>, data: Vec<V> }
+
+/// One row of a `CsrMatrix`, with the column indices and values both mutable.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CsrRowMut<'a, V: 'a> {
+    /// The column index of every stored value.
+    pub indices: &'a mut [usize],
+    /// The stored values.
+    pub data: &'a mut [V],
+}
+
+impl<V> CsrMatrix<V> {
+    /// Wraps the row pointers, column indices and values of a matrix.
+    ///
+    /// # Panics
+    /// Panics if `indptr` is empty, decreases anywhere, or does not end
+    /// with the number of values, or if `indices` and `data` differ in length.
+    pub fn new(indptr: Vec<usize>, indices: Vec<usize>, data: Vec<V>) -> Self {
+        assert!(indices.len() == data.len(), "column indices and values must have the same length");
+        assert!(indptr.last() == Some(&data.len()) && indptr.windows(2).all(|w| w[0] <= w[1]),
+            "row pointers must be non-decreasing and end with the number of values");
+        CsrMatrix { indptr, indices, data }
+    }
+
+    /// Number of rows.
+    pub fn rows(&self) -> usize { self.indptr.len() - 1 }
+
+    /// Returns the row pointers.
+    pub fn indptr(&self) -> &[usize] { &self.indptr }
+
+    /// Returns the column indices of all values, row by row.
+    pub fn indices(&self) -> &[usize] { &self.indices }
+
+    /// Returns all values, row by row.
+    pub fn data(&self) -> &[V] { &self.data }
+
+    /// Returns the row pointers, column indices and values.
+    pub fn into_inner(self) -> (Vec<usize>, Vec<usize>, Vec<V>) { (self.indptr, self.indices, self.data) }
+
+    fn range(&self, r: usize) -> Option<Range<usize>> {
+        if r < self.rows() { Some(self.indptr[r]..self.indptr[r + 1]) } else { None }
+    }
+
+    /// Returns a row, mutable.
+    pub fn row_mut(&mut self, r: usize) -> Option<CsrRowMut<'_, V>> {
+        let x = self.range(r)?;
+        Some(CsrRowMut { indices: &mut self.indices[x.clone()], data: &mut self.data[x] })
+    }
+
+    /// Returns two distinct rows, both mutable.
+    ///
+    /// If `a` and `b` are the same, the second one is `Err(SplitMutError::SameValue)`.
+    pub fn row2_mut(&mut self, a: usize, b: usize) -> (Result<CsrRowMut<'_, V>, SplitMutError>, Result<CsrRowMut<'_, V>, SplitMutError>) {
+        trace_method!("row2_mut");
+        match (self.range(a), self.range(b)) {
+            (Some(_), Some(_)) if a == b => (Ok(self.row_mut(a).unwrap()), Err(err(SplitMutError::SameValue))),
+            (Some(ra), Some(rb)) => {
+                let (lo, hi) = if a < b { (ra, rb) } else { (rb, ra) };
+                let (i1, i2) = self.indices.split_at_mut(hi.start);
+                let (d1, d2) = self.data.split_at_mut(hi.start);
+                let l = CsrRowMut { indices: &mut i1[lo.clone()], data: &mut d1[lo] };
+                let h = CsrRowMut { indices: &mut i2[..hi.len()], data: &mut d2[..hi.len()] };
+                if a < b { (Ok(l), Ok(h)) } else { (Ok(h), Ok(l)) }
+            },
+            (Some(_), None) => (Ok(self.row_mut(a).unwrap()), Err(err(SplitMutError::NoValue))),
+            (None, Some(_)) => (Err(err(SplitMutError::NoValue)), Ok(self.row_mut(b).unwrap())),
+            (None, None) => (Err(err(SplitMutError::NoValue)), Err(err(SplitMutError::NoValue))),
+        }
+    }
+}
+
+#[test]
+fn csr_rows() {
+    let mut m = CsrMatrix::new(vec![0, 1, 1, 3], vec![2, 0, 1], vec![5u8, 6, 7]);
+    assert_eq!(m.rows(), 3);
+    {
+        let (a, b) = m.row2_mut(2, 1);
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(b.data, []);
+        assert_eq!((&*a.indices, &*a.data), (&[0, 1][..], &[6, 7][..]));
+    }
+    assert_eq!(m.row2_mut(0, 0).1, Err(SplitMutError::SameValue));
+    assert_eq!(m.row2_mut(3, 0).0, Err(SplitMutError::NoValue));
+    assert!(m.row_mut(3).is_none());
+    m.row_mut(0).unwrap().data[0] = 1;
+    assert_eq!(m.data(), [1, 6, 7]);
+}
+
+#[test]
+#[should_panic(expected = "row pointers must be non-decreasing")]
+fn csr_bad_indptr() { CsrMatrix::new(vec![0, 2, 1], vec![0], vec![0]); }
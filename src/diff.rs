@@ -0,0 +1,87 @@
+//! Recording which of the selected values were changed.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use {SplitMut, SplitMutBase};
+
+/// A change to a value, as reported by `DiffSession::finish`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<K, V> {
+    /// The key of the value.
+    pub key: K,
+    /// The value when it was first handed out.
+    pub before: V,
+    /// The value when the session finished.
+    pub after: V,
+}
+
+/// Takes a snapshot of every value handed out, and reports which of them changed.
+///
+/// The session implements `SplitMut` and gives direct access to the values of the
+/// collection; the first time a value is asked for, a copy of it is kept.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, DiffSession, Change};
+///
+/// let mut v = vec![1, 2, 3];
+/// let mut s = DiffSession::new(&mut v);
+/// {
+///     let (a, b) = s.get2_mut(0, 1);
+///     let (a, b) = (a.unwrap(), b.unwrap());
+///     *b *= 10;
+///     *a += *b - 20;
+/// }
+/// assert_eq!(s.finish(), [Change { key: 1, before: 2, after: 20 }]);
+/// assert_eq!(v, [1, 20, 3]);
+/// ```
+#[derive(Debug)]
+pub struct DiffSession<'a, C: 'a + ?Sized, K, V> {
+    c: &'a mut C,
+    seen: BTreeSet<usize>,
+    before: Vec<(K, V)>,
+}
+
+impl<'a, C: 'a + SplitMut<K, V> + ?Sized, K: Clone, V: Clone + PartialEq> DiffSession<'a, C, K, V> {
+    /// Starts a session on a collection.
+    pub fn new(c: &'a mut C) -> Self { DiffSession { c, seen: BTreeSet::new(), before: Vec::new() } }
+
+    /// Ends the session, returning the values that are different from when they
+    /// were first handed out, in the order they were first handed out.
+    pub fn finish(self) -> Vec<Change<K, V>> {
+        trace_method!("finish");
+        let DiffSession { c, before, .. } = self;
+        before.into_iter().filter_map(|(key, before)| {
+            let after = c.get1_mut(key.clone())?;
+            if *after == before { None } else { Some(Change { after: after.clone(), key, before }) }
+        }).collect()
+    }
+}
+
+unsafe impl<'a, C: 'a + SplitMut<K, V> + ?Sized, K: Clone, V: Clone + PartialEq> SplitMutBase<K, V> for DiffSession<'a, C, K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: K) -> Option<&mut V> {
+        let kk = k.clone();
+        let v = self.c.get1_mut(k)?;
+        if self.seen.insert(v as *mut V as usize) { self.before.push((kk, v.clone())) }
+        Some(v)
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: K) -> &mut V { self.get1_mut(k).unwrap() }
+}
+
+#[test]
+fn diff_session() {
+    use alloc::collections::BTreeMap;
+    let mut h: BTreeMap<_, _> = (0..5).map(|i| (i, i)).collect();
+    let mut s = DiffSession::new(&mut h);
+    {
+        let (a, b) = s.get2_mut(&1, &3);
+        std::mem::swap(a.unwrap(), b.unwrap());
+    }
+    // Changed back and forth, so not reported
+    *s.get1_mut(&2).unwrap() = 7;
+    *s.get1_mut(&2).unwrap() = 2;
+    ::testing::assert_splitmut_sound(&mut s, &[&0, &4], &[&5]);
+    assert_eq!(s.finish(), [Change { key: &1, before: 1, after: 3 }, Change { key: &3, before: 3, after: 1 }]);
+}
@@ -0,0 +1,171 @@
+//! A two-dimensional grid, with mutable access to pairs of rows and columns.
+
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::ops::{Index, IndexMut};
+use alloc::vec::Vec;
+use {SplitMutBase, GetRef, SplitMutSlice, SplitMutError, err};
+
+/// Values stored row by row in a `Vec`, indexed by `(row, column)`.
+///
+/// Apart from the `SplitMut` methods for single values, whole rows can be split
+/// with `row2_mut` and columns with `col2_mut`.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, Grid2D};
+///
+/// let mut g = Grid2D::new(vec![1, 2, 3, 4, 5, 6], 3);
+/// {
+///     // Subtract twice the first row from the second
+///     let (a, b) = g.row2_mut(0, 1);
+///     for (x, y) in a.unwrap().iter().zip(b.unwrap()) { *y -= 2 * x; }
+/// }
+/// assert_eq!(g.get_ref(), &[1, 2, 3, 2, 1, 0]);
+/// let (a, b) = g.get2_mut((0, 2), (1, 0));
+/// std::mem::swap(a.unwrap(), b.unwrap());
+/// assert_eq!(g.get_ref(), &[1, 2, 2, 3, 1, 0]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid2D<V> { data: Vec<V>, width: usize }
+
+impl<V> Grid2D<V> {
+    /// Wraps values stored row by row, with `width` values in every row.
+    ///
+    /// # Panics
+    /// Panics if `width` is zero or the number of values is not a multiple of `width`.
+    pub fn new(data: Vec<V>, width: usize) -> Self {
+        assert!(width != 0 && data.len().is_multiple_of(width), "grid width must be non-zero and divide the number of values");
+        Grid2D { data, width }
+    }
+
+    /// Number of values in every row.
+    pub fn width(&self) -> usize { self.width }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize { self.data.len() / self.width }
+
+    // The position of a value in `data`, if the row and column are within the grid.
+    #[inline]
+    fn pos(&self, (r, c): (usize, usize)) -> Option<usize> {
+        if c < self.width && r < self.height() { Some(r * self.width + c) } else { None }
+    }
+
+    /// Returns all values, row by row.
+    pub fn get_ref(&self) -> &[V] { &self.data }
+
+    /// Returns the wrapped values.
+    pub fn into_inner(self) -> Vec<V> { self.data }
+
+    /// Returns two distinct rows, both mutable.
+    ///
+    /// If `a` and `b` are the same, the second one is `Err(SplitMutError::SameValue)`.
+    pub fn row2_mut(&mut self, a: usize, b: usize) -> (Result<&mut [V], SplitMutError>, Result<&mut [V], SplitMutError>) {
+        trace_method!("row2_mut");
+        self.data.get2_chunks_mut(self.width, a, b)
+    }
+
+    /// Returns iterators over two distinct columns, from the top row down,
+    /// both yielding mutable references.
+    ///
+    /// If `a` and `b` are the same, the second one is `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::Grid2D;
+    ///
+    /// let mut g = Grid2D::new(vec![1, 2, 3, 4, 5, 6], 3);
+    /// {
+    ///     let (a, b) = g.col2_mut(2, 0);
+    ///     for (x, y) in a.unwrap().zip(b.unwrap()) { std::mem::swap(x, y); }
+    /// }
+    /// assert_eq!(g.get_ref(), &[3, 2, 1, 6, 5, 4]);
+    /// ```
+    pub fn col2_mut(&mut self, a: usize, b: usize) -> (Result<ColumnMut<'_, V>, SplitMutError>, Result<ColumnMut<'_, V>, SplitMutError>) {
+        trace_method!("col2_mut");
+        let (w, h, p) = (self.width, self.height(), self.data.as_mut_ptr());
+        let col = |c: usize| if c < w { Ok(ColumnMut { p: p.wrapping_add(c), left: h, stride: w, _l: PhantomData }) }
+            else { Err(err(SplitMutError::NoValue)) };
+        let ca = col(a);
+        let cb = if a == b && a < w { Err(err(SplitMutError::SameValue)) } else { col(b) };
+        (ca, cb)
+    }
+}
+
+unsafe impl<V> SplitMutBase<(usize, usize), V> for Grid2D<V> {
+    #[inline]
+    fn get1_mut(&mut self, k: (usize, usize)) -> Option<&mut V> {
+        let i = self.pos(k)?;
+        self.data.get_mut(i)
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, (r, c): (usize, usize)) -> &mut V { self.data.get_unchecked_mut(r * self.width + c) }
+}
+
+impl<V> GetRef<(usize, usize), V> for Grid2D<V> {
+    #[inline]
+    fn get1_ref(&self, k: (usize, usize)) -> Option<&V> { self.data.get(self.pos(k)?) }
+}
+
+impl<V> Index<(usize, usize)> for Grid2D<V> {
+    type Output = V;
+    fn index(&self, k: (usize, usize)) -> &V { self.get1_ref(k).expect("grid index out of bounds") }
+}
+
+impl<V> IndexMut<(usize, usize)> for Grid2D<V> {
+    fn index_mut(&mut self, k: (usize, usize)) -> &mut V { self.get1_mut(k).expect("grid index out of bounds") }
+}
+
+/// Iterator over the values of one column of a `Grid2D`, returned from the col2_mut function.
+pub struct ColumnMut<'a, V: 'a> { p: *mut V, left: usize, stride: usize, _l: PhantomData<&'a mut V> }
+
+impl<'a, V: 'a> Iterator for ColumnMut<'a, V> {
+    type Item = &'a mut V;
+    fn next(&mut self) -> Option<&'a mut V> {
+        if self.left == 0 { return None }
+        let v = unsafe { &mut *self.p };
+        self.left -= 1;
+        // Only step within the grid; after the last row the pointer is not used again.
+        if self.left > 0 { self.p = unsafe { self.p.add(self.stride) } }
+        Some(v)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { (self.left, Some(self.left)) }
+}
+
+unsafe impl<'a, V: 'a + Send> Send for ColumnMut<'a, V> {}
+unsafe impl<'a, V: 'a + Sync> Sync for ColumnMut<'a, V> {}
+
+impl<'a, V: 'a> ExactSizeIterator for ColumnMut<'a, V> {}
+impl<'a, V: 'a> FusedIterator for ColumnMut<'a, V> {}
+
+#[test]
+fn grid_pairs() {
+    use SplitMut;
+    let mut g = Grid2D::new((0..6).collect(), 2);
+    assert_eq!(g.height(), 3);
+    assert_eq!(g.row2_mut(1, 1).1, Err(SplitMutError::SameValue));
+    assert_eq!(g.row2_mut(3, 0).0, Err(SplitMutError::NoValue));
+    assert!(g.col2_mut(1, 1).1.is_err());
+    assert!(g.col2_mut(2, 0).0.is_err());
+    let (a, b) = g.col2_mut(1, 0);
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert_eq!(a.len(), 3);
+    for (x, y) in a.zip(b) { *x += *y * 10; }
+    assert_eq!(g.get_ref(), &[0, 1, 2, 23, 4, 45]);
+    assert_eq!(g.get2_mut((0, 2), (2, 1)), (Err(SplitMutError::NoValue), Ok(&mut 45)));
+    ::testing::assert_splitmut_sound(&mut g, &[(0, 0), (2, 1)], &[(0, 2), (3, 0)]);
+    g[(1, 0)] += g[(2, 1)];
+    assert_eq!(g[(1, 0)], 47);
+}
+
+#[test]
+#[should_panic(expected = "grid index out of bounds")]
+fn grid_index_column() { let g = Grid2D::new(vec![1, 2], 2); let _ = g[(0, 2)]; }
+
+#[test]
+fn grid_huge_row() {
+    use SplitMut;
+    let mut g = Grid2D::new(vec![1, 2, 3, 4], 2);
+    assert_eq!(g.get2_mut((0, 0), (1 << 63, 0)), (Ok(&mut 1), Err(SplitMutError::NoValue)));
+    assert_eq!(g.get1_ref((usize::MAX, 1)), None);
+}
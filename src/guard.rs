@@ -0,0 +1,217 @@
+//! An owning wrapper that hands out storable, runtime-checked guards.
+
+use std::cell::{RefCell, UnsafeCell};
+use ptrset::PtrSet;
+use std::ops::{Deref, DerefMut};
+use std::fmt;
+use std::boxed::Box;
+use std::vec::Vec;
+use {SharedLookup, SplitMutError, to_r, err};
+
+/// Owns a collection and hands out `BorrowGuard`s to its values.
+///
+/// Unlike the tuple returned from `get2_mut`, guards are separate objects that
+/// can be stored and dropped independently, much like `RefCell`'s `RefMut`.
+/// A registry of claimed values is kept at runtime; asking for a value that is
+/// currently guarded returns `SplitMutError::SameValue`. The collection must
+/// implement `SharedLookup`, since values are looked up while guards are alive:
+///
+/// ```compile_fail
+/// use splitmut::{Guarded, IndexSplit};
+///
+/// let a = unsafe { IndexSplit::new([1, 2, 3], |_: &[u32; 3], &k: &usize| k < 3) };
+/// let g = Guarded::new(a);
+/// let _ = g.guard(0);
+/// ```
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use splitmut::{Guarded, SplitMutError};
+///
+/// let mut h = HashMap::new();
+/// h.insert("a", 1);
+/// h.insert("b", 2);
+/// let g = Guarded::new(h);
+/// let mut a = g.guard(&"a").unwrap();
+/// assert_eq!(g.guard(&"a").err(), Some(SplitMutError::SameValue));
+/// *a += 10;
+/// drop(a);
+/// assert_eq!(*g.guard(&"a").unwrap(), 11);
+/// ```
+pub struct Guarded<C>(UnsafeCell<C>, RefCell<PtrSet<usize>>);
+
+impl<C> Guarded<C> {
+    /// Wraps a collection.
+    pub fn new(c: C) -> Self { Guarded(UnsafeCell::new(c), RefCell::new(PtrSet::default())) }
+
+    /// Returns the wrapped collection.
+    pub fn into_inner(self) -> C { self.0.into_inner() }
+
+    /// Returns a mutable reference to the wrapped collection. Since this requires
+    /// `&mut self`, no guards can be alive.
+    pub fn get_mut(&mut self) -> &mut C { self.0.get_mut() }
+
+    /// Returns a guard for the value of `k`, or an error if the value does not exist
+    /// or is currently claimed by another guard.
+    pub fn guard<K, V>(&self, k: K) -> Result<BorrowGuard<'_, V>, SplitMutError> where C: SharedLookup<K, V> {
+        trace_method!("guard");
+        // SharedLookup promises that the `&mut C` covers no values held by other guards.
+        let c = unsafe { &mut *self.0.get() };
+        let p = to_r(c.get1_mut(k))?;
+        if !self.1.borrow_mut().insert(p as usize) { return Err(err(SplitMutError::SameValue)) };
+        Ok(BorrowGuard(p, &self.1))
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for Guarded<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.1.borrow().is_empty() {
+            f.debug_tuple("Guarded").field(unsafe { &*self.0.get() }).finish()
+        } else {
+            f.write_str("Guarded(<borrowed>)")
+        }
+    }
+}
+
+/// A mutable handle to a value inside a `Guarded` collection.
+/// The claim on the value is released when the guard is dropped.
+pub struct BorrowGuard<'a, V: 'a>(*mut V, &'a RefCell<PtrSet<usize>>);
+
+impl<'a, V: 'a> Deref for BorrowGuard<'a, V> {
+    type Target = V;
+    fn deref(&self) -> &V { unsafe { &*self.0 } }
+}
+
+impl<'a, V: 'a> DerefMut for BorrowGuard<'a, V> {
+    fn deref_mut(&mut self) -> &mut V { unsafe { &mut *self.0 } }
+}
+
+impl<'a, V: 'a> Drop for BorrowGuard<'a, V> {
+    fn drop(&mut self) { self.1.borrow_mut().remove(&(self.0 as usize)); }
+}
+
+impl<'a, V: 'a + fmt::Debug> fmt::Debug for BorrowGuard<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.debug_tuple("BorrowGuard").field(&**self).finish() }
+}
+
+/// Like `Guarded`, but keeps track of which values were changed.
+///
+/// A guard that was mutably dereferenced reports its key when it is dropped: the
+/// key is added to a list of dirty keys, and passed to a callback, if one is set.
+///
+/// # Example
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use splitmut::Observed;
+///
+/// let changed = Rc::new(RefCell::new(vec![]));
+/// let c = changed.clone();
+/// let mut o = Observed::new(vec![1, 2, 3]).on_change(move |k: &usize| c.borrow_mut().push(*k));
+/// {
+///     let mut a = o.guard(0).unwrap();
+///     let b = o.guard(2).unwrap();
+///     *a += *b;
+/// }
+/// assert_eq!(*changed.borrow(), [0]);
+/// assert_eq!(o.take_dirty(), [0]);
+/// assert_eq!(o.into_inner(), [4, 2, 3]);
+/// ```
+pub struct Observed<C, K> {
+    inner: Guarded<C>,
+    dirty: RefCell<Vec<K>>,
+    callback: Option<Box<dyn Fn(&K)>>,
+}
+
+impl<C, K> Observed<C, K> {
+    /// Wraps a collection.
+    pub fn new(c: C) -> Self { Observed { inner: Guarded::new(c), dirty: RefCell::new(vec!()), callback: None } }
+
+    /// Sets a function to be called with the key of every changed value, when its
+    /// guard is dropped.
+    pub fn on_change<F: Fn(&K) + 'static>(mut self, f: F) -> Self { self.callback = Some(Box::new(f)); self }
+
+    /// Returns the wrapped collection.
+    pub fn into_inner(self) -> C { self.inner.into_inner() }
+
+    /// Returns a mutable reference to the wrapped collection. Changes made through it are not tracked.
+    pub fn get_mut(&mut self) -> &mut C { self.inner.get_mut() }
+
+    /// Returns the keys of changed values, in the order their guards were dropped,
+    /// and clears the list. A key is listed once for every guard that changed it.
+    pub fn take_dirty(&mut self) -> Vec<K> { std::mem::take(self.dirty.get_mut()) }
+
+    /// Returns a guard for the value of `k`, or an error if the value does not exist
+    /// or is currently claimed by another guard.
+    pub fn guard<V>(&self, k: K) -> Result<ObservedGuard<'_, C, K, V>, SplitMutError> where C: SharedLookup<K, V>, K: Clone {
+        trace_method!("guard");
+        let g = self.inner.guard(k.clone())?;
+        Ok(ObservedGuard { g, k: Some(k), changed: false, o: self })
+    }
+}
+
+impl<C: fmt::Debug, K: fmt::Debug> fmt::Debug for Observed<C, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Observed").field("inner", &self.inner).field("dirty", &self.dirty).finish()
+    }
+}
+
+/// A mutable handle to a value inside an `Observed` collection.
+pub struct ObservedGuard<'a, C: 'a, K: 'a, V: 'a> { g: BorrowGuard<'a, V>, k: Option<K>, changed: bool, o: &'a Observed<C, K> }
+
+impl<'a, C: 'a, K: 'a, V: 'a> Deref for ObservedGuard<'a, C, K, V> {
+    type Target = V;
+    fn deref(&self) -> &V { &self.g }
+}
+
+impl<'a, C: 'a, K: 'a, V: 'a> DerefMut for ObservedGuard<'a, C, K, V> {
+    fn deref_mut(&mut self) -> &mut V { self.changed = true; &mut self.g }
+}
+
+impl<'a, C: 'a, K: 'a, V: 'a> Drop for ObservedGuard<'a, C, K, V> {
+    fn drop(&mut self) {
+        if !self.changed { return }
+        let k = self.k.take().unwrap();
+        if let Some(ref f) = self.o.callback { f(&k) }
+        self.o.dirty.borrow_mut().push(k);
+    }
+}
+
+impl<'a, C: 'a, K: 'a, V: 'a + fmt::Debug> fmt::Debug for ObservedGuard<'a, C, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.debug_tuple("ObservedGuard").field(&**self).finish() }
+}
+
+#[test]
+fn guard_store() {
+    struct Pair<'a> { a: BorrowGuard<'a, u8>, b: BorrowGuard<'a, u8> }
+    let g = Guarded::new(vec![1u8, 2, 3]);
+    let mut p = Pair { a: g.guard(0).unwrap(), b: g.guard(2).unwrap() };
+    std::mem::swap(&mut *p.a, &mut *p.b);
+    assert_eq!(g.guard(2).err(), Some(SplitMutError::SameValue));
+    assert_eq!(g.guard(3).err(), Some(SplitMutError::NoValue));
+    drop(p);
+    assert_eq!(*g.guard(2).unwrap(), 1);
+    assert_eq!(g.into_inner(), [3, 2, 1]);
+}
+
+#[test]
+fn observed() {
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use std::cell::Cell;
+    let count = Rc::new(Cell::new(0));
+    let c2 = count.clone();
+    let h: HashMap<_, _> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    let mut o = Observed::new(h).on_change(move |_: &&str| c2.set(c2.get() + 1));
+    {
+        let a = o.guard("a").unwrap();
+        let mut b = o.guard("b").unwrap();
+        assert_eq!(o.guard("a").err(), Some(SplitMutError::SameValue));
+        *b += *a;
+    }
+    *o.guard("b").unwrap() += 1;
+    assert_eq!(o.take_dirty(), ["b", "b"]);
+    assert_eq!(count.get(), 2);
+    assert!(o.take_dirty().is_empty());
+}
@@ -0,0 +1,123 @@
+use bimap::{BiHashMap, Overwritten};
+use std::collections::HashMap;
+use std::hash::Hash;
+use {SplitMutBase, GetRef};
+
+/// A key for a `BiValueMap`, naming an entry by either its left or its right key.
+#[derive(Clone, Copy, Debug)]
+pub enum BiKey<'a, L: 'a, R: 'a> {
+    /// Look up the entry by its left key.
+    Left(&'a L),
+    /// Look up the entry by its right key.
+    Right(&'a R),
+}
+
+/// A `bimap::BiHashMap` of left and right keys, with a value for every pair.
+///
+/// Values can be looked up by either key (using `BiKey`), and a left key and a
+/// right key resolving to the same pair give `Err(SplitMutError::SameValue)`.
+///
+/// # Example
+/// ```
+/// extern crate splitmut;
+/// use splitmut::{SplitMut, SplitMutError, BiValueMap, BiKey};
+///
+/// # fn main() {
+/// let mut m = BiValueMap::new();
+/// m.insert(1, "one", 'a');
+/// m.insert(2, "two", 'b');
+/// {
+///     let (a, b) = m.get2_mut(BiKey::Left(&1), BiKey::Right(&"two"));
+///     std::mem::swap(a.unwrap(), b.unwrap());
+/// }
+/// assert_eq!(m.value_by_left(&1), Some(&'b'));
+/// assert_eq!(m.get2_mut(BiKey::Left(&1), BiKey::Right(&"one")).1, Err(SplitMutError::SameValue));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BiValueMap<L: Eq + Hash, R: Eq + Hash, V> { keys: BiHashMap<L, R>, values: HashMap<L, V> }
+
+impl<L: Eq + Hash + Clone, R: Eq + Hash, V> Default for BiValueMap<L, R, V> {
+    fn default() -> Self { BiValueMap { keys: BiHashMap::new(), values: HashMap::new() } }
+}
+
+impl<L: Eq + Hash + Clone, R: Eq + Hash, V> BiValueMap<L, R, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self { Self::default() }
+
+    /// The left/right pairs of this map.
+    pub fn keys(&self) -> &BiHashMap<L, R> { &self.keys }
+
+    /// Inserts a pair with a value. Like `BiHashMap::insert`, this removes any
+    /// existing pairs with the same left or right key, together with their values.
+    pub fn insert(&mut self, left: L, right: R, value: V) {
+        match self.keys.insert(left.clone(), right) {
+            Overwritten::Neither => {},
+            Overwritten::Left(l, _) | Overwritten::Right(l, _) | Overwritten::Pair(l, _) => { self.values.remove(&l); },
+            Overwritten::Both((l1, _), (l2, _)) => { self.values.remove(&l1); self.values.remove(&l2); },
+        }
+        self.values.insert(left, value);
+    }
+
+    /// Removes a pair and its value by the left key.
+    pub fn remove_by_left(&mut self, left: &L) -> Option<(L, R, V)> {
+        let (l, r) = self.keys.remove_by_left(left)?;
+        let v = self.values.remove(&l)?;
+        Some((l, r, v))
+    }
+
+    /// Removes a pair and its value by the right key.
+    pub fn remove_by_right(&mut self, right: &R) -> Option<(L, R, V)> {
+        let (l, r) = self.keys.remove_by_right(right)?;
+        let v = self.values.remove(&l)?;
+        Some((l, r, v))
+    }
+
+    /// Returns the value for a left key.
+    pub fn value_by_left(&self, left: &L) -> Option<&V> { self.values.get(left) }
+
+    /// Returns the value for a right key.
+    pub fn value_by_right(&self, right: &R) -> Option<&V> { self.values.get(self.keys.get_by_right(right)?) }
+
+    /// Returns the number of pairs.
+    pub fn len(&self) -> usize { self.values.len() }
+
+    /// Returns true if there are no pairs.
+    pub fn is_empty(&self) -> bool { self.values.is_empty() }
+}
+
+unsafe impl<'a, L: Eq + Hash + Clone, R: Eq + Hash, V> SplitMutBase<BiKey<'a, L, R>, V> for BiValueMap<L, R, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: BiKey<'a, L, R>) -> Option<&mut V> {
+        match k {
+            BiKey::Left(l) => self.values.get_mut(l),
+            BiKey::Right(r) => self.values.get_mut(self.keys.get_by_right(r)?),
+        }
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: BiKey<'a, L, R>) -> &mut V { std::mem::transmute(self.get1_mut(k)) }
+}
+
+impl<'a, L: Eq + Hash + Clone, R: Eq + Hash, V> GetRef<BiKey<'a, L, R>, V> for BiValueMap<L, R, V> {
+    #[inline]
+    fn get1_ref(&self, k: BiKey<'a, L, R>) -> Option<&V> {
+        match k {
+            BiKey::Left(l) => self.value_by_left(l),
+            BiKey::Right(r) => self.value_by_right(r),
+        }
+    }
+}
+
+#[test]
+fn bi_value_map() {
+    use {SplitMut, SplitMutError};
+    let mut m = BiValueMap::new();
+    m.insert('a', 1, "a1");
+    m.insert('b', 2, "b2");
+    m.insert('c', 2, "c2");
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.get3_mut(BiKey::Right(&2), BiKey::Left(&'b'), BiKey::Left(&'c')),
+        (Ok(&mut "c2"), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue)));
+    assert_eq!(m.remove_by_right(&1), Some(('a', 1, "a1")));
+    ::testing::assert_splitmut_sound(&mut m, &[BiKey::Left(&'c'), BiKey::Right(&2)], &[BiKey::Left(&'a')]);
+}
@@ -0,0 +1,24 @@
+use bumpalo::collections::Vec;
+use {SplitMutBase, GetRef};
+
+unsafe impl<'bump, V> SplitMutBase<usize, V> for Vec<'bump, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: usize) -> Option<&mut V> { self.get_mut(k) }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: usize) -> &mut V { self.get_unchecked_mut(k) }
+}
+
+impl<'bump, V> GetRef<usize, V> for Vec<'bump, V> {
+    #[inline]
+    fn get1_ref(&self, k: usize) -> Option<&V> { self.get(k) }
+}
+
+#[test]
+fn bump_vec() {
+    use {SplitMut, SplitMutError};
+    let bump = bumpalo::Bump::new();
+    let mut v = Vec::new_in(&bump);
+    v.extend_from_slice(&[1, 2, 3]);
+    assert_eq!(v.get3_mut(2, 0, 2), (Ok(&mut 3), Ok(&mut 1), Err(SplitMutError::SameValue)));
+    ::testing::assert_splitmut_sound(&mut v, &[0, 1, 2], &[3]);
+}
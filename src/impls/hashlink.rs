@@ -0,0 +1,31 @@
+use hashlink::LinkedHashMap;
+use std::{hash, borrow};
+use {SplitMutBase, GetRef};
+
+unsafe impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> SplitMutBase<&'a Q, V> for LinkedHashMap<K, V, S> {
+    #[inline]
+    fn get1_mut(&mut self, k: &'a Q) -> Option<&mut V> { self.get_mut(k) }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: &'a Q) -> &mut V { std::mem::transmute(self.get_mut(k)) }
+}
+
+impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> GetRef<&'a Q, V> for LinkedHashMap<K, V, S> {
+    #[inline]
+    fn get1_ref(&self, k: &'a Q) -> Option<&V> { self.get(k) }
+}
+
+#[test]
+fn linked_hash_map() {
+    use std::vec::Vec;
+    use {SplitMut, SplitMutError};
+    let mut h = LinkedHashMap::new();
+    h.insert("b", 2);
+    h.insert("a", 1);
+    {
+        let (a, b, c) = h.get3_mut(&"a", &"b", &"a");
+        std::mem::swap(a.unwrap(), b.unwrap());
+        assert_eq!(c, Err(SplitMutError::SameValue));
+    }
+    assert_eq!(h.iter().collect::<Vec<_>>(), [(&"b", &1), (&"a", &2)]);
+    ::testing::assert_splitmut_sound(&mut h, &[&"a", &"b"], &[&"c"]);
+}
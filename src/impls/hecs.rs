@@ -0,0 +1,30 @@
+use hecs::{Component, Entity, World};
+use SplitMutBase;
+
+// No GetRef: shared access to a World hands out dynamically checked borrows, not plain references.
+unsafe impl<T: Component> SplitMutBase<Entity, T> for World {
+    #[inline]
+    fn get1_mut(&mut self, k: Entity) -> Option<&mut T> { self.query_one_mut::<&mut T>(k).ok() }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: Entity) -> &mut T { std::mem::transmute(self.query_one_mut::<&mut T>(k).ok()) }
+}
+
+#[test]
+fn hecs_world() {
+    use {SplitMut, SplitMutError};
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+    let mut w = World::new();
+    let a = w.spawn((Health(10), 1u8));
+    let b = w.spawn((Health(20),));
+    let c = w.spawn((2u8,));
+    {
+        let (x, y, z) = SplitMut::<_, Health>::get3_mut(&mut w, a, b, c);
+        let (x, y) = (x.unwrap(), y.unwrap());
+        std::mem::swap(&mut x.0, &mut y.0);
+        assert_eq!(z, Err(SplitMutError::NoValue));
+    }
+    let (x, y): (Result<&mut Health, _>, _) = w.get2_mut(a, a);
+    assert_eq!((x, y), (Ok(&mut Health(20)), Err(SplitMutError::SameValue)));
+    ::testing::assert_splitmut_sound::<_, Health, _>(&mut w, &[a, b], &[c]);
+}
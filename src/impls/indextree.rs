@@ -0,0 +1,31 @@
+use indextree::{Arena, NodeId};
+use {SplitMutBase, GetRef};
+
+unsafe impl<T> SplitMutBase<NodeId, T> for Arena<T> {
+    #[inline]
+    fn get1_mut(&mut self, k: NodeId) -> Option<&mut T> { self.get_data_mut(k) }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: NodeId) -> &mut T { std::mem::transmute(self.get_data_mut(k)) }
+}
+
+impl<T> GetRef<NodeId, T> for Arena<T> {
+    #[inline]
+    fn get1_ref(&self, k: NodeId) -> Option<&T> { self.get_data(k) }
+}
+
+#[test]
+fn index_tree() {
+    use {SplitMut, SplitMutError};
+    let mut a = Arena::new();
+    let root = a.new_node(1);
+    let child = root.append_value(2, &mut a);
+    {
+        let parent = a[child].parent().unwrap();
+        let (p, c) = a.get2_mut(parent, child);
+        *p.unwrap() += 10;
+        *c.unwrap() += 20;
+    }
+    assert_eq!(a[root].get(), &11);
+    assert_eq!(a.get3_mut(child, root, child), (Ok(&mut 22), Ok(&mut 11), Err(SplitMutError::SameValue)));
+    ::testing::assert_splitmut_sound(&mut a, &[root, child], &[]);
+}
@@ -0,0 +1,27 @@
+use intmap::{IntMap, IntKey};
+use {SplitMutBase, GetRef};
+
+unsafe impl<K: IntKey, V> SplitMutBase<K, V> for IntMap<K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: K) -> Option<&mut V> { self.get_mut(k) }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: K) -> &mut V { std::mem::transmute(self.get_mut(k)) }
+}
+
+impl<K: IntKey, V> GetRef<K, V> for IntMap<K, V> {
+    #[inline]
+    fn get1_ref(&self, k: K) -> Option<&V> { self.get(k) }
+}
+
+#[test]
+fn int_map() {
+    use std::vec::Vec;
+    use {SplitMut, SplitMutError};
+    let mut h: IntMap<u64, &str> = IntMap::new();
+    h.insert(10, "ten");
+    h.insert(20, "twenty");
+    assert_eq!(h.get3_mut(20, 30, 20), (Ok(&mut "twenty"), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue)));
+    let keys: Vec<u64> = (0..100).collect();
+    for &k in &keys { h.insert(k, "many"); }
+    ::testing::assert_splitmut_sound(&mut h, &keys, &[100, 1000]);
+}
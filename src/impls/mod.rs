@@ -0,0 +1,47 @@
+//! SplitMut implementations for collections in other crates.
+//! Each one is behind a feature with the same name as the crate.
+
+#[cfg(feature = "hashlink")]
+mod hashlink;
+
+#[cfg(feature = "intmap")]
+mod intmap;
+
+#[cfg(feature = "bimap")]
+mod bimap;
+#[cfg(feature = "bimap")]
+pub use self::bimap::{BiValueMap, BiKey};
+
+#[cfg(feature = "rangemap")]
+mod rangemap;
+#[cfg(feature = "rangemap")]
+pub use self::rangemap::RangeValueMap;
+
+#[cfg(feature = "qp-trie")]
+mod qp_trie;
+#[cfg(feature = "qp-trie")]
+pub use self::qp_trie::{SplitMutTrie, TriePrefixMut};
+
+#[cfg(feature = "indextree")]
+mod indextree;
+
+#[cfg(feature = "bumpalo")]
+mod bumpalo;
+
+#[cfg(feature = "hecs")]
+mod hecs;
+
+#[cfg(feature = "rand")]
+mod rand;
+#[cfg(feature = "rand")]
+pub use self::rand::SplitMutSample;
+
+#[cfg(feature = "regex")]
+mod regex;
+#[cfg(feature = "regex")]
+pub use self::regex::{SplitMutMatching, MatchingMut};
+
+#[cfg(feature = "roaring")]
+mod roaring;
+#[cfg(feature = "roaring")]
+pub use self::roaring::{SplitMutBitmap, BitmapMut};
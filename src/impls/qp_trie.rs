@@ -0,0 +1,131 @@
+use qp_trie::Trie;
+use std::borrow::Borrow;
+use std::ops::{Index, IndexMut};
+use std::vec::Vec;
+use {SplitMutBase, SplitMutError, GetRef, err};
+
+/// A mutable view of all entries in a `qp_trie::Trie` that start with a given prefix.
+///
+/// Point lookups work through `SplitMut` and `GetRef`, just like on the trie itself.
+#[derive(Debug)]
+pub struct TriePrefixMut<'a, K: 'a, V: 'a>(Vec<(&'a K, &'a mut V)>);
+
+impl<'a, K: Borrow<[u8]>, V> TriePrefixMut<'a, K, V> {
+    /// Number of entries in this view.
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Returns true if no entries start with the prefix.
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Iterates over the entries, in key order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(&K, &mut V)> + use<'_, 'a, K, V> {
+        self.0.iter_mut().map(|&mut (k, ref mut v)| (k, &mut **v))
+    }
+
+    fn find(&self, k: &[u8]) -> Option<usize> { self.0.binary_search_by(|e| e.0.borrow().cmp(k)).ok() }
+}
+
+impl<'a, 'b, K: Borrow<[u8]>, V> Index<&'b [u8]> for TriePrefixMut<'a, K, V> {
+    type Output = V;
+    fn index(&self, k: &'b [u8]) -> &V { self.get1_ref(k).expect("no value for key") }
+}
+
+impl<'a, 'b, K: Borrow<[u8]>, V> IndexMut<&'b [u8]> for TriePrefixMut<'a, K, V> {
+    fn index_mut(&mut self, k: &'b [u8]) -> &mut V { self.get1_mut(k).expect("no value for key") }
+}
+
+/// Just add `use splitmut::SplitMutTrie;` to have these methods working on `qp_trie::Trie`.
+pub trait SplitMutTrie<K, V> {
+    /// Returns mutable views of the entries starting with two different prefixes.
+    ///
+    /// If one prefix starts with the other, the subtrees overlap and
+    /// `Err(SplitMutError::SameValue)` is returned.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate qp_trie;
+    /// extern crate splitmut;
+    /// use splitmut::{SplitMut, SplitMutError, SplitMutTrie};
+    ///
+    /// # fn main() {
+    /// let mut t = qp_trie::Trie::new();
+    /// t.insert(b"10.0.0".to_vec(), 1);
+    /// t.insert(b"10.0.1".to_vec(), 2);
+    /// t.insert(b"192.168".to_vec(), 3);
+    /// {
+    ///     let (mut a, mut b) = t.split_prefix2_mut(b"10.", b"192.").unwrap();
+    ///     assert_eq!(a.len(), 2);
+    ///     for (_, v) in a.iter_mut() { *v += 10; }
+    ///     *b.get_muts().at(&b"192.168"[..]).unwrap() += 20;
+    /// }
+    /// assert_eq!(t.get(&b"10.0.1"[..]), Some(&12));
+    /// assert_eq!(t.get(&b"192.168"[..]), Some(&23));
+    /// assert_eq!(t.split_prefix2_mut(b"10.", b"10.0").err(), Some(SplitMutError::SameValue));
+    /// # }
+    /// ```
+    fn split_prefix2_mut(&mut self, a: &[u8], b: &[u8]) -> Result<(TriePrefixMut<'_, K, V>, TriePrefixMut<'_, K, V>), SplitMutError>;
+}
+
+impl<K: Borrow<[u8]>, V> SplitMutTrie<K, V> for Trie<K, V> {
+    fn split_prefix2_mut(&mut self, a: &[u8], b: &[u8]) -> Result<(TriePrefixMut<'_, K, V>, TriePrefixMut<'_, K, V>), SplitMutError> {
+        trace_method!("split_prefix2_mut");
+        if a.starts_with(b) || b.starts_with(a) { return Err(err(SplitMutError::SameValue)) }
+        // Both subtrees are below the common prefix, so one pass over it splits them safely.
+        let common = a.iter().zip(b).take_while(|&(x, y)| x == y).count();
+        let (mut va, mut vb) = (vec!(), vec!());
+        for (k, v) in self.iter_prefix_mut(&a[..common]) {
+            let kb: &[u8] = k.borrow();
+            if kb.starts_with(a) { va.push((k, v)) } else if kb.starts_with(b) { vb.push((k, v)) }
+        }
+        va.sort_by(|x, y| x.0.borrow().cmp(y.0.borrow()));
+        vb.sort_by(|x, y| x.0.borrow().cmp(y.0.borrow()));
+        Ok((TriePrefixMut(va), TriePrefixMut(vb)))
+    }
+}
+
+unsafe impl<'a, K: Borrow<[u8]>, V> SplitMutBase<&'a [u8], V> for Trie<K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: &'a [u8]) -> Option<&mut V> { self.get_mut(k) }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: &'a [u8]) -> &mut V { std::mem::transmute(self.get_mut(k)) }
+}
+
+impl<'a, K: Borrow<[u8]>, V> GetRef<&'a [u8], V> for Trie<K, V> {
+    #[inline]
+    fn get1_ref(&self, k: &'a [u8]) -> Option<&V> { self.get(k) }
+}
+
+unsafe impl<'a, 'b, K: Borrow<[u8]>, V> SplitMutBase<&'b [u8], V> for TriePrefixMut<'a, K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: &'b [u8]) -> Option<&mut V> {
+        let i = self.find(k)?;
+        Some(&mut *self.0[i].1)
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: &'b [u8]) -> &mut V { std::mem::transmute(self.get1_mut(k)) }
+}
+
+impl<'a, 'b, K: Borrow<[u8]>, V> GetRef<&'b [u8], V> for TriePrefixMut<'a, K, V> {
+    #[inline]
+    fn get1_ref(&self, k: &'b [u8]) -> Option<&V> { self.find(k).map(|i| &*self.0[i].1) }
+}
+
+#[test]
+fn trie_prefixes() {
+    use SplitMut;
+    let mut t: Trie<Vec<u8>, u32> = Trie::new();
+    for (i, k) in ["aa", "ab", "abc", "b", "ba"].iter().enumerate() { t.insert(k.as_bytes().to_vec(), i as u32); }
+    assert_eq!(t.get3_mut(&b"ab"[..], &b"x"[..], &b"ab"[..]), (Ok(&mut 1), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue)));
+    ::testing::assert_splitmut_sound(&mut t, &[&b"aa"[..], &b"abc"[..]], &[&b"a"[..]]);
+    {
+        let (mut x, mut y) = t.split_prefix2_mut(b"ab", b"aa").unwrap();
+        assert_eq!(x.len(), 2);
+        assert_eq!(y.len(), 1);
+        ::testing::assert_splitmut_sound(&mut x, &[&b"ab"[..], &b"abc"[..]], &[&b"aa"[..]]);
+        *y.get1_mut(&b"aa"[..]).unwrap() = 6;
+        y[&b"aa"[..]] += 1;
+        assert_eq!(x[&b"abc"[..]], 2);
+    }
+    assert_eq!(t.get(&b"aa"[..]), Some(&7));
+    assert_eq!(t.split_prefix2_mut(b"c", b"").err(), Some(SplitMutError::SameValue));
+}
@@ -0,0 +1,95 @@
+use rand::Rng;
+use rand::seq::IteratorRandom;
+use rand::seq::index;
+use alloc::vec::Vec;
+use alloc::collections::{VecDeque, BTreeMap};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use core::hash::BuildHasher;
+
+/// Random selection of values to mutate.
+///
+/// Just add `use splitmut::SplitMutSample;` to have this working on mutable slices
+/// (and through them, on `Vec`), `VecDeque`, `HashMap` and `BTreeMap`.
+pub trait SplitMutSample<V> {
+    /// Picks `k` distinct values uniformly at random and returns mutable references
+    /// to all of them, or to all values if there are fewer than `k`.
+    ///
+    /// The order of the returned references is not specified.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate rand;
+    /// extern crate splitmut;
+    /// use rand::SeedableRng;
+    /// use splitmut::SplitMutSample;
+    ///
+    /// # fn main() {
+    /// let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+    /// let mut v = vec![0; 10];
+    /// for x in v.sample_mut(3, &mut rng) { *x += 1; }
+    /// assert_eq!(v.iter().sum::<i32>(), 3);
+    /// assert_eq!(v.sample_mut(20, &mut rng).len(), 10);
+    /// # }
+    /// ```
+    fn sample_mut<R: Rng + ?Sized>(&mut self, k: usize, rng: &mut R) -> Vec<&mut V>;
+}
+
+impl<V> SplitMutSample<V> for [V] {
+    fn sample_mut<R: Rng + ?Sized>(&mut self, k: usize, rng: &mut R) -> Vec<&mut V> {
+        trace_method!("sample_mut");
+        let p = self.as_mut_ptr();
+        // The sampled indices are distinct and in range.
+        index::sample(rng, self.len(), k.min(self.len())).into_iter().map(|i| unsafe { &mut *p.add(i) }).collect()
+    }
+}
+
+impl<V> SplitMutSample<V> for VecDeque<V> {
+    fn sample_mut<R: Rng + ?Sized>(&mut self, k: usize, rng: &mut R) -> Vec<&mut V> {
+        trace_method!("sample_mut");
+        let (a, b) = self.as_mut_slices();
+        let (pa, pb, la) = (a.as_mut_ptr(), b.as_mut_ptr(), a.len());
+        let n = la + b.len();
+        index::sample(rng, n, k.min(n)).into_iter()
+            .map(|i| unsafe { &mut *if i < la { pa.add(i) } else { pb.add(i - la) } }).collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S: BuildHasher> SplitMutSample<V> for HashMap<K, V, S> {
+    fn sample_mut<R: Rng + ?Sized>(&mut self, k: usize, rng: &mut R) -> Vec<&mut V> {
+        trace_method!("sample_mut");
+        self.values_mut().choose_multiple(rng, k)
+    }
+}
+
+impl<K, V> SplitMutSample<V> for BTreeMap<K, V> {
+    fn sample_mut<R: Rng + ?Sized>(&mut self, k: usize, rng: &mut R) -> Vec<&mut V> {
+        trace_method!("sample_mut");
+        self.values_mut().choose_multiple(rng, k)
+    }
+}
+
+#[test]
+fn sample_distinct() {
+    use rand::SeedableRng;
+    let mut rng = ::rand::rngs::SmallRng::seed_from_u64(7);
+    let mut d: VecDeque<u32> = (0..8).collect();
+    d.rotate_left(3);
+    for k in 0..10 {
+        let mut s: Vec<u32> = d.sample_mut(k, &mut rng).into_iter().map(|x| *x).collect();
+        s.sort();
+        s.dedup();
+        assert_eq!(s.len(), k.min(8));
+    }
+    #[cfg(feature = "std")] {
+        let mut h: HashMap<u32, u32> = (0..20).map(|i| (i, 0)).collect();
+        for x in h.sample_mut(5, &mut rng) { *x += 1 }
+        assert_eq!(h.values().sum::<u32>(), 5);
+    }
+    let mut b: BTreeMap<u32, u32> = (0..3).map(|i| (i, 0)).collect();
+    assert_eq!(b.sample_mut(5, &mut rng).len(), 3);
+    let mut e: [u8; 0] = [];
+    assert!(e.sample_mut(1, &mut rng).is_empty());
+}
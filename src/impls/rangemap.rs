@@ -0,0 +1,146 @@
+use rangemap::RangeMap;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::vec::Vec;
+use {SplitMutBase, GetRef};
+
+/// A `rangemap::RangeMap` whose values can be split.
+///
+/// `RangeMap` itself has no mutable access to its values, since adjacent ranges
+/// with equal values are merged. This wrapper instead stores a slot number for
+/// every inserted range, and keeps the values by slot. If an insert or remove
+/// splits an existing range in two, both parts keep referring to the same value.
+///
+/// Values can be looked up by points (`&K`), which give the value of the range
+/// containing them, or by ranges (`&Range<K>`), which must be completely covered
+/// by a single stored range. Two keys resolving to the same stored value give
+/// `Err(SplitMutError::SameValue)`, which includes overlapping ranges.
+///
+/// # Example
+/// ```
+/// extern crate splitmut;
+/// use splitmut::{SplitMut, SplitMutError, RangeValueMap};
+///
+/// # fn main() {
+/// let mut m = RangeValueMap::new();
+/// m.insert(0..10, "low".to_string());
+/// m.insert(10..20, "high".to_string());
+/// {
+///     let (a, b) = m.get2_mut(&2, &15);
+///     a.unwrap().push_str("er");
+///     b.unwrap().push_str("er");
+/// }
+/// assert_eq!(m.get(&3).map(|s| &**s), Some("lower"));
+/// assert_eq!(m.get2_mut(&(2..5), &(4..8)).1, Err(SplitMutError::SameValue));
+/// assert_eq!(m.get2_mut(&(8..12), &(15..20)).0, Err(SplitMutError::NoValue));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RangeValueMap<K, V> { ranges: RangeMap<K, usize>, values: HashMap<usize, (Range<K>, V)>, next: usize }
+
+impl<K: Ord + Clone, V> Default for RangeValueMap<K, V> {
+    fn default() -> Self { RangeValueMap { ranges: RangeMap::new(), values: HashMap::new(), next: 0 } }
+}
+
+impl<K: Ord + Clone, V> RangeValueMap<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self { Self::default() }
+
+    /// The stored ranges, with the slot number of each value.
+    pub fn ranges(&self) -> &RangeMap<K, usize> { &self.ranges }
+
+    /// Inserts a value for a range, overwriting what was there before.
+    /// Empty ranges are ignored.
+    pub fn insert(&mut self, range: Range<K>, value: V) {
+        if range.start >= range.end { return }
+        let slot = self.next;
+        self.next += 1;
+        self.values.insert(slot, (range.clone(), value));
+        self.change(range.clone(), |r| r.insert(range, slot));
+    }
+
+    /// Removes a range; values that no longer have any range left are dropped.
+    pub fn remove(&mut self, range: Range<K>) {
+        if range.start >= range.end { return }
+        self.change(range.clone(), |r| r.remove(range));
+    }
+
+    /// Returns the value of the range containing the key.
+    pub fn get(&self, key: &K) -> Option<&V> { self.values.get(self.ranges.get(key)?).map(|x| &x.1) }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize { self.values.len() }
+
+    /// Returns true if there are no values.
+    pub fn is_empty(&self) -> bool { self.values.is_empty() }
+
+    fn change<F: FnOnce(&mut RangeMap<K, usize>)>(&mut self, range: Range<K>, f: F) {
+        // Only values of ranges overlapping the changed range can lose all their ranges,
+        // and whatever is left of a value lies within the range it was inserted with.
+        let affected: Vec<usize> = self.ranges.overlapping(&range).map(|(_, &s)| s).collect();
+        f(&mut self.ranges);
+        for s in affected {
+            let gone = match self.values.get(&s) {
+                Some((orig, _)) => !self.ranges.overlapping(orig).any(|(_, &t)| t == s),
+                None => false,
+            };
+            if gone { self.values.remove(&s); }
+        }
+    }
+
+    fn slot_of_range(&self, r: &Range<K>) -> Option<&usize> {
+        let (stored, slot) = self.ranges.get_key_value(&r.start)?;
+        if r.end <= stored.end { Some(slot) } else { None }
+    }
+}
+
+unsafe impl<'a, K: Ord + Clone, V> SplitMutBase<&'a K, V> for RangeValueMap<K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: &'a K) -> Option<&mut V> {
+        let slot = *self.ranges.get(k)?;
+        self.values.get_mut(&slot).map(|x| &mut x.1)
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: &'a K) -> &mut V { std::mem::transmute(self.get1_mut(k)) }
+}
+
+unsafe impl<'a, K: Ord + Clone, V> SplitMutBase<&'a Range<K>, V> for RangeValueMap<K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: &'a Range<K>) -> Option<&mut V> {
+        let slot = *self.slot_of_range(k)?;
+        self.values.get_mut(&slot).map(|x| &mut x.1)
+    }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: &'a Range<K>) -> &mut V { std::mem::transmute(self.get1_mut(k)) }
+}
+
+impl<'a, K: Ord + Clone, V> GetRef<&'a K, V> for RangeValueMap<K, V> {
+    #[inline]
+    fn get1_ref(&self, k: &'a K) -> Option<&V> { self.get(k) }
+}
+
+impl<'a, K: Ord + Clone, V> GetRef<&'a Range<K>, V> for RangeValueMap<K, V> {
+    #[inline]
+    fn get1_ref(&self, k: &'a Range<K>) -> Option<&V> { self.values.get(self.slot_of_range(k)?).map(|x| &x.1) }
+}
+
+#[test]
+fn range_value_map() {
+    use {SplitMut, SplitMutError};
+    let mut m = RangeValueMap::new();
+    m.insert(0..10, 'a');
+    m.insert(4..6, 'b');
+    // 0..4 and 6..10 still share 'a'
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.get3_mut(&1, &4, &8), (Ok(&mut 'a'), Ok(&mut 'b'), Err(SplitMutError::SameValue)));
+    assert_eq!(m.get2_mut(&(0..4), &(4..6)), (Ok(&mut 'a'), Ok(&mut 'b')));
+    m.remove(0..4);
+    assert_eq!(m.len(), 2);
+    m.remove(5..10);
+    assert_eq!(m.len(), 1);
+    assert_eq!(m.get(&4), Some(&'b'));
+    m.insert(0..10, 'c');
+    assert_eq!(m.len(), 1);
+    ::testing::assert_splitmut_sound(&mut m, &[&0, &9], &[&10]);
+    ::testing::assert_splitmut_sound(&mut m, &[&(0..10)], &[&(5..11)]);
+}
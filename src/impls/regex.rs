@@ -0,0 +1,75 @@
+use regex::Regex;
+use std::borrow::Borrow;
+use std::collections::{HashMap, BTreeMap, hash_map, btree_map};
+
+/// Just add `use splitmut::SplitMutMatching;` to have this working on
+/// `HashMap` and `BTreeMap` with string keys.
+pub trait SplitMutMatching<K, V> {
+    /// The iterator over all entries of the map, used internally.
+    type IterMut<'a>: Iterator<Item=(&'a K, &'a mut V)> where Self: 'a, K: 'a, V: 'a;
+
+    /// Returns an iterator over all entries whose key matches the regular expression,
+    /// with the values mutable. Other entries are left untouched.
+    ///
+    /// As with `Regex::is_match`, the expression can match anywhere in the key,
+    /// unless anchored with `^` and `$`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate regex;
+    /// extern crate splitmut;
+    /// use std::collections::BTreeMap;
+    /// use splitmut::SplitMutMatching;
+    ///
+    /// # fn main() {
+    /// let mut m: BTreeMap<String, u32> = BTreeMap::new();
+    /// for k in ["net.timeout", "net.retries", "ui.timeout"] { m.insert(k.into(), 1); }
+    /// let re = regex::Regex::new(r"^net\.").unwrap();
+    /// let mut keys = vec![];
+    /// for (k, v) in m.get_matching_mut(&re) {
+    ///     *v *= 10;
+    ///     keys.push(k.clone());
+    /// }
+    /// assert_eq!(keys, ["net.retries", "net.timeout"]);
+    /// assert_eq!(m["ui.timeout"], 1);
+    /// # }
+    /// ```
+    fn get_matching_mut<'a, 'r>(&'a mut self, re: &'r Regex) -> MatchingMut<'r, Self::IterMut<'a>>;
+}
+
+/// Iterator returned from the get_matching_mut function.
+pub struct MatchingMut<'r, I> { i: I, re: &'r Regex }
+
+impl<'r, 'a, K: 'a + Borrow<str>, V: 'a, I: Iterator<Item=(&'a K, &'a mut V)>> Iterator for MatchingMut<'r, I> {
+    type Item = (&'a K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let re = self.re;
+        self.i.find(|(k, _)| re.is_match((*k).borrow()))
+    }
+}
+
+impl<K: Borrow<str>, V, S> SplitMutMatching<K, V> for HashMap<K, V, S> {
+    type IterMut<'a> = hash_map::IterMut<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    fn get_matching_mut<'a, 'r>(&'a mut self, re: &'r Regex) -> MatchingMut<'r, Self::IterMut<'a>> {
+        trace_method!("get_matching_mut");
+        MatchingMut { i: self.iter_mut(), re }
+    }
+}
+
+impl<K: Borrow<str>, V> SplitMutMatching<K, V> for BTreeMap<K, V> {
+    type IterMut<'a> = btree_map::IterMut<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    fn get_matching_mut<'a, 'r>(&'a mut self, re: &'r Regex) -> MatchingMut<'r, Self::IterMut<'a>> {
+        trace_method!("get_matching_mut");
+        MatchingMut { i: self.iter_mut(), re }
+    }
+}
+
+#[test]
+fn matching_hashmap() {
+    let mut h: HashMap<&str, u32> = vec![("/api/users", 0), ("/api/posts", 0), ("/static", 0)].into_iter().collect();
+    let re = Regex::new("^/api/").unwrap();
+    assert_eq!(h.get_matching_mut(&re).map(|(_, v)| *v += 1).count(), 2);
+    assert_eq!(h["/static"], 0);
+    assert_eq!(h["/api/posts"], 1);
+    assert_eq!(h.get_matching_mut(&Regex::new("nothing").unwrap()).count(), 0);
+}
@@ -0,0 +1,87 @@
+use roaring::RoaringBitmap;
+use roaring::bitmap::Iter;
+use alloc::collections::VecDeque;
+use core::mem;
+use {SplitMutError, err};
+
+/// Just add `use splitmut::SplitMutBitmap;` to have this working on mutable slices
+/// (and through them, on `Vec`) and `VecDeque`.
+pub trait SplitMutBitmap<V> {
+    /// Returns an iterator over the values at the indices in the bitmap, with the
+    /// values mutable.
+    ///
+    /// A bitmap is a set, so no values need to be tracked: values are visited in
+    /// index order, each one at most once. Indices past the end give
+    /// `Err(SplitMutError::NoValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate roaring;
+    /// extern crate splitmut;
+    /// use roaring::RoaringBitmap;
+    /// use splitmut::{SplitMutBitmap, SplitMutError};
+    ///
+    /// # fn main() {
+    /// let mut v = vec![0; 6];
+    /// let rows: RoaringBitmap = [1, 4, 9].iter().collect();
+    /// let mut i = v.get_bitmap_mut(&rows);
+    /// *i.next().unwrap().unwrap() = 1;
+    /// *i.next().unwrap().unwrap() = 4;
+    /// assert_eq!(i.next(), Some(Err(SplitMutError::NoValue)));
+    /// assert_eq!(v, [0, 1, 0, 0, 4, 0]);
+    /// # }
+    /// ```
+    fn get_bitmap_mut<'a>(&'a mut self, keys: &'a RoaringBitmap) -> BitmapMut<'a, V>;
+}
+
+/// Iterator returned from the get_bitmap_mut function.
+pub struct BitmapMut<'a, V: 'a> { a: &'a mut [V], b: &'a mut [V], pos: usize, i: Iter<'a> }
+
+impl<'a, V: 'a> Iterator for BitmapMut<'a, V> {
+    type Item = Result<&'a mut V, SplitMutError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let k = self.i.next()? as usize;
+        // The indices are increasing, so everything before `k` can be dropped.
+        let mut skip = k - self.pos;
+        self.pos = k + 1;
+        if skip >= self.a.len() {
+            skip -= self.a.len();
+            self.a = mem::take(&mut self.b);
+        }
+        if skip >= self.a.len() {
+            self.a = &mut [];
+            return Some(Err(err(SplitMutError::NoValue)));
+        }
+        let (x, rest) = mem::take(&mut self.a)[skip..].split_first_mut().unwrap();
+        self.a = rest;
+        Some(Ok(x))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { self.i.size_hint() }
+}
+
+impl<V> SplitMutBitmap<V> for [V] {
+    fn get_bitmap_mut<'a>(&'a mut self, keys: &'a RoaringBitmap) -> BitmapMut<'a, V> {
+        trace_method!("get_bitmap_mut");
+        BitmapMut { a: self, b: &mut [], pos: 0, i: keys.iter() }
+    }
+}
+
+impl<V> SplitMutBitmap<V> for VecDeque<V> {
+    fn get_bitmap_mut<'a>(&'a mut self, keys: &'a RoaringBitmap) -> BitmapMut<'a, V> {
+        trace_method!("get_bitmap_mut");
+        let (a, b) = self.as_mut_slices();
+        BitmapMut { a, b, pos: 0, i: keys.iter() }
+    }
+}
+
+#[test]
+fn bitmap_deque() {
+    let mut d: VecDeque<u32> = (0..10).collect();
+    d.rotate_left(4);
+    let keys: RoaringBitmap = [0, 5, 6, 9, 10, 100].iter().collect();
+    let r: alloc::vec::Vec<_> = d.get_bitmap_mut(&keys).map(|x| x.map(|x| *x)).collect();
+    assert_eq!(r, [Ok(4), Ok(9), Ok(0), Ok(3), Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)]);
+    for x in d.get_bitmap_mut(&keys).flatten() { *x = 0 }
+    assert_eq!(d.iter().filter(|&&x| x == 0).count(), 4);
+    assert_eq!(d.get_bitmap_mut(&RoaringBitmap::new()).count(), 0);
+}
@@ -0,0 +1,100 @@
+//! Joining several collections on a shared key.
+
+use {SplitMut, SplitMutError, GetMuts};
+
+macro_rules! join {
+    ($(#[$attr: meta])* $name: ident, $fname: ident; $($c: ident $cv: ident $ct: ident),+) => {
+        /// Iterator returned from the
+        #[doc = concat!("`", stringify!($fname), "`")]
+        /// function.
+        pub struct $name<'a, K, I, $($cv: 'a, $ct: 'a + SplitMut<K, $cv> + ?Sized),+> {
+            i: I,
+            $($c: GetMuts<'a, K, $cv, $ct>),+
+        }
+
+        impl<'a, K: Clone, V: 'a, I: Iterator<Item=(K, &'a mut V)>, $($cv: 'a, $ct: 'a + SplitMut<K, $cv> + ?Sized),+> Iterator for $name<'a, K, I, $($cv, $ct),+> {
+            type Item = (K, &'a mut V, $(Result<&'a mut $cv, SplitMutError>),+);
+            fn next(&mut self) -> Option<Self::Item> {
+                let (k, v) = self.i.next()?;
+                $(let $c = self.$c.at(k.clone());)+
+                Some((k, v, $($c),+))
+            }
+        }
+
+        impl<'a, K: Clone, V: 'a, I: Iterator<Item=(K, &'a mut V)>, $($cv: 'a, $ct: 'a + SplitMut<K, $cv> + ?Sized),+> $name<'a, K, I, $($cv, $ct),+> {
+            /// Skips keys that are missing in any of the collections, yielding
+            /// only the entries present in all of them.
+            pub fn all(self) -> impl Iterator<Item=(K, &'a mut V, $(&'a mut $cv),+)> {
+                self.filter_map(|(k, v, $($c),+)| Some((k, v, $($c.ok()?),+)))
+            }
+        }
+
+        $(#[$attr])*
+        pub fn $fname<'a, K: Clone, V: 'a, I: IntoIterator<Item=(K, &'a mut V)>, $($cv: 'a, $ct: 'a + SplitMut<K, $cv> + ?Sized),+>(
+            i: I, $($c: &'a mut $ct),+) -> $name<'a, K, I::IntoIter, $($cv, $ct),+> {
+            $name { i: i.into_iter(), $($c: $c.get_muts()),+ }
+        }
+    }
+}
+
+join!(
+    /// Joins the entries of a collection with the values of other collections
+    /// that have the same key.
+    ///
+    /// For every `(key, value)` pair of `a` (typically from `iter_mut`), the iterator yields
+    /// the key, the value, and the result of looking up the key in `b`.
+    /// Use `all` to skip keys that are missing in `b`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::{join2_mut, SplitMutError};
+    ///
+    /// let mut pos: HashMap<_, _> = vec![(1, 0.0), (2, 5.0)].into_iter().collect();
+    /// let mut vel: HashMap<_, _> = vec![(1, 1.5)].into_iter().collect();
+    /// for (_, p, v) in join2_mut(pos.iter_mut(), &mut vel).all() {
+    ///     *p += *v;
+    /// }
+    /// assert_eq!(pos[&1], 1.5);
+    /// let missing: Vec<_> = join2_mut(pos.iter_mut(), &mut vel).filter_map(|(k, _, v)| v.err().map(|e| (*k, e))).collect();
+    /// assert_eq!(missing, [(2, SplitMutError::NoValue)]);
+    /// ```
+    Join2, join2_mut; b V2 B);
+join!(
+    /// Like `join2_mut`, but joins with two other collections.
+    Join3, join3_mut; b V2 B, c V3 C);
+join!(
+    /// Like `join2_mut`, but joins with three other collections.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use splitmut::join4_mut;
+    ///
+    /// let mut a: BTreeMap<_, _> = (0..5).map(|i| (i, i)).collect();
+    /// let mut b: BTreeMap<_, _> = (1..5).map(|i| (i, i * 10)).collect();
+    /// let mut c: BTreeMap<_, _> = (0..4).map(|i| (i, i * 100)).collect();
+    /// let mut sum: BTreeMap<_, _> = (0..5).map(|i| (i, 0)).collect();
+    /// for (_, a, b, c, s) in join4_mut(a.iter_mut(), &mut b, &mut c, &mut sum).all() {
+    ///     *s = *a + *b + *c;
+    /// }
+    /// assert_eq!(sum.values().collect::<Vec<_>>(), [&0, &111, &222, &333, &0]);
+    /// ```
+    Join4, join4_mut; b V2 B, c V3 C, d V4 D);
+
+#[test]
+fn join3() {
+    use std::vec::Vec;
+    use std::collections::HashMap;
+    let mut a: HashMap<u8, u8> = (0..6).map(|i| (i, i)).collect();
+    let mut b: HashMap<u8, u16> = (2..8).map(|i| (i, i as u16)).collect();
+    let mut c: HashMap<u8, u32> = (0..4).map(|i| (i, i as u32)).collect();
+    let mut got: Vec<_> = join3_mut(a.iter_mut(), &mut b, &mut c).all().map(|(&k, a, b, c)| {
+        *b += 10; *c += 100;
+        (k, *a, *b, *c)
+    }).collect();
+    got.sort();
+    assert_eq!(got, [(2, 2, 12, 102), (3, 3, 13, 103)]);
+    let n = join3_mut(a.iter_mut(), &mut b, &mut c).filter(|j| j.2 == Err(SplitMutError::NoValue)).count();
+    assert_eq!(n, 2);
+}
@@ -0,0 +1,170 @@
+//! Splitting collections whose values are handed out through guards or proxies.
+
+use core::cell::{RefCell, RefMut};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard};
+use {SplitMut, SplitMutError, err};
+
+/// Like `SplitMut`, but the references handed out are of type `RefMut<'a>`, which
+/// can be a guard (e g `core::cell::RefMut` or `MutexGuard`) or a proxy object
+/// instead of a bare `&mut V`.
+///
+/// Every `SplitMut` collection is also a `SplitMutLend` collection, with `&'a mut V`
+/// as its `RefMut`. In addition, it is implemented for `CellSlice` and (with the `std`
+/// feature) `MutexSlice`, which hand out guards from shared slices. For those two, the
+/// `V` parameter is the cell type (`RefCell<V>` or `Mutex<V>`), which keeps them apart from
+/// the `SplitMut` implementations.
+///
+/// # Example
+/// ```
+/// use std::cell::RefCell;
+/// use splitmut::{SplitMutError, SplitMutLend, CellSlice};
+///
+/// let v = vec![RefCell::new(1), RefCell::new(2)];
+/// let mut s = CellSlice(&v);
+/// {
+///     let (a, b) = s.lend2_mut(0, 1);
+///     std::mem::swap(&mut *a.unwrap(), &mut *b.unwrap());
+/// }
+/// assert_eq!(*v[0].borrow(), 2);
+/// let _keep = v[1].borrow();
+/// assert_eq!(s.lend2_mut(0, 1).1.err(), Some(SplitMutError::SameValue));
+/// ```
+///
+/// # Safety
+/// `lend_raw` must return the same slot for two keys only if they refer to the same
+/// value, and values for different slots must be usable at the same time.
+pub unsafe trait SplitMutLend<K, V> {
+    /// The reference type handed out for a value.
+    type RefMut<'a> where Self: 'a, V: 'a;
+    /// What `lend_raw` returns, before it is turned into a `RefMut`.
+    type Raw;
+
+    /// Looks up a key, and returns an identifier for the slot together with
+    /// something that `lend_from_raw` turns into a `RefMut`.
+    ///
+    /// Returns `Err(SplitMutError::SameValue)` if the value cannot be lent out
+    /// because it is already in use.
+    fn lend_raw(&mut self, k: K) -> Result<(usize, Self::Raw), SplitMutError>;
+
+    /// Turns the result of `lend_raw` into a `RefMut`.
+    ///
+    /// # Safety
+    /// `raw` must come from `lend_raw` on a collection borrowed for `'a`, and no other
+    /// `RefMut` or `Raw` for the same slot may be alive.
+    unsafe fn lend_from_raw<'a>(raw: Self::Raw) -> Self::RefMut<'a> where Self: 'a, V: 'a;
+
+    /// Returns a single value.
+    fn lend1_mut(&mut self, k: K) -> Result<Self::RefMut<'_>, SplitMutError> {
+        trace_method!("lend1_mut");
+        self.lend_raw(k).map(|(_, r)| unsafe { Self::lend_from_raw(r) })
+    }
+
+    /// Returns two values, giving `Err(SplitMutError::SameValue)` for the second
+    /// one if both keys refer to the same slot.
+    fn lend2_mut(&mut self, k1: K, k2: K) -> (Result<Self::RefMut<'_>, SplitMutError>, Result<Self::RefMut<'_>, SplitMutError>) {
+        trace_method!("lend2_mut");
+        let p1 = self.lend_raw(k1);
+        let p2 = lend_check(&p1, self.lend_raw(k2));
+        unsafe { (lend_r::<K, V, Self>(p1), lend_r::<K, V, Self>(p2)) }
+    }
+
+    /// Returns three values, giving `Err(SplitMutError::SameValue)` for keys
+    /// referring to a slot already returned.
+    fn lend3_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<Self::RefMut<'_>, SplitMutError>, Result<Self::RefMut<'_>, SplitMutError>, Result<Self::RefMut<'_>, SplitMutError>) {
+        trace_method!("lend3_mut");
+        let p1 = self.lend_raw(k1);
+        let p2 = lend_check(&p1, self.lend_raw(k2));
+        let p3 = lend_check(&p1, self.lend_raw(k3));
+        let p3 = lend_check(&p2, p3);
+        unsafe { (lend_r::<K, V, Self>(p1), lend_r::<K, V, Self>(p2), lend_r::<K, V, Self>(p3)) }
+    }
+}
+
+#[inline]
+fn lend_check<T>(a: &Result<(usize, T), SplitMutError>, b: Result<(usize, T), SplitMutError>) -> Result<(usize, T), SplitMutError> {
+    if let (Ok(aa), Ok(bb)) = (a, &b) {
+        if aa.0 == bb.0 { return Err(err(SplitMutError::SameValue)) }
+    }
+    b
+}
+
+#[inline]
+unsafe fn lend_r<'a, K, V, A: SplitMutLend<K, V> + ?Sized + 'a>(r: Result<(usize, A::Raw), SplitMutError>) -> Result<A::RefMut<'a>, SplitMutError> {
+    r.map(|(_, raw)| A::lend_from_raw(raw))
+}
+
+unsafe impl<K, V, A: SplitMut<K, V> + ?Sized> SplitMutLend<K, V> for A {
+    type RefMut<'a> = &'a mut V where Self: 'a, V: 'a;
+    type Raw = *mut V;
+
+    #[inline]
+    fn lend_raw(&mut self, k: K) -> Result<(usize, *mut V), SplitMutError> {
+        self.get1_mut(k).map(|v| (v as *mut V as usize, v as *mut V)).ok_or_else(|| err(SplitMutError::NoValue))
+    }
+
+    #[inline]
+    unsafe fn lend_from_raw<'a>(raw: *mut V) -> &'a mut V where Self: 'a, V: 'a { &mut *raw }
+}
+
+/// A shared slice of `RefCell`s, lending out `RefMut` guards.
+///
+/// A value that is already borrowed elsewhere gives `Err(SplitMutError::SameValue)`.
+#[derive(Debug, Clone, Copy)]
+pub struct CellSlice<'s, V: 's>(pub &'s [RefCell<V>]);
+
+unsafe impl<'s, V> SplitMutLend<usize, RefCell<V>> for CellSlice<'s, V> {
+    type RefMut<'a> = RefMut<'s, V> where Self: 'a, RefCell<V>: 'a;
+    type Raw = &'s RefCell<V>;
+
+    fn lend_raw(&mut self, k: usize) -> Result<(usize, &'s RefCell<V>), SplitMutError> {
+        let c = self.0.get(k).ok_or_else(|| err(SplitMutError::NoValue))?;
+        if c.try_borrow_mut().is_err() { return Err(err(SplitMutError::SameValue)) }
+        Ok((c as *const _ as usize, c))
+    }
+
+    unsafe fn lend_from_raw<'a>(raw: &'s RefCell<V>) -> RefMut<'s, V> where Self: 'a, RefCell<V>: 'a { raw.borrow_mut() }
+}
+
+/// A shared slice of `Mutex`es, lending out `MutexGuard`s.
+///
+/// The mutexes are locked when the values are returned, waiting for other threads
+/// to release them. Poisoning is ignored.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct MutexSlice<'s, V: 's>(pub &'s [Mutex<V>]);
+
+#[cfg(feature = "std")]
+unsafe impl<'s, V> SplitMutLend<usize, Mutex<V>> for MutexSlice<'s, V> {
+    type RefMut<'a> = MutexGuard<'s, V> where Self: 'a, Mutex<V>: 'a;
+    type Raw = &'s Mutex<V>;
+
+    fn lend_raw(&mut self, k: usize) -> Result<(usize, &'s Mutex<V>), SplitMutError> {
+        let c = self.0.get(k).ok_or_else(|| err(SplitMutError::NoValue))?;
+        Ok((c as *const _ as usize, c))
+    }
+
+    unsafe fn lend_from_raw<'a>(raw: &'s Mutex<V>) -> MutexGuard<'s, V> where Self: 'a, Mutex<V>: 'a { raw.lock().unwrap_or_else(|e| e.into_inner()) }
+}
+
+#[test]
+fn lend_plain() {
+    let mut v = vec![1, 2, 3];
+    let (a, b, c) = v.lend3_mut(2, 0, 2);
+    assert_eq!((a, b, c), (Ok(&mut 3), Ok(&mut 1), Err(SplitMutError::SameValue)));
+    assert_eq!(v.lend1_mut(3), Err(SplitMutError::NoValue));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn lend_mutex() {
+    use std::vec::Vec;
+    let v: Vec<_> = (0..3).map(Mutex::new).collect();
+    let mut s = MutexSlice(&v);
+    let (a, b, c) = s.lend3_mut(1, 2, 1);
+    assert!(c.is_err());
+    let (mut a, mut b) = (a.unwrap(), b.unwrap());
+    std::mem::swap(&mut *a, &mut *b);
+    drop((a, b));
+    assert_eq!(*v[2].lock().unwrap(), 1);
+}
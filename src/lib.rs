@@ -8,6 +8,20 @@
 //! If you need more than four values, you can use `get_muts` or `get_mut_iter` to get as many mutable
 //! values as you like.
 //!
+//! Integrations with other crates are behind cargo features, see the README for a list.
+//! With `default-features = false`, the crate is `no_std` (it still needs `alloc`). What needs the
+//! `std` feature is everything that keeps track of returned values in a hash set (`get_muts`,
+//! `get_mut_iter` and the other methods built on them, `apply_n`, `OwnedGetMuts`, `Guarded`),
+//! the locking types (`SyncGetMuts`, `SplitRwLock`, `SplitMutLock`, `MutexSlice`), `join2_mut`
+//! and friends, `ReadView`, `SecondaryIndex`, `SplitMutInsert`, `SplitMutGetOrInsert` and the `HashMap` impls.
+//! The tuple getters, the adapters, and the slice, `BTreeMap`, transaction, grid, CSR,
+//! sorted and uninit helpers all work without it.
+//!
+//! The `safe` feature implements `get2_mut`, `get3_mut` and `get4_mut` for slices, `Vec`,
+//! `VecDeque`, `HashMap` and `BTreeMap` without unsafe code. It covers only these: `get_muts`,
+//! `get_mut_iter`, `get5_mut` to `get8_mut`, `get_disjoint_mut`, the `*_maybe_mut` methods and
+//! all other collections still use raw pointers.
+//!
 //! # Example
 //! ```
 //! use std::collections::HashMap;
@@ -38,10 +52,215 @@
 //!
 
 #![warn(missing_docs)]
+#![allow(clippy::type_complexity)]
+#![no_std]
+
+extern crate alloc;
+// Always `no_std`, so that `core` is at the crate root; tests use std also without the std feature.
+#[cfg(any(feature = "std", test))]
+#[macro_use]
+extern crate std;
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use ptrset::{Tracker, PtrSet};
+#[cfg(feature = "std")]
+use std::hash;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::borrow;
+
+#[macro_use]
+mod macros;
+#[cfg(feature = "safe")]
+#[macro_use]
+mod safe;
+
+#[cfg(feature = "std")]
+mod ptrset;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "std")]
+mod guard;
+#[cfg(feature = "std")]
+mod join;
+#[cfg(feature = "std")]
+mod view;
+#[cfg(feature = "std")]
+mod secondary;
+#[cfg(feature = "std")]
+mod owned;
+#[cfg(feature = "std")]
+mod lock;
+mod tuple;
+mod adapter;
+mod slice;
+mod btree;
+mod any;
+mod rc;
+mod modify;
+mod lend;
+mod transaction;
+mod diff;
+mod plan;
+mod grid;
+mod csr;
+mod uninit;
+mod sorted;
+#[cfg(feature = "const-fn")]
+mod constfn;
+
+#[cfg(feature = "std")]
+pub use sync::{SyncGetMuts, SplitRwLock, SplitWriteGuard, SyncLookup};
+#[cfg(feature = "std")]
+pub use guard::{Guarded, BorrowGuard, Observed, ObservedGuard};
+#[cfg(feature = "std")]
+pub use join::{join2_mut, join3_mut, join4_mut, Join2, Join3, Join4};
+#[cfg(feature = "std")]
+pub use view::{ReadView, CollectionLen};
+#[cfg(feature = "std")]
+pub use secondary::SecondaryIndex;
+#[cfg(feature = "std")]
+pub use owned::OwnedGetMuts;
+#[cfg(feature = "std")]
+pub use lock::{SplitMutLock, LockMode, ManyGuard};
+
+#[cfg(feature = "stats")]
+pub mod stats;
+
+pub mod testing;
+pub mod prelude;
+pub use tuple::KeyTuple;
+pub use adapter::{IndexSplit, RelativeIndex, WrappingIndex, TypedIndex, KeyAdapter};
+pub use slice::{SplitMutSlice, PairsCombinations, AlignedMut, TwoPointer};
+pub use btree::{SplitMutBTree, BTreeKeyCursors, BTreePrefixMut};
+pub use any::SplitMutAny;
+pub use rc::{SplitMutRc, SplitMutArc};
+pub use modify::{SplitMutRetain, SplitMutRemove};
+pub use lend::{SplitMutLend, CellSlice};
+pub use transaction::Transaction;
+pub use diff::{DiffSession, Change};
+pub use plan::{AccessPlan, Tracked};
+pub use grid::{Grid2D, ColumnMut};
+pub use csr::{CsrMatrix, CsrRowMut};
+pub use uninit::{InitBuffer, InitRegion};
+pub use sorted::SortedVecMap;
+#[doc(hidden)]
+pub use macros::__private;
+#[cfg(feature = "const-fn")]
+pub use constfn::{const_get2_mut, const_get3_mut, const_get4_mut};
+#[cfg(feature = "std")]
+pub use lend::MutexSlice;
+#[cfg(feature = "std")]
+pub use modify::{SplitMutInsert, SplitMutGetOrInsert};
+
+#[cfg(feature = "qcell")]
+extern crate qcell;
+
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+#[cfg(feature = "hashlink")]
+extern crate hashlink;
+
+#[cfg(feature = "intmap")]
+extern crate intmap;
+
+#[cfg(feature = "bimap")]
+extern crate bimap;
+
+#[cfg(feature = "rangemap")]
+extern crate rangemap;
+
+#[cfg(feature = "qp-trie")]
+extern crate qp_trie;
+
+#[cfg(feature = "indextree")]
+extern crate indextree;
+
+#[cfg(feature = "bumpalo")]
+extern crate bumpalo;
+
+#[cfg(feature = "hecs")]
+extern crate hecs;
+
+#[cfg(feature = "rand")]
+extern crate rand;
+
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "roaring")]
+extern crate roaring;
+#[cfg(feature = "checked")]
+extern crate splitmut_derive;
+#[cfg(all(test, feature = "checked"))]
+extern crate self as splitmut;
+
+/// Rewrites a function taking several `&mut c[k]` from the same collection
+/// in one statement, so that it compiles.
+///
+/// Within each statement, all `&mut c[k]` of a collection `c` that occurs more than once
+/// are replaced with one `splitmut!(c; k...)` lookup before the statement. The collection
+/// is recognized by how it is written, so `v` and `self.v` are different collections.
+/// By default, the function panics if a key is missing or two keys give the same value.
+/// With `#[checked(result)]`, it returns `Err` instead, converted from a `KeyError`
+/// with `From`, so the function should return something like `Result<_, SplitMutError>`.
+///
+/// The keys are evaluated before the statement, so only expressions that always run
+/// are rewritten: not those in blocks, closures, match arms, macros or after `&&` and `||`.
+///
+/// # Example
+/// ```
+/// extern crate splitmut;
+/// use splitmut::{checked, SplitMutError};
+///
+/// fn add_to(a: &mut u32, b: &mut u32) { *a += *b; *b = 0 }
+///
+/// #[checked]
+/// fn add(v: &mut Vec<u32>, i: usize, j: usize) {
+///     add_to(&mut v[i], &mut v[j]);
+/// }
+///
+/// #[checked(result)]
+/// fn swap(v: &mut [u32], i: usize, j: usize) -> Result<(), SplitMutError> {
+///     std::mem::swap(&mut v[i], &mut v[j]);
+///     Ok(())
+/// }
+///
+/// # fn main() {
+/// let mut v = vec![1, 2, 3];
+/// add(&mut v, 0, 2);
+/// assert_eq!(swap(&mut v, 0, 1), Ok(()));
+/// assert_eq!(v, [2, 4, 0]);
+/// assert_eq!(swap(&mut v, 1, 1), Err(SplitMutError::SameValue));
+/// assert_eq!(swap(&mut v, 1, 3), Err(SplitMutError::NoValue));
+/// # }
+/// ```
+#[cfg(feature = "checked")]
+pub use splitmut_derive::checked;
+
+mod impls;
+
+#[cfg(feature = "bimap")]
+pub use impls::{BiValueMap, BiKey};
+#[cfg(feature = "rangemap")]
+pub use impls::RangeValueMap;
+#[cfg(feature = "qp-trie")]
+pub use impls::{SplitMutTrie, TriePrefixMut};
+#[cfg(feature = "rand")]
+pub use impls::SplitMutSample;
+#[cfg(feature = "regex")]
+pub use impls::{SplitMutMatching, MatchingMut};
+#[cfg(feature = "roaring")]
+pub use impls::{SplitMutBitmap, BitmapMut};
 
-use std::collections::{HashMap, BTreeMap, HashSet, VecDeque};
-use std::marker::PhantomData;
-use std::{hash, borrow};
+#[cfg(feature = "qcell")]
+mod cell;
+
+#[cfg(feature = "qcell")]
+pub use cell::SplitCell;
 
 /// Error returned from get*_mut functions.
 #[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
@@ -50,141 +269,993 @@ pub enum SplitMutError {
     NoValue,
     /// The same value has already been returned (earlier in the same tuple)
     SameValue,
+    /// The value was found, but is not of the requested type (when downcasting)
+    WrongType,
+    /// The value was found, but other pointers share it (when reaching into an `Rc`)
+    Shared,
+    /// More than one value was found (when looking up by something else than the key)
+    Ambiguous,
+    /// The value is locked by someone else (when trying to lock without waiting)
+    WouldBlock,
 }
 
-impl std::error::Error for SplitMutError {
-    fn description(&self) -> &'static str {
-         match self {
-              &SplitMutError::NoValue => "No value",
-              &SplitMutError::SameValue => "Duplicate values",
+impl SplitMutError {
+    fn as_str(&self) -> &'static str {
+         match *self {
+              SplitMutError::NoValue => "No value",
+              SplitMutError::SameValue => "Duplicate values",
+              SplitMutError::WrongType => "Wrong type",
+              SplitMutError::Shared => "Shared value",
+              SplitMutError::Ambiguous => "Ambiguous value",
+              SplitMutError::WouldBlock => "Value is locked",
          }
     }
+
+    /// Returns true for `SplitMutError::NoValue`.
+    pub fn is_no_value(&self) -> bool { *self == SplitMutError::NoValue }
+
+    /// Returns true for `SplitMutError::SameValue`.
+    pub fn is_same_value(&self) -> bool { *self == SplitMutError::SameValue }
+
+    /// Returns true for `SplitMutError::WrongType`.
+    pub fn is_wrong_type(&self) -> bool { *self == SplitMutError::WrongType }
+
+    /// Returns true for `SplitMutError::Shared`.
+    pub fn is_shared(&self) -> bool { *self == SplitMutError::Shared }
+
+    /// Returns true for `SplitMutError::Ambiguous`.
+    pub fn is_ambiguous(&self) -> bool { *self == SplitMutError::Ambiguous }
+
+    /// Returns true for `SplitMutError::WouldBlock`.
+    pub fn is_would_block(&self) -> bool { *self == SplitMutError::WouldBlock }
 }
 
-impl std::fmt::Display for SplitMutError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-         use std::error::Error;
-         f.write_str(self.description())
+/// Conversions for results returned from get*_mut functions.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, SplitMutError, SplitMutResult};
+///
+/// let mut v = vec![1, 2];
+/// let (a, b) = v.get2_mut(0, 5);
+/// assert_eq!(a.ok_or_missing(), Ok(Some(&mut 1)));
+/// assert_eq!(b.ok_or_missing(), Ok(None));
+/// let (_, c) = v.get2_mut(1, 1);
+/// assert_eq!(c.ok_or_missing(), Err(SplitMutError::SameValue));
+/// ```
+pub trait SplitMutResult<T> {
+    /// Turns a missing value into `Ok(None)`, like the `get_mut` of the collection
+    /// would, while keeping other errors.
+    fn ok_or_missing(self) -> Result<Option<T>, SplitMutError>;
+}
+
+impl<T> SplitMutResult<T> for Result<T, SplitMutError> {
+    fn ok_or_missing(self) -> Result<Option<T>, SplitMutError> {
+        match self {
+            Ok(t) => Ok(Some(t)),
+            Err(SplitMutError::NoValue) => Ok(None),
+            Err(e) => Err(e),
+        }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for SplitMutError {
+    fn description(&self) -> &'static str { self.as_str() }
+}
+
+impl core::fmt::Display for SplitMutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+         f.write_str(self.as_str())
+    }
+}
+
+/// Error returned from the try_get*_mut and try_get_mut_iter functions: the first key
+/// that failed, and why.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+pub struct KeyError {
+    /// The position of the key among the keys, starting from 0.
+    pub position: usize,
+    /// The error for that key.
+    pub error: SplitMutError,
+}
+
+impl KeyError {
+    // The first error among the results for each key.
+    fn first(e: &[Option<SplitMutError>]) -> KeyError {
+        let position = e.iter().position(|e| e.is_some()).unwrap();
+        KeyError { position, error: e[position].unwrap() }
+    }
+}
+
+impl From<KeyError> for SplitMutError {
+    fn from(e: KeyError) -> SplitMutError { e.error }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeyError {}
+
+impl core::fmt::Display for KeyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+         write!(f, "{} for key {}", self.error, self.position)
+    }
+}
+
+// Every error handed out passes through here, so it can be recorded.
+#[inline]
+fn err(e: SplitMutError) -> SplitMutError {
+    #[cfg(feature = "stats")]
+    stats::record(e);
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "splitmut", error = ?e, "split failed");
+    e
+}
+
 // Used internally.
 type R<V> = Result<*mut V, SplitMutError>;
 
 #[inline]
 fn to_r<V>(s: Option<&mut V>) -> R<V> {
-    s.map(|s| s as *mut V).ok_or(SplitMutError::NoValue)
+    s.map(|s| s as *mut V).ok_or_else(|| err(SplitMutError::NoValue))
 }
 
 #[inline]
 fn check_r<V>(a: &R<V>, b: R<V>) -> R<V> {
-    match (a, &b) {
-        (&Ok(ref aa), &Ok(ref bb)) => if aa == bb { return Err(SplitMutError::SameValue) },
-        _ => {},
+    if let (Ok(aa), Ok(bb)) = (a, &b) {
+        if aa == bb { return Err(err(SplitMutError::SameValue)) }
     }
     b
 }
 
+#[inline]
+fn maybe_r<K, V, A: SplitMut<K, V> + ?Sized>(a: &mut A, k: Option<K>) -> R<V> {
+    k.map_or_else(|| Err(err(SplitMutError::NoValue)), |k| to_r(a.get1_mut(k)))
+}
+
+#[inline]
+fn dedup_r<V>(p: &mut [R<V>]) {
+    for i in 1..p.len() {
+        for j in 0..i { p[i] = check_r(&p[j], p[i]); }
+    }
+}
+
+#[track_caller]
+fn unwrap_key<'a, V>(name: &str, pos: usize, r: Result<&'a mut V, SplitMutError>) -> &'a mut V {
+    match r {
+        Ok(v) => v,
+        Err(SplitMutError::NoValue) => panic!("{}: key at position {} has no value", name, pos),
+        Err(SplitMutError::SameValue) => panic!("{}: key at position {} refers to the same value as an earlier key", name, pos),
+        Err(e) => panic!("{}: key at position {}: {}", name, pos, e),
+    }
+}
+
 #[inline]
 unsafe fn from_r<'a, V>(a: R<V>) -> Result<&'a mut V, SplitMutError> { a.map(|aa| &mut *aa) } 
 
+macro_rules! res_ty { ($x: ident) => { Result<&mut V, SplitMutError> } }
+macro_rules! ref_ty { ($x: ident) => { &mut V } }
 
-/// Just add `use splitmut::SplitMut;` to have these methods working on
-/// mutable slices, Vec, VecDeque, HashMap and BTreeMap.
+// Generates getN_mut and getN_unchecked_mut for more than four keys.
+macro_rules! getn_mut {
+    ($n: expr, $fname: ident, $uname: ident, $($k: ident $i: tt),+) => {
+        #[doc = concat!("Returns ", $n, " mutable references to ", $n, " distinct values within")]
+        /// the same collection.
+        #[allow(clippy::too_many_arguments)]
+        fn $fname(&mut self, $($k: K),+) -> ($(res_ty!($k)),+) {
+            trace_method!(stringify!($fname));
+            let mut p = [$(to_r(self.get1_mut($k))),+];
+            dedup_r(&mut p);
+            unsafe { ($(from_r(p[$i])),+) }
+        }
+
+        #[doc = concat!("Returns ", $n, " mutable references to ", $n, " distinct values within")]
+        /// the same collection.
+        ///
+        /// # Safety
+        /// It is undefined behaviour to call this with a key that does not
+        /// correspond to a value, or with any two keys pointing to the same value.
+        /// You have been warned.
+        #[allow(clippy::too_many_arguments)]
+        unsafe fn $uname(&mut self, $($k: K),+) -> ($(ref_ty!($k)),+) {
+            let p = [$(self.get1_unchecked_mut($k) as *mut V),+];
+            ($(&mut *p[$i]),+)
+        }
+    }
+}
+
+
+/// The lookups a collection provides to get the `SplitMut` methods.
 ///
-/// In case you want to implement `SplitMut` for your own collection, just
-/// implement `get1_mut` and `get1_unchecked_mut` and the other methods will
+/// In case you want to use `SplitMut` with your own collection, just
+/// implement `get1_mut` and `get1_unchecked_mut` and all `SplitMut` methods will
 /// be provided for you. If you do so, you must make sure that these functions
 /// do not mutate your collection in ways that would invalidate previously returned
 /// values from `get1_mut` and `get1_unchecked_mut`.
-pub unsafe trait SplitMut<K, V> {
+///
+/// Code that only uses collections should import `SplitMut` instead.
+///
+/// # Safety
+/// Implementors must guarantee that distinct keys resolving to the same
+/// value give the same pointer, and that returned references stay valid
+/// for as long as the collection is mutably borrowed.
+///
+/// Values must also not move, or be dropped, through a shared reference to the
+/// collection, e g, through interior mutability: `AccessPlan` hands out pointers it
+/// resolved earlier for as long as the collection has only been shared since.
+pub unsafe trait SplitMutBase<K, V> {
     /// Wrapper for get_mut, used internally.
     fn get1_mut(&mut self, k1: K) -> Option<&mut V>;
     /// Wrapper for get_unchecked_mut, used internally.
     ///
-    /// # Undefined behaviour
+    /// # Safety
     /// It is undefined behaviour to call this with a key that does not correspond to a value.
     /// You have been warned.
     unsafe fn get1_unchecked_mut(&mut self, k1: K) -> &mut V;
 
-    /// Returns two mutable references to two distinct values within
-    /// the same collection.
-    fn get2_mut(&mut self, k1: K, k2: K) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+    /// The addresses that all values lie within, as a half-open range, if the
+    /// collection keeps its values in a single allocation. Used internally to keep
+    /// track of returned values in a bitset; values outside the range, or not at a
+    /// multiple of the value size from its start, are tracked by their address instead.
+    #[doc(hidden)]
+    #[inline]
+    fn value_range(&self) -> Option<(usize, usize)> { None }
+
+    /// The lookup behind `get2_mut`, which collections can replace with a faster one.
+    #[doc(hidden)]
+    fn split2_mut(&mut self, k1: K, k2: K) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
         let p1 = to_r(self.get1_mut(k1));
         let p2 = to_r(self.get1_mut(k2));
         let p2 = check_r(&p1, p2);
         unsafe { (from_r(p1), from_r(p2)) }
     }
 
-    /// Returns three mutable references to three distinct values within
-    /// the same collection.
-    fn get3_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut V, SplitMutError>, 
-        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+    /// The lookup behind `get3_mut`.
+    #[doc(hidden)]
+    fn split3_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        let p1 = to_r(self.get1_mut(k1));
+        let p2 = to_r(self.get1_mut(k2));
+        let p3 = to_r(self.get1_mut(k3));
+        let p2 = check_r(&p1, p2);
+        let p3 = check_r(&p1, p3);
+        let p3 = check_r(&p2, p3);
+        unsafe { (from_r(p1), from_r(p2), from_r(p3)) }
+    }
+
+    /// The lookup behind `get4_mut`.
+    #[doc(hidden)]
+    fn split4_mut(&mut self, k1: K, k2: K, k3: K, k4: K) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        let p1 = to_r(self.get1_mut(k1));
+        let p2 = to_r(self.get1_mut(k2));
+        let p3 = to_r(self.get1_mut(k3));
+        let p4 = to_r(self.get1_mut(k4));
+        let p2 = check_r(&p1, p2);
+        let p3 = check_r(&p1, p3);
+        let p3 = check_r(&p2, p3);
+        let p4 = check_r(&p1, p4);
+        let p4 = check_r(&p2, p4);
+        let p4 = check_r(&p3, p4);
+        unsafe { (from_r(p1), from_r(p2), from_r(p3), from_r(p4)) }
+    }
+}
+
+/// Collections whose values can be looked up while values returned earlier are
+/// still in use, as the wrappers that hand out values one at a time do.
+///
+/// # Safety
+///
+/// The values must be stored outside of the collection itself, e g on the heap, so
+/// that a `&mut` to the collection does not cover them. `get1_mut` must not read or
+/// write any value, or create references to any value except the one it returns.
+pub unsafe trait SharedLookup<K, V>: SplitMutBase<K, V> {}
+
+unsafe impl<V> SharedLookup<usize, V> for &mut [V] {}
+unsafe impl<V> SharedLookup<usize, V> for Vec<V> {}
+unsafe impl<V> SharedLookup<usize, V> for VecDeque<V> {}
+#[cfg(feature = "std")]
+unsafe impl<K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> SharedLookup<&Q, V> for HashMap<K, V, S> {}
+unsafe impl<K: Ord + borrow::Borrow<Q>, Q: Ord + ?Sized, V> SharedLookup<&Q, V> for BTreeMap<K, V> {}
+
+/// Just add `use splitmut::SplitMut;` to have these methods working on
+/// mutable slices, Vec, VecDeque, HashMap and BTreeMap.
+///
+/// This trait is implemented for every collection implementing `SplitMutBase`,
+/// which is where your own collections plug in.
+pub trait SplitMut<K, V>: SplitMutBase<K, V> {
+    /// Returns two mutable references to two distinct values within
+    /// the same collection.
+    fn get2_mut(&mut self, k1: K, k2: K) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get2_mut");
+        self.split2_mut(k1, k2)
+    }
+
+    /// Returns three mutable references to three distinct values within
+    /// the same collection.
+    fn get3_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut V, SplitMutError>, 
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get3_mut");
+        self.split3_mut(k1, k2, k3)
+    }
+
+    /// Returns four mutable references to four distinct values within
+    /// the same collection.
+    fn get4_mut(&mut self, k1: K, k2: K, k3: K, k4: K) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get4_mut");
+        self.split4_mut(k1, k2, k3, k4)
+    }
+
+    /// Like `get2_mut`, but fails if any of the keys fails, so both values
+    /// can be had with a single `?`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError, KeyError};
+    ///
+    /// fn transfer(v: &mut Vec<u32>, from: usize, to: usize) -> Result<(), KeyError> {
+    ///     let (a, b) = v.try_get2_mut(from, to)?;
+    ///     *b += std::mem::replace(a, 0);
+    ///     Ok(())
+    /// }
+    ///
+    /// let mut v = vec![5, 1];
+    /// assert_eq!(transfer(&mut v, 0, 1), Ok(()));
+    /// assert_eq!(v, [0, 6]);
+    /// assert_eq!(transfer(&mut v, 1, 1), Err(KeyError { position: 1, error: SplitMutError::SameValue }));
+    /// assert_eq!(transfer(&mut v, 2, 1).unwrap_err().to_string(), "No value for key 0");
+    /// ```
+    fn try_get2_mut(&mut self, k1: K, k2: K) -> Result<(&mut V, &mut V), KeyError> {
+        trace_method!("try_get2_mut");
+        match self.split2_mut(k1, k2) {
+            (Ok(a), Ok(b)) => Ok((a, b)),
+            (a, b) => Err(KeyError::first(&[a.err(), b.err()])),
+        }
+    }
+
+    /// Like `get3_mut`, but fails if any of the keys fails. See `try_get2_mut`.
+    fn try_get3_mut(&mut self, k1: K, k2: K, k3: K) -> Result<(&mut V, &mut V, &mut V), KeyError> {
+        trace_method!("try_get3_mut");
+        match self.split3_mut(k1, k2, k3) {
+            (Ok(a), Ok(b), Ok(c)) => Ok((a, b, c)),
+            (a, b, c) => Err(KeyError::first(&[a.err(), b.err(), c.err()])),
+        }
+    }
+
+    /// Like `get4_mut`, but fails if any of the keys fails. See `try_get2_mut`.
+    fn try_get4_mut(&mut self, k1: K, k2: K, k3: K, k4: K) -> Result<(&mut V, &mut V, &mut V, &mut V), KeyError> {
+        trace_method!("try_get4_mut");
+        match self.split4_mut(k1, k2, k3, k4) {
+            (Ok(a), Ok(b), Ok(c), Ok(d)) => Ok((a, b, c, d)),
+            (a, b, c, d) => Err(KeyError::first(&[a.err(), b.err(), c.err(), d.err()])),
+        }
+    }
+
+    /// Like `get2_mut`, but gives `None` instead of an error, for code that only
+    /// cares whether it got the value or not.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut hp = vec![10, 7];
+    /// // A unit attacking itself does nothing
+    /// if let (Some(attacker), Some(target)) = hp.get2_opt_mut(1, 1) { *target -= *attacker; }
+    /// if let (Some(attacker), Some(target)) = hp.get2_opt_mut(1, 0) { *target -= *attacker; }
+    /// assert_eq!(hp, [3, 7]);
+    /// ```
+    fn get2_opt_mut(&mut self, k1: K, k2: K) -> (Option<&mut V>, Option<&mut V>) {
+        trace_method!("get2_opt_mut");
+        let (a, b) = self.split2_mut(k1, k2);
+        (a.ok(), b.ok())
+    }
+
+    /// Like `get3_mut`, but gives `None` instead of an error. See `get2_opt_mut`.
+    fn get3_opt_mut(&mut self, k1: K, k2: K, k3: K) -> (Option<&mut V>, Option<&mut V>, Option<&mut V>) {
+        trace_method!("get3_opt_mut");
+        let (a, b, c) = self.split3_mut(k1, k2, k3);
+        (a.ok(), b.ok(), c.ok())
+    }
+
+    /// Like `get4_mut`, but gives `None` instead of an error. See `get2_opt_mut`.
+    fn get4_opt_mut(&mut self, k1: K, k2: K, k3: K, k4: K) -> (Option<&mut V>, Option<&mut V>, Option<&mut V>, Option<&mut V>) {
+        trace_method!("get4_opt_mut");
+        let (a, b, c, d) = self.split4_mut(k1, k2, k3, k4);
+        (a.ok(), b.ok(), c.ok(), d.ok())
+    }
+
+    /// Like `get2_mut`, but panics if any of the keys fails, with a message telling
+    /// which key it was and why. The panic is reported at the caller's location.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// let (a, b) = v.get2_mut_unwrap(0, 2);
+    /// std::mem::swap(a, b);
+    /// assert_eq!(v, [3, 2, 1]);
+    /// ```
+    ///
+    /// ```should_panic
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// // Panics with "get2_mut_unwrap: key at position 1 refers to the same value as an earlier key"
+    /// v.get2_mut_unwrap(2, 2);
+    /// ```
+    #[track_caller]
+    fn get2_mut_unwrap(&mut self, k1: K, k2: K) -> (&mut V, &mut V) {
+        trace_method!("get2_mut_unwrap");
+        let (a, b) = self.split2_mut(k1, k2);
+        let n = "get2_mut_unwrap";
+        (unwrap_key(n, 0, a), unwrap_key(n, 1, b))
+    }
+
+    /// Like `get3_mut`, but panics if any of the keys fails. See `get2_mut_unwrap`.
+    #[track_caller]
+    fn get3_mut_unwrap(&mut self, k1: K, k2: K, k3: K) -> (&mut V, &mut V, &mut V) {
+        trace_method!("get3_mut_unwrap");
+        let (a, b, c) = self.split3_mut(k1, k2, k3);
+        let n = "get3_mut_unwrap";
+        (unwrap_key(n, 0, a), unwrap_key(n, 1, b), unwrap_key(n, 2, c))
+    }
+
+    /// Like `get4_mut`, but panics if any of the keys fails. See `get2_mut_unwrap`.
+    #[track_caller]
+    fn get4_mut_unwrap(&mut self, k1: K, k2: K, k3: K, k4: K) -> (&mut V, &mut V, &mut V, &mut V) {
+        trace_method!("get4_mut_unwrap");
+        let (a, b, c, d) = self.split4_mut(k1, k2, k3, k4);
+        let n = "get4_mut_unwrap";
+        (unwrap_key(n, 0, a), unwrap_key(n, 1, b), unwrap_key(n, 2, c), unwrap_key(n, 3, d))
+    }
+
+    /// Takes keys two at a time, and returns the values of each pair, like `get2_mut`.
+    ///
+    /// The returned iterator is a lending one: each pair lives only until the next
+    /// call to `next`, so the same value can show up in several pairs. If the number
+    /// of keys is odd, the second value of the last pair is `Err(SplitMutError::NoValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut v = vec!['a', 'b', 'c', 'd'];
+    /// let mut p = v.iter_pairs_mut(vec![0, 1, 1, 2, 2, 3]);
+    /// while let Some((a, b)) = p.next() {
+    ///     std::mem::swap(a.unwrap(), b.unwrap());
+    /// }
+    /// assert_eq!(v, ['b', 'c', 'd', 'a']);
+    /// ```
+    fn iter_pairs_mut<I: IntoIterator<Item=K>>(&mut self, keys: I) -> PairsMut<'_, K, V, Self, I::IntoIter> {
+        PairsMut { a: self, i: keys.into_iter(), _p: PhantomData }
+    }
+
+    /// Returns mutable references to `N` distinct values within the same collection,
+    /// for any fixed number of keys.
+    ///
+    /// Like `get2_mut` and friends, every key is checked against the ones before it,
+    /// so nothing is allocated, but the time grows with the square of `N`. For many
+    /// keys, `get_mut_iter` is faster.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// let [a, b, c] = v.get_disjoint_mut([2, 0, 2]);
+    /// std::mem::swap(a.unwrap(), b.unwrap());
+    /// assert_eq!(c, Err(SplitMutError::SameValue));
+    /// assert_eq!(v, [3, 2, 1]);
+    /// ```
+    fn get_disjoint_mut<const N: usize>(&mut self, keys: [K; N]) -> [Result<&mut V, SplitMutError>; N] {
+        trace_method!("get_disjoint_mut");
+        let mut keys = IntoIterator::into_iter(keys);
+        let mut p: [R<V>; N] = core::array::from_fn(|_| to_r(self.get1_mut(keys.next().unwrap())));
+        dedup_r(&mut p);
+        p.map(|r| unsafe { from_r(r) })
+    }
+
+    /// Like `get2_mut`, but each key is optional. A `None` key gives
+    /// `Err(SplitMutError::NoValue)` without any lookup.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// let k = v.iter().position(|&x| x == 3);
+    /// assert_eq!(v.get2_maybe_mut(k, None), (Ok(&mut 3), Err(SplitMutError::NoValue)));
+    /// ```
+    fn get2_maybe_mut(&mut self, k1: Option<K>, k2: Option<K>) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get2_maybe_mut");
+        let mut p = [maybe_r(self, k1), maybe_r(self, k2)];
+        dedup_r(&mut p);
+        unsafe { (from_r(p[0]), from_r(p[1])) }
+    }
+
+    /// Like `get3_mut`, but each key is optional. A `None` key gives
+    /// `Err(SplitMutError::NoValue)` without any lookup.
+    fn get3_maybe_mut(&mut self, k1: Option<K>, k2: Option<K>, k3: Option<K>) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get3_maybe_mut");
+        let mut p = [maybe_r(self, k1), maybe_r(self, k2), maybe_r(self, k3)];
+        dedup_r(&mut p);
+        unsafe { (from_r(p[0]), from_r(p[1]), from_r(p[2])) }
+    }
+
+    /// Like `get4_mut`, but each key is optional. A `None` key gives
+    /// `Err(SplitMutError::NoValue)` without any lookup.
+    fn get4_maybe_mut(&mut self, k1: Option<K>, k2: Option<K>, k3: Option<K>, k4: Option<K>) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get4_maybe_mut");
+        let mut p = [maybe_r(self, k1), maybe_r(self, k2), maybe_r(self, k3), maybe_r(self, k4)];
+        dedup_r(&mut p);
+        unsafe { (from_r(p[0]), from_r(p[1]), from_r(p[2]), from_r(p[3])) }
+    }
+
+    /// Returns any number mutable references to distinct values within
+    /// the same collection. A HashSet is used internally to keep track
+    /// of values already returned.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut h = vec!["Hello", "world", "!"];
+    /// let mut z = h.get_muts();
+    /// let a = z.at(0);
+    /// let b = z.at(1);
+    /// assert_eq!(a, Ok(&mut "Hello"));
+    /// assert_eq!(b, Ok(&mut "world"));
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_muts(&mut self) -> GetMuts<'_, K, V, Self> { GetMuts(Tracker::new::<V>(self.value_range()), self, PhantomData, PtrSet::default()) }
+
+    /// Returns an iterator adapter that maps from a K to a Result<V, SplitMutError>.
+    /// A HashSet is used internally to keep track of values already returned.
+    ///
+    /// The keys can be anything iterable, e g an iterator, a `Vec` or an array.
+    /// For `HashMap` and `BTreeMap`, the keys are borrowed, like for `get_mut`, so
+    /// keys owned elsewhere can be passed by reference instead of being cloned:
+    /// `h.get_mut_iter(names.iter().map(|s| s.as_str()))` for a `Vec<String>` of names,
+    /// or `h.get_mut_iter(&names)` if `h` has `String` keys.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut h = BTreeMap::new();
+    /// h.insert(String::from("borrow"), 1);   
+    /// h.insert(String::from("me"), 2);
+    /// let slice = ["me", "borrow", "me"];
+    /// let z: Vec<_> = h.get_mut_iter(slice.iter().cloned()).collect();
+    /// assert_eq!(&*z, [Ok(&mut 2), Ok(&mut 1), Err(SplitMutError::SameValue)]);
+    /// assert_eq!(h.get_mut_iter(slice).count(), 3);
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_mut_iter<I: IntoIterator<Item=K>>(&mut self, i: I) -> GetMutIter<'_, K, V, Self, I::IntoIter> {
+        let i = i.into_iter();
+        let mut z = self.get_muts();
+        z.0.reserve(i.size_hint().0);
+        GetMutIter(z, i)
+    }
+
+    /// Like `get_mut_iter`, but all or nothing: returns the values of all keys, or
+    /// stops at the first key that fails, and tells which one it was.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError, KeyError};
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// for x in v.try_get_mut_iter(vec![0, 2]).unwrap() { *x = 0 }
+    /// assert_eq!(v, [0, 2, 0]);
+    /// let e = v.try_get_mut_iter(vec![1, 0, 1, 5]).unwrap_err();
+    /// assert_eq!(e, KeyError { position: 2, error: SplitMutError::SameValue });
+    /// ```
+    #[cfg(feature = "std")]
+    fn try_get_mut_iter<I: IntoIterator<Item=K>>(&mut self, i: I) -> Result<Vec<&mut V>, KeyError> {
+        trace_method!("try_get_mut_iter");
+        self.get_mut_iter(i).enumerate().map(|(position, r)| r.map_err(|error| KeyError { position, error })).collect()
+    }
+
+    /// Like `get_mut_iter`, but yields only the values that were found, and skips
+    /// keys without a value, or referring to a value already returned.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut h: HashMap<&str, u32> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    /// for x in h.get_mut_iter_ok(vec![&"b", &"c", &"b", &"a"]) { *x += 10 }
+    /// assert_eq!((h["a"], h["b"]), (11, 12));
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_mut_iter_ok<I: IntoIterator<Item=K>>(&mut self, i: I) -> OkMutIter<'_, K, V, Self, I::IntoIter> {
+        OkMutIter(self.get_mut_iter(i))
+    }
+
+    /// Like `get_mut_iter`, but yields the results in arrays of `N`, for processing
+    /// the keys in batches.
+    ///
+    /// Values are tracked across the whole stream of keys, not only within a chunk.
+    /// If the number of keys is not a multiple of `N`, the last chunk is filled up
+    /// with `Err(SplitMutError::NoValue)`.
+    ///
+    /// # Panics
+    /// Panics if `N` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![1, 2, 3, 4, 5];
+    /// let mut c = v.get_mut_chunks(vec![4, 3, 2, 4, 0]);
+    /// let [a, b] = c.next().unwrap();
+    /// std::mem::swap(a.unwrap(), b.unwrap());
+    /// assert_eq!(c.next().unwrap()[1], Err(SplitMutError::SameValue));
+    /// assert_eq!(c.next(), Some([Ok(&mut 1), Err(SplitMutError::NoValue)]));
+    /// assert_eq!(c.next(), None);
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_mut_chunks<const N: usize, I: IntoIterator<Item=K>>(&mut self, i: I) -> ChunksMutIter<'_, K, V, Self, I::IntoIter, N> {
+        assert!(N > 0, "chunk size must be non-zero");
+        ChunksMutIter(self.get_mut_iter(i))
+    }
+
+    /// Like `get_mut_iter`, but yields every key together with its result.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec!["a", "b"];
+    /// for (k, r) in v.get_mut_iter_keyed(vec![1, 2]) {
+    ///     match r {
+    ///         Ok(x) => *x = "found",
+    ///         Err(e) => assert_eq!((k, e), (2, SplitMutError::NoValue)),
+    ///     }
+    /// }
+    /// assert_eq!(v, ["a", "found"]);
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_mut_iter_keyed<I: IntoIterator<Item=K>>(&mut self, i: I) -> KeyedMutIter<'_, K, V, Self, I::IntoIter> where K: Clone {
+        KeyedMutIter(self.get_mut_iter(i))
+    }
+
+    /// Looks up a slice of keys, and returns the results in the same order, like
+    /// `get_mut_iter` would.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut h: HashMap<String, u32> = HashMap::new();
+    /// h.insert("a".into(), 1);
+    /// h.insert("b".into(), 2);
+    /// let r = h.get_mut_refs(&["b", "a", "b"]);
+    /// assert_eq!(r, [Ok(&mut 2), Ok(&mut 1), Err(SplitMutError::SameValue)]);
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_mut_refs(&mut self, keys: &[K]) -> Vec<Result<&mut V, SplitMutError>> where K: Clone {
+        trace_method!("get_mut_refs");
+        self.get_mut_iter(keys.iter().cloned()).collect()
+    }
+
+    /// Looks up several keys, and returns a map from each key that was found to its
+    /// value, for keyed access to the values afterwards.
+    ///
+    /// Keys without a value are left out, and so are keys referring to the same value
+    /// as a key before them.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut h: HashMap<&str, u32> = vec![("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    /// {
+    ///     let mut m = h.get_mut_map(vec![&"c", &"a", &"x", &"c"]);
+    ///     assert_eq!(m.len(), 2);
+    ///     let a = *m[&"a"];
+    ///     **m.get_mut(&"c").unwrap() += a;
+    /// }
+    /// assert_eq!(h["c"], 4);
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_mut_map<I: IntoIterator<Item=K>>(&mut self, keys: I) -> HashMap<K, &mut V> where K: hash::Hash + Eq + Clone {
+        trace_method!("get_mut_map");
+        let mut z = self.get_muts();
+        let mut m = HashMap::new();
+        for k in keys {
+            if let Ok(v) = z.at(k.clone()) { m.insert(k, v); }
+        }
+        m
+    }
+
+    /// Like `get_mut_iter`, but yields plain mutable references, and panics if
+    /// a key has no value or refers to a value already returned.
+    ///
+    /// Use this when the keys are known to be valid, e g when they come from
+    /// the collection itself.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut v = vec![1, 2, 3, 4];
+    /// for x in v.expect_mut_iter(vec![3, 1]) { *x *= 10; }
+    /// assert_eq!(v, [1, 20, 3, 40]);
+    /// ```
+    #[cfg(feature = "std")]
+    fn expect_mut_iter<I: IntoIterator<Item=K>>(&mut self, i: I) -> ExpectMutIter<'_, K, V, Self, I::IntoIter> {
+        let i = i.into_iter();
+        let mut z = self.get_muts();
+        z.0.reserve(i.size_hint().0);
+        ExpectMutIter(z, i, 0)
+    }
+
+    /// Like `get_muts`, but the returned session can be shared between threads.
+    /// Returned keys are kept track of in `HashSet`s, striped over several mutexes.
+    /// The collection must implement `SyncLookup`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut v = vec![1, 2, 3, 4];
+    /// {
+    ///     let z = v.get_muts_sync();
+    ///     std::thread::scope(|s| {
+    ///         s.spawn(|| *z.at(0).unwrap() += 10);
+    ///         s.spawn(|| *z.at(3).unwrap() += 10);
+    ///     });
+    /// }
+    /// assert_eq!(v, [11, 2, 3, 14]);
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_muts_sync(&mut self) -> SyncGetMuts<'_, K, V, Self> where Self: SyncLookup<K, V>, K: hash::Hash + Eq + Clone { SyncGetMuts::new(self) }
+
+    /// Runs `f` on a scoped thread for each of the `parts`, giving every thread
+    /// mutable references to the values of its own keys, and returns the results
+    /// of `f` in the order of the parts.
+    ///
+    /// All keys are looked up before any thread is started. If a key has no value,
+    /// or its value is in a part before it (or earlier in the same part), no thread
+    /// is started and the error is returned. A panic in `f` is passed on.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![1, 2, 3, 4, 5];
+    /// let sums = v.scope_split(vec![vec![0, 2], vec![4, 1]], |_, mut part| {
+    ///     for x in part.iter_mut() { **x *= 10; }
+    ///     part.iter().map(|x| **x).sum::<i32>()
+    /// });
+    /// assert_eq!(sums, Ok(vec![40, 70]));
+    /// assert_eq!(v, [10, 20, 30, 4, 50]);
+    /// assert_eq!(v.scope_split(vec![vec![0], vec![0]], |_, _| ()), Err(SplitMutError::SameValue));
+    /// ```
+    #[cfg(feature = "std")]
+    fn scope_split<P, I, T, F>(&mut self, parts: I, f: F) -> Result<Vec<T>, SplitMutError>
+    where I: IntoIterator<Item=P>, P: IntoIterator<Item=K>, V: Send, T: Send, F: Fn(usize, Vec<&mut V>) -> T + Sync {
+        trace_method!("scope_split");
+        sync::scope_split(self, parts, f)
+    }
+
+    /// Divides the values of `keys` into `n` parts with about the same total weight,
+    /// as given by `weight`, e g to spread work with uneven costs over threads.
+    ///
+    /// Values are handed out heaviest first, each one to the part that is lightest
+    /// so far, so no part weighs more than the average plus the heaviest value.
+    /// Within a part, values keep the order of their keys. If a key has no value, or a value is repeated, the error is returned.
+    ///
+    /// # Panics
+    /// Panics if `n` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut jobs = vec![9, 1, 1, 5, 4];
+    /// let parts = jobs.split_balanced_mut(0..5, 2, |&w| w).unwrap();
+    /// let totals: Vec<u64> = parts.iter().map(|p| p.iter().map(|&&mut w| w).sum()).collect();
+    /// assert_eq!(totals, [10, 10]);
+    /// ```
+    #[cfg(feature = "std")]
+    fn split_balanced_mut<I: IntoIterator<Item=K>, F: FnMut(&V) -> u64>(&mut self, keys: I, n: usize, mut weight: F) -> Result<Vec<Vec<&mut V>>, SplitMutError> {
+        trace_method!("split_balanced_mut");
+        assert!(n != 0, "number of parts must be non-zero");
+        let mut z = self.get_muts();
+        let values = keys.into_iter().map(|k| z.at(k)).collect::<Result<Vec<_>, _>>()?;
+        let weights: Vec<u64> = values.iter().map(|v| weight(v)).collect();
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(weights[i]));
+        let mut lightest: std::collections::BinaryHeap<_> = (0..n).map(|p| std::cmp::Reverse((0u64, p))).collect();
+        let mut part = vec![0; values.len()];
+        for i in order {
+            let std::cmp::Reverse((w, p)) = lightest.pop().unwrap();
+            part[i] = p;
+            lightest.push(std::cmp::Reverse((w.saturating_add(weights[i]), p)));
+        }
+        let mut parts: Vec<Vec<&mut V>> = (0..n).map(|_| Vec::new()).collect();
+        for (v, p) in values.into_iter().zip(part) { parts[p].push(v) }
+        Ok(parts)
+    }
+
+    /// Returns a mutable reference to the value of `center`, together with mutable
+    /// references to the values of its `neighbors`, e g a node and its adjacent nodes.
+    ///
+    /// A neighbor that is the center itself, or that is repeated, gives
+    /// `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut dist = vec![0, 9, 9, 9];
+    /// let (c, n) = dist.get_star_mut(0, vec![1, 3, 0]);
+    /// let c = c.unwrap();
+    /// for d in n.into_iter().flatten() { *d = (*d).min(*c + 1) }
+    /// assert_eq!(dist, [0, 1, 9, 1]);
+    /// assert_eq!(dist.get_star_mut(1, vec![1]).1, [Err(SplitMutError::SameValue)]);
+    /// ```
+    #[cfg(feature = "std")]
+    fn get_star_mut<I: IntoIterator<Item=K>>(&mut self, center: K, neighbors: I) -> (Result<&mut V, SplitMutError>, Vec<Result<&mut V, SplitMutError>>) {
+        trace_method!("get_star_mut");
+        let mut z = self.get_muts();
+        let c = z.at(center);
+        let n = neighbors.into_iter().map(|k| z.at(k)).collect();
+        (c, n)
+    }
+
+    /// Returns a tuple of mutable references to distinct values within the
+    /// same collection, one for every key in `keys`. Works for tuples of up to
+    /// 12 keys.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![1, 2, 3, 4, 5];
+    /// let (a, b, c, d, e) = v.get_tuple_mut((4, 3, 2, 1, 1));
+    /// assert_eq!(a, Ok(&mut 5));
+    /// assert_eq!(d, Ok(&mut 2));
+    /// assert_eq!(e, Err(SplitMutError::SameValue));
+    /// # let _ = (b, c);
+    /// ```
+    fn get_tuple_mut<'a, T: KeyTuple<'a, K, V>>(&'a mut self, keys: T) -> T::Output where V: 'a {
+        trace_method!("get_tuple_mut");
+        keys.get_tuple_mut(self)
+    }
+
+    /// Writes values into the slots named by the keys, pairing keys and values
+    /// like `zip` does, and returns one result per pair.
+    ///
+    /// A key without a value gives `Err(SplitMutError::NoValue)`, and a key for a
+    /// value already written in the same call gives `Err(SplitMutError::SameValue)`;
+    /// the corresponding value is dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![0; 3];
+    /// let r = v.set_many(vec![2, 0, 2, 5], 1..);
+    /// assert_eq!(r, [Ok(()), Ok(()), Err(SplitMutError::SameValue), Err(SplitMutError::NoValue)]);
+    /// assert_eq!(v, [2, 0, 1]);
+    /// ```
+    #[cfg(feature = "std")]
+    fn set_many<I: IntoIterator<Item=K>, J: IntoIterator<Item=V>>(&mut self, keys: I, values: J) -> Vec<Result<(), SplitMutError>> {
+        trace_method!("set_many");
+        let i = keys.into_iter().zip(values);
+        let mut z = self.get_muts();
+        z.0.reserve(i.size_hint().0);
+        i.map(|(k, v)| z.at(k).map(|r| *r = v)).collect()
+    }
 
-        let p1 = to_r(self.get1_mut(k1));
-        let p2 = to_r(self.get1_mut(k2));
-        let p3 = to_r(self.get1_mut(k3));
-        let p2 = check_r(&p1, p2);
-        let p3 = check_r(&p1, p3);
-        let p3 = check_r(&p2, p3);
-        unsafe { (from_r(p1), from_r(p2), from_r(p3)) }
+    /// Folds the values of several source keys into the value of `dst`, by
+    /// calling `f(dst_value, src_value)` once for every distinct source value.
+    ///
+    /// Source keys without a value, source keys resolving to `dst`, and repeated
+    /// source values are skipped. Returns the number of source values folded, or
+    /// `Err(SplitMutError::NoValue)` if there is no value for `dst`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::SplitMut;
+    ///
+    /// let mut h: HashMap<_, _> = vec![("total", 0), ("a", 1), ("b", 2)].into_iter().collect();
+    /// let n = h.accumulate_into(&"total", vec![&"a", &"b", &"total", &"a", &"c"], |t, x| *t += *x);
+    /// assert_eq!(n, Ok(2));
+    /// assert_eq!(h["total"], 3);
+    /// ```
+    #[cfg(feature = "std")]
+    fn accumulate_into<I: IntoIterator<Item=K>, F: FnMut(&mut V, &V)>(&mut self, dst: K, srcs: I, mut f: F) -> Result<usize, SplitMutError> {
+        self.accumulate_into_mut(dst, srcs, |d, s| f(d, s))
     }
 
-    /// Returns four mutable references to four distinct values within
-    /// the same collection.
-    fn get4_mut(&mut self, k1: K, k2: K, k3: K, k4: K) -> (Result<&mut V, SplitMutError>,
-        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
-        let p1 = to_r(self.get1_mut(k1));
-        let p2 = to_r(self.get1_mut(k2));
-        let p3 = to_r(self.get1_mut(k3));
-        let p4 = to_r(self.get1_mut(k4));
-        let p2 = check_r(&p1, p2);
-        let p3 = check_r(&p1, p3);
-        let p3 = check_r(&p2, p3);
-        let p4 = check_r(&p1, p4);
-        let p4 = check_r(&p2, p4);
-        let p4 = check_r(&p3, p4);
-        unsafe { (from_r(p1), from_r(p2), from_r(p3), from_r(p4)) }
+    /// Like `accumulate_into`, but the source values are mutable too, e g so that
+    /// they can be drained or reset while folding.
+    #[cfg(feature = "std")]
+    fn accumulate_into_mut<I: IntoIterator<Item=K>, F: FnMut(&mut V, &mut V)>(&mut self, dst: K, srcs: I, mut f: F) -> Result<usize, SplitMutError> {
+        trace_method!("accumulate_into_mut");
+        let mut z = self.get_muts();
+        let d = z.at(dst)?;
+        let mut n = 0;
+        for s in srcs.into_iter().filter_map(|k| z.at(k).ok()) {
+            f(d, s);
+            n += 1;
+        }
+        Ok(n)
     }
 
-    /// Returns any number mutable references to distinct values within
-    /// the same collection. A HashSet is used internally to keep track
-    /// of values already returned.
+    /// Looks up all keys, and if all of them have distinct values, calls `f` with
+    /// a slice of mutable references to them, in the order of the keys.
+    ///
+    /// Returns the result of `f`, or the first error without calling `f`.
     ///
     /// # Example
     /// ```
-    /// use splitmut::SplitMut;
+    /// use splitmut::{SplitMut, SplitMutError};
     ///
-    /// let mut h = vec!["Hello", "world", "!"];
-    /// let mut z = h.get_muts();
-    /// let a = z.at(0);
-    /// let b = z.at(1);
-    /// assert_eq!(a, Ok(&mut "Hello"));
-    /// assert_eq!(b, Ok(&mut "world"));
+    /// let mut v = vec![5, 2, 4, 1, 3];
+    /// // Sort the values at the odd positions among themselves
+    /// v.apply_n(vec![1, 3], |r| if let [a, b] = r { if a > b { std::mem::swap(&mut **a, &mut **b) } }).unwrap();
+    /// assert_eq!(v, [5, 1, 4, 2, 3]);
+    /// let max = v.apply_n(vec![0, 2, 4], |r| r.iter().map(|x| **x).max()).unwrap();
+    /// assert_eq!(max, Some(5));
+    /// assert_eq!(v.apply_n(vec![0, 0], |_| ()), Err(SplitMutError::SameValue));
     /// ```
-    fn get_muts(&mut self) -> GetMuts<K, V, Self> { GetMuts(self, HashSet::new(), PhantomData) }
+    #[cfg(feature = "std")]
+    fn apply_n<I: IntoIterator<Item=K>, T, F: FnOnce(&mut [&mut V]) -> T>(&mut self, keys: I, f: F) -> Result<T, SplitMutError> {
+        trace_method!("apply_n");
+        let keys = keys.into_iter();
+        let mut refs = Vec::with_capacity(keys.size_hint().0);
+        let mut z = self.get_muts();
+        z.0.reserve(refs.capacity());
+        for k in keys { refs.push(z.at(k)?) }
+        Ok(f(&mut refs))
+    }
 
-    /// Returns an iterator adapter that maps from a K to a Result<V, SplitMutError>.
-    /// A HashSet is used internally to keep track of values already returned.
+    /// Clones the value for every key from `other` into the value for the
+    /// same key in `self`, returning one result per key.
+    ///
+    /// A key gives `Err(SplitMutError::NoValue)` if it has no value in either
+    /// collection; nothing is copied for that key.
     ///
     /// # Example
     /// ```
     /// use std::collections::BTreeMap;
     /// use splitmut::{SplitMut, SplitMutError};
     ///
-    /// let mut h = BTreeMap::new();
-    /// h.insert(String::from("borrow"), 1);   
-    /// h.insert(String::from("me"), 2);
-    /// let slice = ["me", "borrow", "me"];
-    /// let z: Vec<_> = h.get_mut_iter(slice.into_iter().map(|&k| k)).collect();
-    /// assert_eq!(&*z, [Ok(&mut 2), Ok(&mut 1), Err(SplitMutError::SameValue)]);
+    /// let mut dst: BTreeMap<_, _> = (0..4).map(|i| (i, 0)).collect();
+    /// let src: BTreeMap<_, _> = (1..10).map(|i| (i, i * 10)).collect();
+    /// let r = dst.copy_selected_from(&src, &[0, 2, 3]);
+    /// assert_eq!(r, [Err(SplitMutError::NoValue), Ok(()), Ok(())]);
+    /// assert_eq!(dst.values().collect::<Vec<_>>(), [&0, &0, &20, &30]);
     /// ```
-    fn get_mut_iter<I: Iterator<Item=K>>(&mut self, i: I) -> GetMutIter<K, V, Self, I> { GetMutIter(self.get_muts(), i) }
+    fn copy_selected_from<O: GetRef<K, V> + ?Sized, I: IntoIterator<Item=K>>(&mut self, other: &O, keys: I) -> Vec<Result<(), SplitMutError>>
+    where K: Clone, V: Clone {
+        trace_method!("copy_selected_from");
+        keys.into_iter().map(|k| {
+            let src = other.get1_ref(k.clone()).ok_or_else(|| err(SplitMutError::NoValue))?;
+            let dst = self.get1_mut(k).ok_or_else(|| err(SplitMutError::NoValue))?;
+            dst.clone_from(src);
+            Ok(())
+        }).collect()
+    }
 
     /// Returns two mutable references to two distinct values within
     /// the same collection.
     /// 
-    /// # Undefined behaviour
+    /// # Safety
     /// It is undefined behaviour to call this with a key that does not
     /// correspond to a value, or with keys pointing to the same value.
     /// You have been warned.
@@ -196,7 +1267,7 @@ pub unsafe trait SplitMut<K, V> {
     /// Returns three mutable references to three distinct values within
     /// the same collection.
     /// 
-    /// # Undefined behaviour
+    /// # Safety
     /// It is undefined behaviour to call this with a key that does not
     /// correspond to a value, or with any two keys pointing to the same value.
     /// You have been warned.
@@ -209,7 +1280,7 @@ pub unsafe trait SplitMut<K, V> {
     /// Returns four mutable references to four distinct values within
     /// the same collection.
     /// 
-    /// # Undefined behaviour
+    /// # Safety
     /// It is undefined behaviour to call this with a key that does not
     /// correspond to a value, or with any two keys pointing to the same value.
     /// You have been warned.
@@ -219,11 +1290,39 @@ pub unsafe trait SplitMut<K, V> {
         let p4 = self.get1_unchecked_mut(k4) as *mut V;
         (self.get1_unchecked_mut(k1), &mut *p2, &mut *p3, &mut *p4)
     }
+
+    getn_mut!("five", get5_mut, get5_unchecked_mut, k1 0, k2 1, k3 2, k4 3, k5 4);
+    getn_mut!("six", get6_mut, get6_unchecked_mut, k1 0, k2 1, k3 2, k4 3, k5 4, k6 5);
+    getn_mut!("seven", get7_mut, get7_unchecked_mut, k1 0, k2 1, k3 2, k4 3, k5 4, k6 5, k7 6);
+    getn_mut!("eight", get8_mut, get8_unchecked_mut, k1 0, k2 1, k3 2, k4 3, k5 4, k6 5, k7 6, k8 7);
+}
+
+impl<K, V, A: SplitMutBase<K, V> + ?Sized> SplitMut<K, V> for A {}
+
+/// Lending iterator returned from the iter_pairs_mut function.
+///
+/// This cannot be a regular `Iterator`, because the returned references
+/// only live until the next call to `next`.
+pub struct PairsMut<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized, I> { a: &'a mut A, i: I, _p: PhantomData<(K, *mut V)> }
+
+impl<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>> PairsMut<'a, K, V, A, I> {
+    /// Returns the values of the next two keys, or `None` when there are no keys left.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>)> {
+        trace_method!("iter_pairs_mut");
+        let k1 = self.i.next()?;
+        Some(match self.i.next() {
+            Some(k2) => self.a.get2_mut(k1, k2),
+            None => (self.a.get1_mut(k1).ok_or_else(|| err(SplitMutError::NoValue)), Err(err(SplitMutError::NoValue))),
+        })
+    }
 }
 
 /// Wrapper struct for the get_muts function. 
-pub struct GetMuts<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized>(&'a mut A, HashSet<*mut V>, PhantomData<*const K>);
+#[cfg(feature = "std")]
+pub struct GetMuts<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized>(Tracker, &'a mut A, PhantomData<(*const K, *mut V)>, PtrSet<usize>);
 
+#[cfg(feature = "std")]
 impl<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized> GetMuts<'a, K, V, A> {
     /// Returns a mutable reference with the same lifetime as the
     /// original collection, i e, it allows several distinct mutable references to
@@ -231,16 +1330,43 @@ impl<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized> GetMuts<'a, K, V, A> {
     ///
     /// It returnes an error if the value does not exist or has already been returned.
     pub fn at(&mut self, k: K) -> Result<&'a mut V, SplitMutError> {
-        let p = try!(to_r(self.0.get1_mut(k)));
-        if !self.1.insert(p) { return Err(SplitMutError::SameValue) };
+        trace_method!("at");
+        let p = to_r(self.1.get1_mut(k))?;
+        if self.3.contains(&(p as usize)) || !self.0.insert(p as usize) { return Err(err(SplitMutError::SameValue)) };
         Ok(unsafe { &mut *p })
     }
+
+    /// Like `at`, but returns a shared reference. The same value can be returned
+    /// by `at_ref` any number of times, but not by both `at` and `at_ref`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// let mut z = v.get_muts();
+    /// let (a, b) = (z.at_ref(0).unwrap(), z.at_ref(0).unwrap());
+    /// let c = z.at(2).unwrap();
+    /// *c += a + b;
+    /// assert_eq!(z.at(0), Err(SplitMutError::SameValue));
+    /// assert_eq!(z.at_ref(2), Err(SplitMutError::SameValue));
+    /// assert_eq!(v, [1, 2, 5]);
+    /// ```
+    pub fn at_ref(&mut self, k: K) -> Result<&'a V, SplitMutError> {
+        trace_method!("at_ref");
+        let p = to_r(self.1.get1_mut(k))?;
+        if self.0.contains(p as usize) { return Err(err(SplitMutError::SameValue)) };
+        self.3.insert(p as usize);
+        Ok(unsafe { &*p })
+    }
 }
 
 
 /// Wrapper struct for the get_mut_iter function. 
+#[cfg(feature = "std")]
 pub struct GetMutIter<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized, I>(GetMuts<'a, K, V, A>, I);
 
+#[cfg(feature = "std")]
 impl<'a, K, V: 'a, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>> Iterator for GetMutIter<'a, K, V, A, I> {
     type Item = Result<&'a mut V, SplitMutError>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -248,42 +1374,527 @@ impl<'a, K, V: 'a, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>> Iterato
     }
 }
 
+#[cfg(feature = "std")]
+impl<'a, K, V: 'a, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>> GetMutIter<'a, K, V, A, I> {
+    /// Takes the values of the next `N` keys, failing with the first error.
+    ///
+    /// If there are fewer than `N` keys left, this gives `Err(SplitMutError::NoValue)`.
+    /// Keys after the first `N`, or after the one that failed, are left in the iterator.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMut, SplitMutError};
+    ///
+    /// let mut v = vec![1, 2, 3, 4];
+    /// let [a, b, c] = v.get_mut_iter(vec![3, 0, 1]).collect_array().unwrap();
+    /// *a += *b + *c;
+    /// assert_eq!(v[3], 7);
+    /// assert_eq!(v.get_mut_iter(vec![1, 1]).collect_array::<2>().err(), Some(SplitMutError::SameValue));
+    /// assert_eq!(v.get_mut_iter(vec![1]).collect_array::<2>().err(), Some(SplitMutError::NoValue));
+    /// ```
+    pub fn collect_array<const N: usize>(&mut self) -> Result<[&'a mut V; N], SplitMutError> {
+        let mut r: [Option<&'a mut V>; N] = core::array::from_fn(|_| None);
+        for x in r.iter_mut() {
+            *x = Some(self.next().unwrap_or_else(|| Err(err(SplitMutError::NoValue)))?);
+        }
+        Ok(r.map(Option::unwrap))
+    }
+}
+
+/// Wrapper struct for the get_mut_iter_ok function.
+#[cfg(feature = "std")]
+pub struct OkMutIter<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized, I>(GetMutIter<'a, K, V, A, I>);
+
+#[cfg(feature = "std")]
+impl<'a, K, V: 'a, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>> Iterator for OkMutIter<'a, K, V, A, I> {
+    type Item = &'a mut V;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Ok(v) = self.0.next()? { return Some(v) }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { (0, (self.0).1.size_hint().1) }
+}
+
+/// Wrapper struct for the get_mut_chunks function.
+#[cfg(feature = "std")]
+pub struct ChunksMutIter<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized, I, const N: usize>(GetMutIter<'a, K, V, A, I>);
+
+#[cfg(feature = "std")]
+impl<'a, K, V: 'a, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>, const N: usize> Iterator for ChunksMutIter<'a, K, V, A, I, N> {
+    type Item = [Result<&'a mut V, SplitMutError>; N];
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut first = Some((self.0).1.next()?);
+        Some(core::array::from_fn(|_| match first.take().or_else(|| (self.0).1.next()) {
+            Some(k) => (self.0).0.at(k),
+            None => Err(err(SplitMutError::NoValue)),
+        }))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = (self.0).1.size_hint();
+        (lo.div_ceil(N), hi.map(|hi| hi.div_ceil(N)))
+    }
+}
+
+/// Wrapper struct for the get_mut_iter_keyed function.
+#[cfg(feature = "std")]
+pub struct KeyedMutIter<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized, I>(GetMutIter<'a, K, V, A, I>);
+
+#[cfg(feature = "std")]
+impl<'a, K: Clone, V: 'a, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>> Iterator for KeyedMutIter<'a, K, V, A, I> {
+    type Item = (K, Result<&'a mut V, SplitMutError>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let k = (self.0).1.next()?;
+        let r = (self.0).0.at(k.clone());
+        Some((k, r))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { (self.0).1.size_hint() }
+}
+
+/// Wrapper struct for the expect_mut_iter function.
+#[cfg(feature = "std")]
+pub struct ExpectMutIter<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized, I>(GetMuts<'a, K, V, A>, I, usize);
+
+#[cfg(feature = "std")]
+impl<'a, K, V: 'a, A: 'a + SplitMut<K, V> + ?Sized, I: Iterator<Item=K>> Iterator for ExpectMutIter<'a, K, V, A, I> {
+    type Item = &'a mut V;
+    fn next(&mut self) -> Option<Self::Item> {
+        let k = self.1.next()?;
+        let pos = self.2;
+        self.2 += 1;
+        match self.0.at(k) {
+            Ok(v) => Some(v),
+            Err(SplitMutError::SameValue) => panic!("expect_mut_iter: key at position {} refers to a value already returned", pos),
+            Err(e) => panic!("expect_mut_iter: key at position {}: {}", pos, e),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) { self.1.size_hint() }
+}
+
+
+#[inline]
+fn slice_range<V>(s: &[V]) -> (usize, usize) {
+    let r = s.as_ptr_range();
+    (r.start as usize, r.end as usize)
+}
+
+// Two bounds checks and one comparison, with no pointer compares in between.
+// Zero-sized values all have the same address, so like in the general version,
+// the second one is then always SameValue; the size is known at compile time.
+#[cfg(not(feature = "safe"))]
+#[inline]
+fn slice_get2<V>(s: &mut [V], k1: usize, k2: usize) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+    let (len, p) = (s.len(), s.as_mut_ptr());
+    let r1 = if k1 < len { Ok(unsafe { &mut *p.add(k1) }) } else { Err(err(SplitMutError::NoValue)) };
+    let r2 = if k2 >= len { Err(err(SplitMutError::NoValue)) }
+        else if k1 < len && (k1 == k2 || core::mem::size_of::<V>() == 0) { Err(err(SplitMutError::SameValue)) }
+        else { Ok(unsafe { &mut *p.add(k2) }) };
+    (r1, r2)
+}
 
-unsafe impl<'a, V> SplitMut<usize, V> for &'a mut [V] {
+unsafe impl<V> SplitMutBase<usize, V> for &mut [V] {
+    #[inline]
+    fn value_range(&self) -> Option<(usize, usize)> { Some(slice_range(self)) }
+    #[cfg(feature = "safe")]
+    safe_get_methods!(usize, safe::slice_get);
+    #[cfg(not(feature = "safe"))]
+    #[inline]
+    fn split2_mut(&mut self, k1: usize, k2: usize) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) { slice_get2(self, k1, k2) }
     #[inline]
     fn get1_mut(&mut self, k: usize) -> Option<&mut V> { self.get_mut(k) }
     #[inline]
     unsafe fn get1_unchecked_mut(&mut self, k: usize) -> &mut V { self.get_unchecked_mut(k) }
 }
 
-unsafe impl<'a, V> SplitMut<usize, V> for Vec<V> {
+unsafe impl<V> SplitMutBase<usize, V> for Vec<V> {
+    #[inline]
+    fn value_range(&self) -> Option<(usize, usize)> { Some(slice_range(self)) }
+    #[cfg(feature = "safe")]
+    safe_get_methods!(usize, safe::slice_get);
+    #[cfg(not(feature = "safe"))]
+    #[inline]
+    fn split2_mut(&mut self, k1: usize, k2: usize) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) { slice_get2(self, k1, k2) }
     #[inline]
     fn get1_mut(&mut self, k: usize) -> Option<&mut V> { self.get_mut(k) }
     #[inline]
     unsafe fn get1_unchecked_mut(&mut self, k: usize) -> &mut V { self.get_unchecked_mut(k) }
 }
 
-unsafe impl<'a, V> SplitMut<usize, V> for VecDeque<V> {
+unsafe impl<V> SplitMutBase<usize, V> for VecDeque<V> {
+    #[inline]
+    fn value_range(&self) -> Option<(usize, usize)> {
+        let (a, b) = self.as_slices();
+        let (a, b) = (slice_range(a), slice_range(b));
+        if b.0 == b.1 { Some(a) } else { Some((a.0.min(b.0), a.1.max(b.1))) }
+    }
+    #[cfg(feature = "safe")]
+    safe_get_methods!(usize, safe::deque_get);
     #[inline]
     fn get1_mut(&mut self, k: usize) -> Option<&mut V> { self.get_mut(k) }
     #[inline]
-    unsafe fn get1_unchecked_mut(&mut self, k: usize) -> &mut V { std::mem::transmute(self.get_mut(k)) }
+    unsafe fn get1_unchecked_mut(&mut self, k: usize) -> &mut V { core::mem::transmute(self.get_mut(k)) }
 }
 
-unsafe impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> SplitMut<&'a Q, V> for HashMap<K, V, S> {
+#[cfg(feature = "std")]
+unsafe impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> SplitMutBase<&'a Q, V> for HashMap<K, V, S> {
+    #[cfg(feature = "safe")]
+    safe_get_methods!(&'a Q, safe::hash_get);
     #[inline]
     fn get1_mut(&mut self, k: &'a Q) -> Option<&mut V> { self.get_mut(k) }
     #[inline]
-    unsafe fn get1_unchecked_mut(&mut self, k: &'a Q) -> &mut V { std::mem::transmute(self.get_mut(k)) }
+    unsafe fn get1_unchecked_mut(&mut self, k: &'a Q) -> &mut V { core::mem::transmute(self.get_mut(k)) }
 }
 
-unsafe impl<'a, K: Ord + borrow::Borrow<Q>, Q: Ord + ?Sized, V> SplitMut<&'a Q, V> for BTreeMap<K, V> {
+unsafe impl<'a, K: Ord + borrow::Borrow<Q>, Q: Ord + ?Sized, V> SplitMutBase<&'a Q, V> for BTreeMap<K, V> {
+    #[cfg(feature = "safe")]
+    safe_get_methods!(&'a Q, safe::btree_get);
     #[inline]
     fn get1_mut(&mut self, k: &'a Q) -> Option<&mut V> { self.get_mut(k) }
     #[inline]
-    unsafe fn get1_unchecked_mut(&mut self, k: &'a Q) -> &mut V { std::mem::transmute(self.get_mut(k)) }
+    unsafe fn get1_unchecked_mut(&mut self, k: &'a Q) -> &mut V { core::mem::transmute(self.get_mut(k)) }
+}
+
+/// Shared lookups, for the functions that read from a collection without
+/// taking any mutable borrows.
+///
+/// Implemented for slices, Vec, VecDeque, HashMap and BTreeMap, with the same keys
+/// as their `SplitMut` implementations.
+pub trait GetRef<K, V> {
+    /// Wrapper for get, used internally.
+    fn get1_ref(&self, k: K) -> Option<&V>;
+
+    /// Returns true if all keys have values, and no two keys refer to the same value,
+    /// i e, if `get_mut_iter` would return only `Ok` values for these keys.
+    /// No mutable borrows are taken.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::GetRef;
+    ///
+    /// let v = vec![1, 2, 3];
+    /// assert!(v.are_disjoint(vec![2, 0]));
+    /// assert!(!v.are_disjoint(vec![2, 0, 2]));
+    /// assert!(!v.are_disjoint(vec![3]));
+    /// ```
+    fn are_disjoint<I: IntoIterator<Item=K>>(&self, keys: I) -> bool {
+        let mut p = Vec::new();
+        for k in keys {
+            match self.get1_ref(k) {
+                Some(v) => p.push(v as *const V as usize),
+                None => return false,
+            }
+        }
+        p.sort_unstable();
+        p.windows(2).all(|w| w[0] != w[1])
+    }
+
+    /// Checks a batch of keys without taking any mutable borrows, and reports
+    /// which keys have no value and which refer to the same value as an earlier key.
+    ///
+    /// The keys that are reported are exactly those for which `get_mut_iter` would
+    /// return an error, so a batch can be validated, and rejected with a useful
+    /// message, before splitting the collection.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::GetRef;
+    ///
+    /// let v = vec![1, 2, 3];
+    /// let r = v.analyze_keys(vec![2, 5, 0, 2]);
+    /// assert_eq!(r.missing, [1]);
+    /// assert_eq!(r.duplicates, [(3, 0)]);
+    /// assert_eq!(r.to_string(), "key 1 has no value; key 3 refers to the same value as key 0");
+    /// assert!(v.analyze_keys(vec![2, 0]).is_ok());
+    /// ```
+    fn analyze_keys<I: IntoIterator<Item=K>>(&self, keys: I) -> KeyReport {
+        let mut r = KeyReport { missing: Vec::new(), duplicates: Vec::new() };
+        let mut p = Vec::new();
+        for (i, k) in keys.into_iter().enumerate() {
+            match self.get1_ref(k) {
+                Some(v) => p.push((v as *const V as usize, i)),
+                None => r.missing.push(i),
+            }
+        }
+        p.sort_unstable();
+        for w in p.chunk_by(|a, b| a.0 == b.0) {
+            r.duplicates.extend(w[1..].iter().map(|x| (x.1, w[0].1)));
+        }
+        r.duplicates.sort_unstable();
+        r
+    }
+}
+
+/// The result of the analyze_keys function. Keys are referred to by their position
+/// in the batch.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyReport {
+    /// Positions of the keys without a value, in order.
+    pub missing: Vec<usize>,
+    /// Positions of the keys referring to the same value as an earlier key, in order,
+    /// each with the position of the first key referring to that value.
+    pub duplicates: Vec<(usize, usize)>,
+}
+
+impl KeyReport {
+    /// Returns true if all keys have values and refer to different values.
+    pub fn is_ok(&self) -> bool { self.missing.is_empty() && self.duplicates.is_empty() }
+
+    /// Returns the error `get_mut_iter` would give for the key at position `i`, if any.
+    pub fn error_at(&self, i: usize) -> Option<SplitMutError> {
+        if self.missing.binary_search(&i).is_ok() { Some(SplitMutError::NoValue) }
+        else if self.duplicates.binary_search_by_key(&i, |x| x.0).is_ok() { Some(SplitMutError::SameValue) }
+        else { None }
+    }
+}
+
+impl core::fmt::Display for KeyReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if self.is_ok() { return write!(f, "all keys are valid") }
+        let (mut m, mut d) = (self.missing.iter().peekable(), self.duplicates.iter().peekable());
+        let mut first = true;
+        loop {
+            let missing = match (m.peek(), d.peek()) {
+                (None, None) => return Ok(()),
+                (Some(a), Some(b)) => **a < b.0,
+                (a, _) => a.is_some(),
+            };
+            if !first { write!(f, "; ")? }
+            first = false;
+            if missing { write!(f, "key {} has no value", m.next().unwrap())? }
+            else {
+                let (a, b) = d.next().unwrap();
+                write!(f, "key {} refers to the same value as key {}", a, b)?
+            }
+        }
+    }
+}
+
+impl<V> GetRef<usize, V> for [V] {
+    #[inline]
+    fn get1_ref(&self, k: usize) -> Option<&V> { self.get(k) }
+}
+
+impl<V> GetRef<usize, V> for Vec<V> {
+    #[inline]
+    fn get1_ref(&self, k: usize) -> Option<&V> { self.get(k) }
+}
+
+impl<V> GetRef<usize, V> for VecDeque<V> {
+    #[inline]
+    fn get1_ref(&self, k: usize) -> Option<&V> { self.get(k) }
+}
+
+#[cfg(feature = "std")]
+impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> GetRef<&'a Q, V> for HashMap<K, V, S> {
+    #[inline]
+    fn get1_ref(&self, k: &'a Q) -> Option<&V> { self.get(k) }
+}
+
+impl<'a, K: Ord + borrow::Borrow<Q>, Q: Ord + ?Sized, V> GetRef<&'a Q, V> for BTreeMap<K, V> {
+    #[inline]
+    fn get1_ref(&self, k: &'a Q) -> Option<&V> { self.get(k) }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn try_get() {
+    fn sum(h: &mut HashMap<&'static str, i32>, a: &str, b: &str, c: &str) -> Result<i32, SplitMutError> {
+        let (a, b, c) = h.try_get3_mut(a, b, c)?;
+        Ok(*a + *b + *c)
+    }
+    let mut h: HashMap<_, _> = vec![("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    assert_eq!(sum(&mut h, "a", "b", "c"), Ok(6));
+    assert_eq!(sum(&mut h, "a", "x", "a"), Err(SplitMutError::NoValue));
+    assert_eq!(h.try_get3_mut("a", "b", "a").err(), Some(KeyError { position: 2, error: SplitMutError::SameValue }));
+    let mut v = vec![1, 2, 3, 4];
+    assert!(v.try_get4_mut(3, 2, 1, 0).is_ok());
+    assert_eq!(v.try_get4_mut(3, 2, 1, 3).unwrap_err().position, 3);
+}
+
+#[test]
+fn opt_getters() {
+    let mut b: BTreeMap<_, _> = (0..3).map(|i| (i, i)).collect();
+    assert_eq!(b.get3_opt_mut(&2, &3, &2), (Some(&mut 2), None, None));
+    let mut v = vec![1, 2, 3, 4];
+    assert_eq!(v.get4_opt_mut(0, 1, 2, 3), (Some(&mut 1), Some(&mut 2), Some(&mut 3), Some(&mut 4)));
+    assert_eq!(v.get2_opt_mut(4, 0), (None, Some(&mut 1)));
+}
+
+#[test]
+fn unwrap_getters() {
+    use alloc::string::String;
+    let mut v = vec![1, 2, 3, 4];
+    let (a, b, c, d) = v.get4_mut_unwrap(3, 2, 1, 0);
+    std::mem::swap(a, d);
+    std::mem::swap(b, c);
+    assert_eq!(v, [4, 3, 2, 1]);
+    let e = std::panic::catch_unwind(move || { v.get3_mut_unwrap(0, 4, 1); }).unwrap_err();
+    assert_eq!(e.downcast_ref::<String>().unwrap(), "get3_mut_unwrap: key at position 1 has no value");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn collect_array() {
+    let mut h: HashMap<_, _> = (0..5).map(|i| (i, i)).collect();
+    let k: Vec<_> = (0..6).collect();
+    let mut i = h.get_mut_iter(k.iter().rev());
+    assert_eq!(i.collect_array::<0>(), Ok([]));
+    assert_eq!(i.collect_array::<2>(), Err(SplitMutError::NoValue));
+    assert_eq!(i.collect_array::<2>(), Ok([&mut 4, &mut 3]));
+    assert_eq!(i.next(), Some(Ok(&mut 2)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn mut_refs() {
+    let mut v = vec![1, 2, 3];
+    for x in v.get_mut_refs(&[2, 0, 3]).into_iter().flatten() { *x *= 10 }
+    assert_eq!(v, [10, 2, 30]);
+    assert!(v.get_mut_refs(&[0; 0]).is_empty());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn mut_map() {
+    let mut v = vec![1, 2, 3];
+    {
+        let mut m = v.get_mut_map(vec![2, 5, 0, 2]);
+        let mut k: Vec<_> = m.keys().cloned().collect();
+        k.sort();
+        assert_eq!(k, [0, 2]);
+        **m.get_mut(&2).unwrap() = 30;
+    }
+    assert_eq!(v, [1, 2, 30]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn borrowed_keys() {
+    use std::path::{Path, PathBuf};
+    let mut h: HashMap<PathBuf, u32> = HashMap::new();
+    h.insert("/a".into(), 1);
+    h.insert("/b".into(), 2);
+    let wanted: Vec<PathBuf> = vec!["/b".into(), "/c".into()];
+    let r: Vec<_> = h.get_mut_iter(&wanted).collect();
+    assert_eq!(r, [Ok(&mut 2), Err(SplitMutError::NoValue)]);
+    let r: Vec<_> = h.get_mut_iter(wanted.iter().map(|p| p.as_path()).chain(Some(Path::new("/b")))).collect();
+    assert_eq!(r, [Ok(&mut 2), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue)]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn keyed_iter() {
+    use alloc::string::{String, ToString};
+    let mut h: BTreeMap<String, u32> = vec![("x".to_string(), 1), ("y".to_string(), 2)].into_iter().collect();
+    let r: Vec<_> = h.get_mut_iter_keyed(vec!["y", "z", "y"]).map(|(k, r)| (k, r.map(|v| *v))).collect();
+    assert_eq!(r, [("y", Ok(2)), ("z", Err(SplitMutError::NoValue)), ("y", Err(SplitMutError::SameValue))]);
+    assert_eq!(h.get_mut_iter_keyed(["x"; 4]).size_hint(), (4, Some(4)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn try_iter() {
+    let mut h: HashMap<_, _> = (0..3).map(|i| (i, i)).collect();
+    let k: Vec<_> = (0..4).collect();
+    assert_eq!(h.try_get_mut_iter(&k[..3]).map(|v| v.len()), Ok(3));
+    assert_eq!(h.try_get_mut_iter(k.iter().rev()).err(), Some(KeyError { position: 0, error: SplitMutError::NoValue }));
+    assert_eq!(h.try_get_mut_iter(&k[..0]), Ok(vec![]));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn ok_iter() {
+    let mut v: Vec<u32> = (0..5).collect();
+    let r: Vec<_> = v.get_mut_iter_ok(vec![9, 4, 4, 0, 7]).map(|x| *x).collect();
+    assert_eq!(r, [4, 0]);
+    assert_eq!(v.get_mut_iter_ok(0..3).size_hint(), (0, Some(3)));
+    assert_eq!(v.get_mut_iter_ok(5..9).next(), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn chunks() {
+    let mut h: BTreeMap<_, _> = (0..6).map(|i| (i, i)).collect();
+    let k: Vec<_> = vec![0, 1, 2, 3, 1, 5, 9];
+    let r: Vec<[Result<&mut i32, SplitMutError>; 3]> = h.get_mut_chunks(&k).collect();
+    assert_eq!(r.len(), 3);
+    assert_eq!(r[1], [Ok(&mut 3), Err(SplitMutError::SameValue), Ok(&mut 5)]);
+    assert_eq!(r[2], [Err(SplitMutError::NoValue), Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)]);
+    assert_eq!(h.get_mut_chunks::<4, _>(&k).size_hint(), (2, Some(2)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "chunk size must be non-zero")]
+fn chunks_zero() { vec![1].get_mut_chunks::<0, _>(vec![0]); }
+
+#[cfg(feature = "std")]
+#[test]
+fn pairs_stream() {
+    let mut h: HashMap<_, _> = (0..3).map(|i| (i, i)).collect();
+    let k = [0, 1, 2];
+    let mut p = h.iter_pairs_mut(vec![&k[2], &k[2], &k[0], &k[1], &k[1]]);
+    assert_eq!(p.next(), Some((Ok(&mut 2), Err(SplitMutError::SameValue))));
+    if let Some((Ok(a), Ok(b))) = p.next() { *a += *b * 10 }
+    assert_eq!(p.next(), Some((Ok(&mut 1), Err(SplitMutError::NoValue))));
+    assert_eq!(p.next(), None);
+    assert_eq!(h[&0], 10);
+}
+
+#[test]
+fn disjoint_array() {
+    let mut h: BTreeMap<_, _> = (0..4).map(|i| (i, i * 10)).collect();
+    let k = [0, 1, 2, 3, 4];
+    let r = h.get_disjoint_mut([&k[3], &k[4], &k[0], &k[3]]);
+    assert_eq!(r, [Ok(&mut 30), Err(SplitMutError::NoValue), Ok(&mut 0), Err(SplitMutError::SameValue)]);
+    let [] = h.get_disjoint_mut::<0>([]);
+    let mut z = vec![(); 2];
+    assert_eq!(z.get_disjoint_mut([1, 1]), [Ok(&mut ()), Err(SplitMutError::SameValue)]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn get8() {
+    let mut v: Vec<u32> = (0..10).collect();
+    {
+        let (a, b, c, d, e, f, g, h) = v.get8_mut(9, 0, 9, 1, 10, 2, 3, 4);
+        assert_eq!((c, e), (Err(SplitMutError::SameValue), Err(SplitMutError::NoValue)));
+        for x in [a, b, d, f, g, h].iter_mut() { **x.as_mut().unwrap() += 100 }
+    }
+    assert_eq!(v, [100, 101, 102, 103, 104, 5, 6, 7, 8, 109]);
+    let (a, b, c, d, e) = unsafe { v.get5_unchecked_mut(4, 3, 2, 1, 0) };
+    std::mem::swap(a, e);
+    std::mem::swap(b, d);
+    *c = 0;
+    assert_eq!(v[..5], [104, 103, 0, 101, 100]);
+    let mut h: HashMap<_, _> = (0..7).map(|i| (i, i)).collect();
+    let k: Vec<_> = (0..7).collect();
+    let r = h.get7_mut(&k[6], &k[5], &k[4], &k[3], &k[2], &k[1], &k[6]);
+    assert_eq!((r.0, r.6), (Ok(&mut 6), Err(SplitMutError::SameValue)));
+    assert_eq!(unsafe { h.get6_unchecked_mut(&k[0], &k[1], &k[2], &k[3], &k[4], &k[5]) }.5, &mut 5);
 }
 
+#[cfg(feature = "std")]
+#[test]
+fn analyze_keys() {
+    use alloc::string::ToString;
+    let mut b = BTreeMap::new();
+    b.insert("a", 1);
+    b.insert("b", 2);
+    let r = b.analyze_keys(vec!["b", "x", "b", "a", "b", "y"]);
+    assert_eq!(r.missing, [1, 5]);
+    assert_eq!(r.duplicates, [(2, 0), (4, 0)]);
+    let e: Vec<_> = (0..6).map(|i| r.error_at(i)).collect();
+    let z: Vec<_> = b.get_mut_iter(["b", "x", "b", "a", "b", "y"].iter().cloned()).map(|x| x.err()).collect();
+    assert_eq!(e, z);
+    assert_eq!(r.to_string(), "key 1 has no value; key 2 refers to the same value as key 0; \
+        key 4 refers to the same value as key 0; key 5 has no value");
+    let r = b.analyze_keys(vec!["a"]);
+    assert!(r.is_ok());
+    assert_eq!(r.to_string(), "all keys are valid");
+}
+
+#[cfg(feature = "std")]
 #[test]
 fn hash_same() {
     let mut h = HashMap::new();
@@ -291,6 +1902,7 @@ fn hash_same() {
     assert_eq!(h.get2_mut(&3, &3), (Ok(&mut 5u16), Err(SplitMutError::SameValue)));
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn hash_reg() {
     let mut h = HashMap::new();
@@ -304,13 +1916,15 @@ fn hash_reg() {
     assert_eq!(h.get2_mut(&2, &3), (Err(SplitMutError::NoValue), Ok(&mut 9u16)));
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn tree_borrow() {
+    use alloc::string::String;
     let mut h = BTreeMap::new();
     h.insert(String::from("borrow"), 1);   
     h.insert(String::from("me"), 2);
     let slice = ["me", "borrow", "me"];
-    let z: Vec<_> = h.get_mut_iter(slice.into_iter().map(|&k| k)).collect();
+    let z: Vec<_> = h.get_mut_iter(slice.iter().cloned()).collect();
     assert_eq!(&*z, [Ok(&mut 2), Ok(&mut 1), Err(SplitMutError::SameValue)]);
 }
 
@@ -334,6 +1948,7 @@ fn deque_reg() {
     assert_eq!(h.get2_mut(2, 0), (Err(SplitMutError::NoValue), Ok(&mut 9u16)));
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn vec() {
     let mut h = vec!["Hello", "world", "!"];
@@ -355,3 +1970,151 @@ fn vec() {
     }
     assert_eq!(&*h, &["Hello", "world", "universe"]);
 }
+
+#[test]
+fn fields() {
+    struct P { x: i32, y: (i32, i32) }
+    struct S { p: P, q: Vec<u8> }
+    let mut s = S { p: P { x: 1, y: (2, 3) }, q: vec![] };
+    {
+        let (x, y, q) = splitmut_fields!(&mut s => p.x, p.y, q);
+        std::mem::swap(x, &mut y.0);
+        q.push(5);
+    }
+    assert_eq!((s.p.x, s.p.y, &*s.q), (2, (1, 3), &[5u8][..]));
+    let m = std::sync::Mutex::new(s);
+    let mut g = m.lock().unwrap();
+    let (x, q) = splitmut_fields!(g => p.x, q);
+    q.push(*x as u8);
+    assert_eq!(*q, [5, 2]);
+}
+
+#[test]
+fn maybe_keys() {
+    let mut h = BTreeMap::new();
+    h.insert(1, 'a');
+    h.insert(2, 'b');
+    assert_eq!(h.get3_maybe_mut(Some(&2), None, Some(&2)), (Ok(&mut 'b'), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue)));
+    assert_eq!(h.get4_maybe_mut(None, Some(&1), Some(&3), Some(&2)),
+        (Err(SplitMutError::NoValue), Ok(&mut 'a'), Err(SplitMutError::NoValue), Ok(&mut 'b')));
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "key at position 2 refers to a value already returned")]
+fn expect_iter() {
+    let mut h = vec![1, 2, 3];
+    let z: Vec<_> = h.expect_mut_iter(vec![0, 1]).collect();
+    assert_eq!(z, [&mut 1, &mut 2]);
+    for _ in h.expect_mut_iter(vec![2, 0, 2]) {}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn copy_selected() {
+    let mut dst = vec![0u8; 4];
+    let mut src = HashMap::new();
+    src.insert(1usize, 10u8);
+    let r = dst.copy_selected_from(&vec![5u8, 6, 7], vec![2, 3, 2]);
+    assert_eq!(r, [Ok(()), Err(SplitMutError::NoValue), Ok(())]);
+    assert_eq!(dst, [0, 0, 7, 0]);
+    let mut dst2: HashMap<usize, u8> = (0..3).map(|i| (i, 0)).collect();
+    assert_eq!(dst2.copy_selected_from(&src, &[1, 2]), [Ok(()), Err(SplitMutError::NoValue)]);
+    assert_eq!(dst2[&1], 10);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn accumulate() {
+    let mut v = vec![vec![1], vec![2, 3], vec![]];
+    assert_eq!(v.accumulate_into_mut(2, 0..5, |d, s| d.append(s)), Ok(2));
+    assert_eq!(v, [vec![], vec![], vec![1, 2, 3]]);
+    assert_eq!(v.accumulate_into(3, 0..2, |_, _| ()), Err(SplitMutError::NoValue));
+}
+
+#[test]
+fn slice_pair() {
+    let mut v = vec![1, 2];
+    assert_eq!(v.get2_mut(1, 0), (Ok(&mut 2), Ok(&mut 1)));
+    assert_eq!(v.get2_mut(1, 1), (Ok(&mut 2), Err(SplitMutError::SameValue)));
+    assert_eq!(v.get2_mut(2, 2), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+    assert_eq!((&mut v[..]).get2_mut(0, 2), (Ok(&mut 1), Err(SplitMutError::NoValue)));
+    // The safe feature compares indices, so there zero-sized values are split.
+    #[cfg(not(feature = "safe"))] {
+        let mut z = vec![(); 3];
+        assert_eq!(z.get2_mut(0, 1), (Ok(&mut ()), Err(SplitMutError::SameValue)));
+        assert_eq!(z.get2_mut(3, 1), (Err(SplitMutError::NoValue), Ok(&mut ())));
+        assert_eq!(z.get3_mut(0, 1, 2).1, Err(SplitMutError::SameValue));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn apply_n() {
+    let mut h: HashMap<_, _> = (0..4).map(|i| (i, i * 10)).collect();
+    let r = h.apply_n(vec![&3, &1, &2], |r| { r.rotate_left(1); r.iter().map(|x| **x).sum::<i32>() });
+    assert_eq!(r, Ok(60));
+    assert_eq!(h.apply_n(vec![&1, &5], |_| ()), Err(SplitMutError::NoValue));
+}
+
+#[test]
+fn split_macro() {
+    let mut v = vec![1, 2, 3, 4];
+    for i in 0..4 {
+        split!(let { a = i, b = (i + 1), c = 3 } in v else continue);
+        *a += *b + *c;
+    }
+    assert_eq!(v, [7, 9, 3, 4]);
+}
+
+#[test]
+fn error_helpers() {
+    let mut v = vec![1, 2];
+    let (a, b) = v.get2_mut(2, 2);
+    assert!(a.unwrap_err().is_no_value());
+    assert!(!b.unwrap_err().is_same_value());
+    let (_, b) = v.get2_mut(0, 0);
+    assert!(b.unwrap_err().is_same_value());
+    assert!(SplitMutError::WrongType.is_wrong_type());
+    assert!(SplitMutError::Shared.is_shared());
+    assert!(SplitMutError::Ambiguous.is_ambiguous());
+    assert!(SplitMutError::WouldBlock.is_would_block());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn shared_and_mut() {
+    let mut h: HashMap<_, _> = vec![(1, 'a'), (2, 'b')].into_iter().collect();
+    let mut z = h.get_muts();
+    assert_eq!(z.at_ref(&1), Ok(&'a'));
+    assert_eq!(z.at_ref(&1), Ok(&'a'));
+    assert_eq!(z.at(&1), Err(SplitMutError::SameValue));
+    assert_eq!(z.at(&2), Ok(&mut 'b'));
+    assert_eq!(z.at_ref(&2), Err(SplitMutError::SameValue));
+    assert_eq!(z.at_ref(&3), Err(SplitMutError::NoValue));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn balanced() {
+    let mut h: HashMap<u32, u64> = (0..20).map(|i| (i, if i == 7 { 100 } else { i as u64 })).collect();
+    let keys: Vec<u32> = (0..20).collect();
+    let parts = h.split_balanced_mut(keys.iter(), 3, |&w| w).unwrap();
+    let totals: Vec<u64> = parts.iter().map(|p| p.iter().map(|w| **w).sum()).collect();
+    assert_eq!(totals.iter().sum::<u64>(), 283);
+    assert_eq!(totals.iter().max(), Some(&100));
+    assert_eq!(parts.iter().map(|p| p.len()).sum::<usize>(), 20);
+    assert_eq!(h.split_balanced_mut(vec![&1, &1], 2, |_| 1).err(), Some(SplitMutError::SameValue));
+    assert_eq!(h.split_balanced_mut(None, 2, |_| 1).unwrap().len(), 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn star() {
+    let mut h: HashMap<_, _> = (0..4).map(|i| (i, i * 10)).collect();
+    let (c, n) = h.get_star_mut(&5, vec![&2, &3, &2]);
+    assert_eq!(c, Err(SplitMutError::NoValue));
+    assert_eq!(n, [Ok(&mut 20), Ok(&mut 30), Err(SplitMutError::SameValue)]);
+    let (c, n) = h.get_star_mut(&1, None);
+    assert_eq!((c, n), (Ok(&mut 10), vec![]));
+}
@@ -0,0 +1,202 @@
+//! Locking several values in a collection of `RwLock`s at once.
+
+use std::ops::Deref;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, TryLockResult};
+use std::ptr;
+use std::vec::Vec;
+use {GetRef, SplitMutError, err};
+
+/// Whether `lock_many` locks a value for reading or for writing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LockMode {
+    /// Shared access, through `RwLock::read`.
+    Read,
+    /// Exclusive access, through `RwLock::write`.
+    Write,
+}
+
+/// A guard returned from the lock_many function, holding either a read or a write lock.
+pub enum ManyGuard<'a, V: 'a> {
+    /// The value was locked with `LockMode::Read`.
+    Read(RwLockReadGuard<'a, V>),
+    /// The value was locked with `LockMode::Write`.
+    Write(RwLockWriteGuard<'a, V>),
+}
+
+impl<'a, V: 'a> ManyGuard<'a, V> {
+    /// Returns a mutable reference to the value, if it was locked for writing.
+    pub fn get_mut(&mut self) -> Option<&mut V> {
+        match self {
+            ManyGuard::Read(_) => None,
+            ManyGuard::Write(g) => Some(g),
+        }
+    }
+}
+
+impl<'a, V: 'a> Deref for ManyGuard<'a, V> {
+    type Target = V;
+    fn deref(&self) -> &V {
+        match self {
+            ManyGuard::Read(g) => g,
+            ManyGuard::Write(g) => g,
+        }
+    }
+}
+
+/// Just add `use splitmut::SplitMutLock;` to have this working on all collections
+/// with `RwLock` values, e g `HashMap<K, RwLock<V>>`.
+pub trait SplitMutLock<K, V> {
+    /// Locks the values of several keys, each one for reading or writing, and
+    /// returns the guards in the order of the keys.
+    ///
+    /// The locks are taken in the order of their addresses, so two threads calling
+    /// `lock_many` on the same collection cannot deadlock against each other, no matter
+    /// the order of their keys. A key without a value gives `Err(SplitMutError::NoValue)`,
+    /// and a key whose value was already locked by the same call gives
+    /// `Err(SplitMutError::SameValue)`, instead of deadlocking on it.
+    ///
+    /// Poisoned locks are taken anyway, like the other locking functions of this crate do.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use std::sync::RwLock;
+    /// use splitmut::{SplitMutLock, LockMode, SplitMutError};
+    ///
+    /// let h: HashMap<_, _> = vec![("a", RwLock::new(1)), ("b", RwLock::new(2))].into_iter().collect();
+    /// let mut g = h.lock_many(vec![(&"b", LockMode::Write), (&"a", LockMode::Read), (&"b", LockMode::Read)]);
+    /// assert_eq!(g[2].as_ref().err(), Some(&SplitMutError::SameValue));
+    /// let a = **g[1].as_ref().unwrap();
+    /// *g[0].as_mut().unwrap().get_mut().unwrap() += a;
+    /// drop(g);
+    /// assert_eq!(*h["b"].read().unwrap(), 3);
+    /// ```
+    fn lock_many<I: IntoIterator<Item=(K, LockMode)>>(&self, keys: I) -> Vec<Result<ManyGuard<'_, V>, SplitMutError>>;
+
+    /// Like `lock_many`, but does not wait for values that are locked elsewhere:
+    /// these give `Err(SplitMutError::WouldBlock)`, and the other values are still locked.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::RwLock;
+    /// use splitmut::{SplitMutLock, LockMode, SplitMutError};
+    ///
+    /// let v = vec![RwLock::new(1), RwLock::new(2)];
+    /// let held = v[1].write().unwrap();
+    /// let g = v.try_lock_many(vec![(1, LockMode::Read), (0, LockMode::Write)]);
+    /// assert_eq!(g[0].as_ref().err(), Some(&SplitMutError::WouldBlock));
+    /// assert!(g[1].is_ok());
+    /// # drop(held);
+    /// ```
+    fn try_lock_many<I: IntoIterator<Item=(K, LockMode)>>(&self, keys: I) -> Vec<Result<ManyGuard<'_, V>, SplitMutError>>;
+
+    /// Locks the values of two keys, like `lock_many` does.
+    fn lock2(&self, k1: K, m1: LockMode, k2: K, m2: LockMode) -> (Result<ManyGuard<'_, V>, SplitMutError>, Result<ManyGuard<'_, V>, SplitMutError>) {
+        trace_method!("lock2");
+        pair(self.lock_many(vec![(k1, m1), (k2, m2)]))
+    }
+
+    /// Locks the values of two keys without waiting, like `try_lock_many` does.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::sync::RwLock;
+    /// use splitmut::{SplitMutLock, LockMode, SplitMutError};
+    ///
+    /// let m: BTreeMap<_, _> = (0..3).map(|i| (i, RwLock::new(i))).collect();
+    /// let (a, b) = m.lock2(&0, LockMode::Write, &1, LockMode::Read);
+    /// let (c, d) = m.try_lock2(&1, LockMode::Read, &0, LockMode::Read);
+    /// assert!(c.is_ok());
+    /// assert_eq!(d.err().unwrap(), SplitMutError::WouldBlock);
+    /// # drop((a, b));
+    /// ```
+    fn try_lock2(&self, k1: K, m1: LockMode, k2: K, m2: LockMode) -> (Result<ManyGuard<'_, V>, SplitMutError>, Result<ManyGuard<'_, V>, SplitMutError>) {
+        trace_method!("try_lock2");
+        pair(self.try_lock_many(vec![(k1, m1), (k2, m2)]))
+    }
+}
+
+fn pair<T>(v: Vec<T>) -> (T, T) {
+    let mut i = v.into_iter();
+    (i.next().unwrap(), i.next().unwrap())
+}
+
+// Ignores poisoning, and turns WouldBlock into an error.
+fn taken<G>(r: TryLockResult<G>) -> Result<G, SplitMutError> {
+    match r {
+        Ok(g) => Ok(g),
+        Err(TryLockError::Poisoned(e)) => Ok(e.into_inner()),
+        Err(TryLockError::WouldBlock) => Err(err(SplitMutError::WouldBlock)),
+    }
+}
+
+// Looks up all keys and takes their locks with `f`, in the order of their addresses.
+fn lock_all<'a, K, V: 'a, C, I, F>(c: &'a C, keys: I, f: F) -> Vec<Result<ManyGuard<'a, V>, SplitMutError>>
+where C: GetRef<K, RwLock<V>> + ?Sized, I: IntoIterator<Item=(K, LockMode)>, F: Fn(&'a RwLock<V>, LockMode) -> Result<ManyGuard<'a, V>, SplitMutError> {
+    let mut locks: Vec<Result<(&RwLock<V>, LockMode), SplitMutError>> = keys.into_iter()
+        .map(|(k, m)| c.get1_ref(k).map(|l| (l, m)).ok_or_else(|| err(SplitMutError::NoValue))).collect();
+    for i in 1..locks.len() {
+        if let Ok((l, _)) = locks[i] {
+            if locks[..i].iter().any(|x| matches!(x, Ok((p, _)) if ptr::eq(*p, l))) { locks[i] = Err(err(SplitMutError::SameValue)) }
+        }
+    }
+    let mut order: Vec<(usize, &RwLock<V>, LockMode)> = locks.iter().enumerate()
+        .filter_map(|(i, x)| x.as_ref().ok().map(|&(l, m)| (i, l, m))).collect();
+    order.sort_by_key(|&(_, l, _)| l as *const RwLock<V> as usize);
+    let mut guards: Vec<Option<Result<ManyGuard<'a, V>, SplitMutError>>> = locks.iter().map(|_| None).collect();
+    for (i, l, m) in order { guards[i] = Some(f(l, m)) }
+    locks.into_iter().zip(guards).map(|(l, g)| l.and_then(|_| g.unwrap())).collect()
+}
+
+impl<K, V, C: GetRef<K, RwLock<V>> + ?Sized> SplitMutLock<K, V> for C {
+    fn lock_many<I: IntoIterator<Item=(K, LockMode)>>(&self, keys: I) -> Vec<Result<ManyGuard<'_, V>, SplitMutError>> {
+        trace_method!("lock_many");
+        lock_all(self, keys, |l, m| Ok(match m {
+            LockMode::Read => ManyGuard::Read(l.read().unwrap_or_else(|e| e.into_inner())),
+            LockMode::Write => ManyGuard::Write(l.write().unwrap_or_else(|e| e.into_inner())),
+        }))
+    }
+
+    fn try_lock_many<I: IntoIterator<Item=(K, LockMode)>>(&self, keys: I) -> Vec<Result<ManyGuard<'_, V>, SplitMutError>> {
+        trace_method!("try_lock_many");
+        lock_all(self, keys, |l, m| match m {
+            LockMode::Read => taken(l.try_read()).map(ManyGuard::Read),
+            LockMode::Write => taken(l.try_write()).map(ManyGuard::Write),
+        })
+    }
+}
+
+#[test]
+fn lock_many_threads() {
+    let v: Vec<RwLock<u32>> = (0..4).map(RwLock::new).collect();
+    std::thread::scope(|s| {
+        for t in 0..8usize {
+            let v = &v;
+            s.spawn(move || for _ in 0..100 {
+                let (a, b) = (t % 4, (t + 1) % 4);
+                let mut g = v.lock_many(vec![(a, LockMode::Write), (b, LockMode::Write), (4, LockMode::Read)]);
+                assert_eq!(g[2].as_ref().err(), Some(&SplitMutError::NoValue));
+                *g[0].as_mut().unwrap().get_mut().unwrap() += 1;
+                *g[1].as_mut().unwrap().get_mut().unwrap() += 1;
+            });
+        }
+    });
+    assert_eq!(v.iter().map(|l| *l.read().unwrap()).sum::<u32>(), 6 + 1600);
+    let mut g = v.lock_many(vec![(0, LockMode::Read), (1, LockMode::Read)]);
+    assert!(g[0].as_mut().unwrap().get_mut().is_none());
+    assert_eq!(**g[1].as_ref().unwrap(), 401);
+}
+
+#[test]
+fn try_lock() {
+    let v: Vec<RwLock<u32>> = (0..3).map(RwLock::new).collect();
+    let (a, b) = v.lock2(0, LockMode::Read, 2, LockMode::Write);
+    let g = v.try_lock_many(vec![(0, LockMode::Read), (0, LockMode::Write), (1, LockMode::Write), (2, LockMode::Read), (3, LockMode::Read)]);
+    let e: Vec<_> = g.iter().map(|x| x.as_ref().err().copied()).collect();
+    assert_eq!(e, [None, Some(SplitMutError::SameValue), None, Some(SplitMutError::WouldBlock), Some(SplitMutError::NoValue)]);
+    drop(g);
+    assert_eq!(v.try_lock2(0, LockMode::Write, 1, LockMode::Write).0.err().unwrap(), SplitMutError::WouldBlock);
+    drop((a, b));
+    assert!(v.try_lock2(0, LockMode::Write, 2, LockMode::Write).1.is_ok());
+}
@@ -0,0 +1,295 @@
+// Enters a tracing span named after the calling method, so that events for
+// errors produced within it show which method was called.
+macro_rules! trace_method {
+    ($name: expr) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::trace_span!(target: "splitmut", $name).entered();
+    }
+}
+
+/// Returns a tuple of mutable references to several fields of the same struct.
+///
+/// The first argument is anything that can be mutably dereferenced to the struct,
+/// e g `&mut s`, a `Box` or a `MutexGuard`. It is dereferenced only once, so the
+/// fields can be borrowed simultaneously even when the struct sits behind a
+/// `DerefMut` smart pointer. Field paths that overlap (one being a prefix of
+/// another, or the same path twice) are rejected at compile time.
+///
+/// # Example
+/// ```
+/// #[macro_use] extern crate splitmut;
+///
+/// struct Inner { x: i32 }
+/// struct Outer { a: Vec<i32>, b: Inner, c: String }
+///
+/// # fn main() {
+/// let mut o = Box::new(Outer { a: vec![1], b: Inner { x: 2 }, c: String::new() });
+/// {
+///     let (a, x, c) = splitmut_fields!(o => a, b.x, c);
+///     a.push(*x);
+///     *x += 1;
+///     c.push('!');
+/// }
+/// assert_eq!(o.a, [1, 2]);
+/// assert_eq!(o.b.x, 3);
+/// assert_eq!(o.c, "!");
+/// # }
+/// ```
+///
+/// Overlapping paths do not compile:
+/// ```compile_fail
+/// #[macro_use] extern crate splitmut;
+///
+/// struct Inner { x: i32 }
+/// struct Outer { b: Inner }
+///
+/// # fn main() {
+/// let mut o = Outer { b: Inner { x: 2 } };
+/// let (b, x) = splitmut_fields!(&mut o => b, b.x);
+/// # let _ = (b, x);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! splitmut_fields {
+    ($v: expr => $($($f: ident).+),+ $(,)*) => {{
+        let v = &mut *$v;
+        ($(&mut v $(.$f)+,)+)
+    }};
+}
+
+/// Binds mutable references to several values of a collection to names, in one statement.
+///
+/// The keys are looked up with `get_tuple_mut`, so up to 12 keys are supported.
+/// The collection must be a single token tree, e g `map` or `(self.map)`.
+/// If any key gives an error, the `else` expression is evaluated instead; it must
+/// diverge, e g by `return`, `continue` or `panic!`.
+///
+/// This is also the way to port code that takes several `&mut v[i]` from the same
+/// collection, which the borrow checker rejects: bind the values with one `split!`
+/// at the top, and use the names instead of the index expressions. With the `checked`
+/// feature, the `checked` attribute does a similar rewrite for a whole function.
+///
+/// ```
+/// #[macro_use]
+/// extern crate splitmut;
+///
+/// // Instead of `std::mem::swap(&mut v[i], &mut v[j])`
+/// fn swap_items(v: &mut Vec<String>, i: usize, j: usize) {
+///     split!(let { a = i, b = j } in v else panic!("cannot swap item {} with item {}", i, j));
+///     std::mem::swap(a, b);
+/// }
+///
+/// # fn main() {
+/// let mut v = vec!["x".to_string(), "y".to_string()];
+/// swap_items(&mut v, 1, 0);
+/// assert_eq!(v, ["y", "x"]);
+/// # }
+/// ```
+///
+/// # Example
+/// ```
+/// #[macro_use]
+/// extern crate splitmut;
+/// use std::collections::HashMap;
+///
+/// fn transfer(m: &mut HashMap<&str, u32>, amount: u32) -> Result<(), String> {
+///     split!(let { from = &"alice", to = &"bob" } in m else return Err("no such account".into()));
+///     if *from < amount { return Err("not enough money".into()) }
+///     *from -= amount;
+///     *to += amount;
+///     Ok(())
+/// }
+///
+/// # fn main() {
+/// let mut m: HashMap<_, _> = vec![("alice", 10), ("bob", 0)].into_iter().collect();
+/// assert_eq!(transfer(&mut m, 4), Ok(()));
+/// assert_eq!(m["bob"], 4);
+/// m.remove("bob");
+/// assert!(transfer(&mut m, 4).is_err());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! split {
+    (let { $($name: ident = $k: expr),+ $(,)* } in $c: tt else $e: expr) => {
+        let ($($name,)+) = match { use $crate::SplitMut; $c.get_tuple_mut(($($k,)+)) } {
+            ($(Ok($name),)+) => ($($name,)+),
+            _ => $e,
+        };
+    };
+}
+
+/// Looks up any number of keys in a collection, and returns a tuple with a
+/// `Result<&mut V, SplitMutError>` for each one of them.
+///
+/// This works like `get2_mut`, `get3_mut` and so on, but for any number of keys:
+/// every value is checked against the ones before it, so no `HashSet` is allocated,
+/// as `get_muts` would do, but the time grows with the square of the number of keys.
+/// The collection is borrowed like for a method call, so both `v` and `&mut v` work.
+///
+/// # Example
+/// ```
+/// #[macro_use] extern crate splitmut;
+/// use splitmut::SplitMutError;
+///
+/// # fn main() {
+/// let mut v: Vec<u32> = (0..20).collect();
+/// let (a, b, c, d, e, f, g, h, i, j, k, l, m, n) = splitmut!(v; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 19, 19);
+/// *m.unwrap() += *a.unwrap() + *l.unwrap();
+/// assert_eq!(n, Err(SplitMutError::SameValue));
+/// # let _ = (b, c, d, e, f, g, h, i, j, k);
+/// assert_eq!(v[19], 30);
+/// # }
+/// ```
+///
+/// The values borrow the collection, so they cannot outlive it:
+///
+/// ```compile_fail
+/// #[macro_use] extern crate splitmut;
+///
+/// # fn main() {
+/// let a = { let mut v = vec![1, 2]; splitmut!(v; 0, 1).0 };
+/// # let _ = a;
+/// # }
+/// ```
+#[macro_export]
+macro_rules! splitmut {
+    ($c: expr; $($k: expr),+ $(,)*) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{Lookup, LookupSlice};
+        match $c.__splitmut_lookup() { mut c => {
+            let mut p = [$(c.find($k)),+];
+            $crate::__private::dedup(&mut p);
+            let mut p = p.iter();
+            ($({ let _ = stringify!($k); unsafe { c.get(*p.next().unwrap()) } },)+)
+        }}
+    }};
+}
+
+/// Support for the exported macros. Not public API.
+pub mod __private {
+    use core::marker::PhantomData;
+    use {SplitMutBase, SplitMutError, KeyError, to_r, from_r, dedup_r};
+
+    /// A collection borrowed by the splitmut! macro.
+    pub struct Borrowed<'a, K, V, H>(H, PhantomData<(&'a mut V, K)>);
+
+    impl<'a, K, V, H: Find<K, V>> Borrowed<'a, K, V, H> {
+        /// Looks up a key.
+        pub fn find(&mut self, k: K) -> Result<*mut V, SplitMutError> { self.0.find(k) }
+
+        /// Turns a lookup into a reference, borrowing the collection.
+        ///
+        /// # Safety
+        /// The pointer must come from `find` on this collection, and not be returned twice.
+        pub unsafe fn get(&self, p: Result<*mut V, SplitMutError>) -> Result<&'a mut V, SplitMutError> { from_r(p) }
+    }
+
+    /// Looks up keys in a borrowed collection.
+    pub trait Find<K, V> {
+        /// Looks up a key.
+        fn find(&mut self, k: K) -> Result<*mut V, SplitMutError>;
+    }
+
+    impl<K, V, A: SplitMutBase<K, V> + ?Sized> Find<K, V> for &mut A {
+        fn find(&mut self, k: K) -> Result<*mut V, SplitMutError> { to_r(self.get1_mut(k)) }
+    }
+
+    /// A borrowed slice, which unlike other collections implements `SplitMutBase`
+    /// for a reference to it.
+    pub struct Slice<'a, V: 'a>(&'a mut [V]);
+
+    impl<'a, V> Find<usize, V> for Slice<'a, V> {
+        fn find(&mut self, k: usize) -> Result<*mut V, SplitMutError> { to_r(self.0.get1_mut(k)) }
+    }
+
+    /// Replaces lookups resolving to a value found before them with `Err(SplitMutError::SameValue)`.
+    pub fn dedup<V>(p: &mut [Result<*mut V, SplitMutError>]) { dedup_r(p) }
+
+    /// The first failing key, for the `checked` attribute.
+    pub fn key_error(e: &[Option<SplitMutError>]) -> KeyError { KeyError::first(e) }
+
+    /// Borrows a collection like a method call does, with auto-referencing.
+    pub trait Lookup<K, V> {
+        /// Borrows the collection.
+        fn __splitmut_lookup(&mut self) -> Borrowed<'_, K, V, &mut Self>;
+    }
+
+    impl<K, V, A: SplitMutBase<K, V> + ?Sized> Lookup<K, V> for A {
+        fn __splitmut_lookup(&mut self) -> Borrowed<'_, K, V, &mut Self> { Borrowed(self, PhantomData) }
+    }
+
+    /// Reborrows a mutable slice reference, so that it works without being declared `mut`,
+    /// like `&mut v[i]` does.
+    pub trait LookupSlice<'a, V> {
+        /// Borrows the slice.
+        fn __splitmut_lookup(self) -> Borrowed<'a, usize, V, Slice<'a, V>>;
+    }
+
+    impl<'a, V> LookupSlice<'a, V> for &'a mut [V] {
+        fn __splitmut_lookup(self) -> Borrowed<'a, usize, V, Slice<'a, V>> { Borrowed(Slice(self), PhantomData) }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn splitmut_any_arity() {
+    use std::collections::HashMap;
+    use SplitMutError;
+    fn f(h: &mut HashMap<u8, u8>) -> (Result<&mut u8, SplitMutError>, Result<&mut u8, SplitMutError>, Result<&mut u8, SplitMutError>) {
+        splitmut!(h; &1, &0, &1)
+    }
+    let mut h: HashMap<u8, u8> = (0..3).map(|i| (i, i)).collect();
+    assert_eq!(f(&mut h), (Ok(&mut 1), Ok(&mut 0), Err(SplitMutError::SameValue)));
+    assert_eq!(splitmut!(h; &5,), (Err(SplitMutError::NoValue),));
+    let mut z = [(); 3];
+    assert_eq!(splitmut!(&mut z[..]; 0, 0), (Ok(&mut ()), Err(SplitMutError::SameValue)));
+}
+
+#[cfg(feature = "checked")]
+#[test]
+fn checked_rewrites_statements() {
+    use alloc::vec::Vec;
+    use {checked, SplitMutError};
+    struct S { v: Vec<u32>, w: Vec<u32> }
+    fn add(a: &mut u32, b: &u32) { *a += *b }
+
+    impl S {
+        #[checked(result)]
+        fn mix(&mut self, i: usize, j: usize) -> Result<u32, SplitMutError> {
+            // Only one `&mut self.v[..]` here, so this is left as it is.
+            add(&mut self.v[i], &1);
+            let (a, b) = (&mut self.v[i], &mut self.v[j]);
+            if *b > 0 {
+                core::mem::swap(&mut self.w[i], &mut self.w[j]);
+            }
+            Ok(*a + *b)
+        }
+    }
+
+    #[checked]
+    fn rotate(v: &mut [u8]) {
+        core::mem::swap(&mut v[0], &mut v[1]);
+        core::mem::swap(&mut v[1], &mut v[2]);
+    }
+
+    let mut s = S { v: vec![1, 2, 3], w: vec![4, 5, 6] };
+    assert_eq!(s.mix(0, 2), Ok(5));
+    assert_eq!((&*s.v, &*s.w), (&[2, 2, 3][..], &[6, 5, 4][..]));
+    assert_eq!(s.mix(1, 1), Err(SplitMutError::SameValue));
+    assert_eq!(s.mix(1, 3), Err(SplitMutError::NoValue));
+    let mut v = [1, 2, 3];
+    rotate(&mut v);
+    assert_eq!(v, [2, 3, 1]);
+}
+
+#[cfg(feature = "checked")]
+#[test]
+#[should_panic(expected = "`v[j]`: Duplicate values")]
+fn checked_panics() {
+    use alloc::vec::Vec;
+    use checked;
+    #[checked]
+    fn add(v: &mut Vec<u32>, i: usize, j: usize) { add_to(&mut v[i], &mut v[j]) }
+    fn add_to(a: &mut u32, b: &mut u32) { *a += *b }
+    add(&mut vec![1, 2], 1, 1);
+}
@@ -0,0 +1,312 @@
+//! Helpers that add or remove entries, as companions to split sessions.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash;
+#[cfg(feature = "std")]
+use std::borrow::ToOwned;
+use core::borrow;
+use {SplitMut, SplitMutError, err};
+
+/// Just add `use splitmut::SplitMutRetain;` to have this method working on
+/// Vec, VecDeque, HashMap and BTreeMap.
+pub trait SplitMutRetain<P, K, V> {
+    /// Like `retain`, but the predicate also gets mutable access to the value of `pivot`.
+    ///
+    /// The predicate is called once for every entry except the pivot, with its
+    /// key (the original index, for sequences), its value, and the pivot's value.
+    /// The pivot itself is always retained. Returns `Err(SplitMutError::NoValue)`,
+    /// without calling the predicate, if there is no value for `pivot`.
+    ///
+    /// For maps, the pivot entry is temporarily removed and then inserted again.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMutRetain;
+    ///
+    /// // Remove everything larger than the pivot, and count what was removed.
+    /// let mut v = vec![(5, 0), (1, 0), (9, 0), (3, 0), (7, 0)];
+    /// v.retain_with(3, |_, x, p| { let keep = x.0 <= p.0; if !keep { p.1 += 1 }; keep }).unwrap();
+    /// assert_eq!(v, [(1, 0), (3, 3)]);
+    /// ```
+    fn retain_with<F: FnMut(&K, &mut V, &mut V) -> bool>(&mut self, pivot: P, f: F) -> Result<(), SplitMutError>;
+}
+
+macro_rules! retain_seq {
+    ($t: ident) => {
+        impl<V> SplitMutRetain<usize, usize, V> for $t<V> {
+            fn retain_with<F: FnMut(&usize, &mut V, &mut V) -> bool>(&mut self, pivot: usize, mut f: F) -> Result<(), SplitMutError> {
+                trace_method!("retain_with");
+                if pivot >= self.len() { return Err(err(SplitMutError::NoValue)) };
+                let (mut p, mut w) = (pivot, 0);
+                for r in 0..self.len() {
+                    let keep = r == pivot || {
+                        let (v, pv) = self.get2_mut(r, p);
+                        f(&r, v.unwrap(), pv.unwrap())
+                    };
+                    if keep {
+                        self.swap(w, r);
+                        if r == pivot { p = w };
+                        w += 1;
+                    }
+                }
+                self.truncate(w);
+                Ok(())
+            }
+        }
+    }
+}
+
+retain_seq!(Vec);
+retain_seq!(VecDeque);
+
+#[cfg(feature = "std")]
+impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized, V, S: hash::BuildHasher> SplitMutRetain<&'a Q, K, V> for HashMap<K, V, S> {
+    fn retain_with<F: FnMut(&K, &mut V, &mut V) -> bool>(&mut self, pivot: &'a Q, mut f: F) -> Result<(), SplitMutError> {
+        trace_method!("retain_with");
+        let (pk, mut pv) = self.remove_entry(pivot).ok_or_else(|| err(SplitMutError::NoValue))?;
+        self.retain(|k, v| f(k, v, &mut pv));
+        self.insert(pk, pv);
+        Ok(())
+    }
+}
+
+impl<'a, K: Ord + borrow::Borrow<Q>, Q: Ord + ?Sized, V> SplitMutRetain<&'a Q, K, V> for BTreeMap<K, V> {
+    fn retain_with<F: FnMut(&K, &mut V, &mut V) -> bool>(&mut self, pivot: &'a Q, mut f: F) -> Result<(), SplitMutError> {
+        trace_method!("retain_with");
+        let (pk, mut pv) = self.remove_entry(pivot).ok_or_else(|| err(SplitMutError::NoValue))?;
+        self.retain(|k, v| f(k, v, &mut pv));
+        self.insert(pk, pv);
+        Ok(())
+    }
+}
+
+/// Just add `use splitmut::SplitMutRemove;` to have this method working on
+/// Vec, VecDeque, HashMap and BTreeMap.
+pub trait SplitMutRemove<K, V> {
+    /// Removes the values for all keys, and returns them in the same order as the keys.
+    ///
+    /// A key without a value, or a key for a value that was already removed earlier
+    /// in the same call, gives `None`. For sequences, the keys are indices into the
+    /// sequence as it was before the call; the remaining values keep their order.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMutRemove;
+    ///
+    /// let mut v = vec!['a', 'b', 'c', 'd'];
+    /// assert_eq!(v.remove_many(vec![3, 1, 3, 7]), [Some('d'), Some('b'), None, None]);
+    /// assert_eq!(v, ['a', 'c']);
+    /// ```
+    fn remove_many<I: IntoIterator<Item=K>>(&mut self, keys: I) -> Vec<Option<V>>;
+
+    /// Consuming version of `remove_many`, which returns the removed values
+    /// together with the rest of the collection.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::SplitMutRemove;
+    ///
+    /// let h: HashMap<_, _> = vec![("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    /// let (taken, rest) = h.partition_owned(vec![&"c", &"a", &"x"]);
+    /// assert_eq!(taken, [Some(3), Some(1), None]);
+    /// assert_eq!(rest.into_iter().collect::<Vec<_>>(), [("b", 2)]);
+    /// ```
+    fn partition_owned<I: IntoIterator<Item=K>>(mut self, keys: I) -> (Vec<Option<V>>, Self) where Self: Sized {
+        trace_method!("partition_owned");
+        let r = self.remove_many(keys);
+        (r, self)
+    }
+}
+
+macro_rules! remove_seq {
+    ($t: ident) => {
+        impl<V> SplitMutRemove<usize, V> for $t<V> {
+            fn remove_many<I: IntoIterator<Item=usize>>(&mut self, keys: I) -> Vec<Option<V>> {
+                let mut all: Vec<Option<V>> = self.drain(..).map(Some).collect();
+                let r = keys.into_iter().map(|k| all.get_mut(k).and_then(|v| v.take())).collect();
+                self.extend(all.into_iter().flatten());
+                r
+            }
+        }
+    }
+}
+
+remove_seq!(Vec);
+remove_seq!(VecDeque);
+
+#[cfg(feature = "std")]
+impl<'a, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ?Sized + 'a, V, S: hash::BuildHasher> SplitMutRemove<&'a Q, V> for HashMap<K, V, S> {
+    fn remove_many<I: IntoIterator<Item=&'a Q>>(&mut self, keys: I) -> Vec<Option<V>> {
+        keys.into_iter().map(|k| self.remove(k)).collect()
+    }
+}
+
+impl<'a, K: Ord + borrow::Borrow<Q>, Q: Ord + ?Sized + 'a, V> SplitMutRemove<&'a Q, V> for BTreeMap<K, V> {
+    fn remove_many<I: IntoIterator<Item=&'a Q>>(&mut self, keys: I) -> Vec<Option<V>> {
+        keys.into_iter().map(|k| self.remove(k)).collect()
+    }
+}
+
+/// Just add `use splitmut::SplitMutInsert;` to have this method working on
+/// HashMap and BTreeMap.
+#[cfg(feature = "std")]
+pub trait SplitMutInsert<K, V> {
+    /// Inserts all entries, then returns mutable references to all inserted values,
+    /// in the same order as the entries.
+    ///
+    /// If the same key occurs more than once, the last value is kept, the first
+    /// occurrence returns a reference to it and the others return
+    /// `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::SplitMutInsert;
+    ///
+    /// let mut h = HashMap::new();
+    /// h.insert("a", 0);
+    /// {
+    ///     let r = h.insert_many_then_split(vec![("b", 1), ("c", 2)]);
+    ///     let mut r = r.into_iter().map(Result::unwrap);
+    ///     let (b, c) = (r.next().unwrap(), r.next().unwrap());
+    ///     std::mem::swap(b, c);
+    /// }
+    /// assert_eq!((h["a"], h["b"], h["c"]), (0, 2, 1));
+    /// ```
+    fn insert_many_then_split<I: IntoIterator<Item=(K, V)>>(&mut self, entries: I) -> Vec<Result<&mut V, SplitMutError>>;
+}
+
+#[cfg(feature = "std")]
+impl<K: hash::Hash + Eq + Clone, V, S: hash::BuildHasher> SplitMutInsert<K, V> for HashMap<K, V, S> {
+    fn insert_many_then_split<I: IntoIterator<Item=(K, V)>>(&mut self, entries: I) -> Vec<Result<&mut V, SplitMutError>> {
+        trace_method!("insert_many_then_split");
+        let entries = entries.into_iter();
+        self.reserve(entries.size_hint().0);
+        let keys: Vec<K> = entries.map(|(k, v)| { self.insert(k.clone(), v); k }).collect();
+        self.get_mut_iter(keys.iter()).collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord + Clone, V> SplitMutInsert<K, V> for BTreeMap<K, V> {
+    fn insert_many_then_split<I: IntoIterator<Item=(K, V)>>(&mut self, entries: I) -> Vec<Result<&mut V, SplitMutError>> {
+        trace_method!("insert_many_then_split");
+        let keys: Vec<K> = entries.into_iter().map(|(k, v)| { self.insert(k.clone(), v); k }).collect();
+        self.get_mut_iter(keys.iter()).collect()
+    }
+}
+
+/// Just add `use splitmut::SplitMutGetOrInsert;` to have these methods working on
+/// HashMap and BTreeMap.
+///
+/// Keys are passed by reference, and only turned into owned keys (with `ToOwned`)
+/// for the entries that need to be inserted.
+#[cfg(feature = "std")]
+pub trait SplitMutGetOrInsert<Q: ?Sized, V> {
+    /// Returns mutable references to the values of two keys, first inserting the
+    /// value `f` returns for a key that has none.
+    ///
+    /// If both keys are the same, the second one is `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use splitmut::SplitMutGetOrInsert;
+    ///
+    /// let mut h: HashMap<String, Vec<&str>> = HashMap::new();
+    /// h.insert("a".into(), vec!["x"]);
+    /// {
+    ///     let (a, b) = h.get2_or_insert_with("a", "b", |_| vec![]);
+    ///     b.unwrap().append(a.unwrap());
+    /// }
+    /// assert_eq!(h["b"], ["x"]);
+    /// assert!(h["a"].is_empty());
+    /// ```
+    fn get2_or_insert_with<F: FnMut(&Q) -> V>(&mut self, k1: &Q, k2: &Q, f: F) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>);
+
+    /// Like `get2_or_insert_with`, but for any number of keys. A key that occurs more
+    /// than once gives `Err(SplitMutError::SameValue)` after the first time.
+    fn get_many_or_insert_with<'k, I: IntoIterator<Item=&'k Q>, F: FnMut(&Q) -> V>(&mut self, keys: I, f: F) -> Vec<Result<&mut V, SplitMutError>> where Q: 'k;
+}
+
+macro_rules! get_or_insert {
+    ($t: ty, $($bound: tt)*) => {
+        #[cfg(feature = "std")]
+        impl<$($bound)*> SplitMutGetOrInsert<Q, V> for $t {
+            fn get2_or_insert_with<F: FnMut(&Q) -> V>(&mut self, k1: &Q, k2: &Q, mut f: F) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+                trace_method!("get2_or_insert_with");
+                for k in [k1, k2] {
+                    if !self.contains_key(k) { let v = f(k); self.insert(k.to_owned(), v); }
+                }
+                self.get2_mut(k1, k2)
+            }
+
+            fn get_many_or_insert_with<'k, I: IntoIterator<Item=&'k Q>, F: FnMut(&Q) -> V>(&mut self, keys: I, mut f: F) -> Vec<Result<&mut V, SplitMutError>> where Q: 'k {
+                trace_method!("get_many_or_insert_with");
+                let keys: Vec<&Q> = keys.into_iter().collect();
+                for &k in &keys {
+                    if !self.contains_key(k) { let v = f(k); self.insert(k.to_owned(), v); }
+                }
+                self.get_mut_iter(keys).collect()
+            }
+        }
+    }
+}
+
+get_or_insert!(HashMap<K, V, S>, K: hash::Hash + Eq + borrow::Borrow<Q>, Q: hash::Hash + Eq + ToOwned<Owned=K> + ?Sized, V, S: hash::BuildHasher);
+get_or_insert!(BTreeMap<K, V>, K: Ord + borrow::Borrow<Q>, Q: Ord + ToOwned<Owned=K> + ?Sized, V);
+
+#[test]
+fn retain_pivot() {
+    let mut d: VecDeque<u8> = (0..10).collect();
+    let mut seen = vec![];
+    d.retain_with(4, |&i, v, p| { seen.push(i); *p += 1; *v % 2 == 0 }).unwrap();
+    assert_eq!(d, [0, 2, 13, 6, 8]);
+    assert_eq!(seen, [0, 1, 2, 3, 5, 6, 7, 8, 9]);
+    assert_eq!(d.retain_with(5, |_, _, _| true), Err(SplitMutError::NoValue));
+
+    let mut h: BTreeMap<u8, u8> = (0..10).map(|i| (i, i)).collect();
+    h.retain_with(&5, |_, v, p| { if *v > *p { *p += 1; false } else { true } }).unwrap();
+    assert_eq!(h.into_iter().collect::<Vec<_>>(), [(0, 0), (1, 1), (2, 2), (3, 3), (4, 4), (5, 9)]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn remove_many() {
+    use alloc::string::{String, ToString};
+    let mut d: VecDeque<u8> = (0..6).collect();
+    assert_eq!(d.remove_many(vec![0, 5, 0, 6]), [Some(0), Some(5), None, None]);
+    assert_eq!(d, [1, 2, 3, 4]);
+    let mut h: HashMap<String, u8> = (0..4).map(|i| (i.to_string(), i)).collect();
+    assert_eq!(h.remove_many(vec!["1", "x", "1", "3"]), [Some(1), None, None, Some(3)]);
+    assert_eq!(h.len(), 2);
+    let (taken, rest) = vec!['a', 'b', 'c'].partition_owned(vec![2, 0]);
+    assert_eq!((taken, rest), (vec![Some('c'), Some('a')], vec!['b']));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn insert_many() {
+    let mut h = BTreeMap::new();
+    let r = h.insert_many_then_split(vec![(1, 'a'), (2, 'b'), (1, 'c')]);
+    assert_eq!(r, [Ok(&mut 'c'), Ok(&mut 'b'), Err(SplitMutError::SameValue)]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn get_or_insert() {
+    use alloc::string::String;
+    let mut h: BTreeMap<String, u32> = BTreeMap::new();
+    let mut made = vec![];
+    let r = h.get_many_or_insert_with(vec!["b", "a", "b"], |k| { made.push(k.to_owned()); 0 });
+    assert_eq!(r, [Ok(&mut 0), Ok(&mut 0), Err(SplitMutError::SameValue)]);
+    assert_eq!(made, ["b", "a"]);
+    *h.get_mut("a").unwrap() = 5;
+    assert_eq!(h.get2_or_insert_with("a", "a", |_| 1), (Ok(&mut 5), Err(SplitMutError::SameValue)));
+    assert_eq!(h.len(), 2);
+}
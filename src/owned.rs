@@ -0,0 +1,97 @@
+//! A split session that owns its collection.
+
+use std::cell::{RefCell, UnsafeCell};
+use std::marker::PhantomData;
+use ptrset::Tracker;
+use {SharedLookup, SplitMutError, to_r, err};
+
+/// Owns a collection together with a split session on it, so both can be stored
+/// in the same struct field.
+///
+/// Works like `GetMuts`, but `at` takes `&self`, and the returned references live
+/// as long as that borrow. Taking `&mut self` ends the session: `get_mut` and
+/// `reset` forget which values have been returned.
+///
+/// # Example
+/// ```
+/// use splitmut::{OwnedGetMuts, SplitMutError};
+///
+/// struct World { cells: OwnedGetMuts<Vec<u32>, usize, u32> }
+///
+/// let w = World { cells: OwnedGetMuts::new(vec![1, 2, 3]) };
+/// let a = w.cells.at(0).unwrap();
+/// let c = w.cells.at(2).unwrap();
+/// *a += *c;
+/// assert_eq!(w.cells.at(2), Err(SplitMutError::SameValue));
+/// assert_eq!(w.cells.into_inner(), [4, 2, 3]);
+/// ```
+///
+/// Since values are looked up while others are in use, the collection must implement
+/// `SharedLookup`. Collections that store their values inline are rejected:
+///
+/// ```compile_fail
+/// use splitmut::{OwnedGetMuts, IndexSplit};
+///
+/// let a = unsafe { IndexSplit::new([1, 2, 3], |_: &[u32; 3], &k: &usize| k < 3) };
+/// let s = OwnedGetMuts::new(a);
+/// let _ = s.at(0);
+/// ```
+pub struct OwnedGetMuts<C, K, V> {
+    c: UnsafeCell<C>,
+    claimed: RefCell<Tracker>,
+    _kv: PhantomData<fn(K) -> V>,
+}
+
+impl<C: SharedLookup<K, V>, K, V> OwnedGetMuts<C, K, V> {
+    /// Wraps a collection and starts a session on it.
+    pub fn new(c: C) -> Self {
+        let t = Tracker::new::<V>(c.value_range());
+        OwnedGetMuts { c: UnsafeCell::new(c), claimed: RefCell::new(t), _kv: PhantomData }
+    }
+
+    /// Returns a mutable reference that lives as long as the borrow of the session,
+    /// or an error if the value does not exist or has already been returned.
+    #[allow(clippy::mut_from_ref)]
+    pub fn at(&self, k: K) -> Result<&mut V, SplitMutError> {
+        trace_method!("at");
+        // Only values are handed out, never the collection, values do not move while
+        // the session is borrowed, and SharedLookup promises that the `&mut C` covers none of them.
+        let p = to_r(unsafe { &mut *self.c.get() }.get1_mut(k))?;
+        if !self.claimed.borrow_mut().insert(p as usize) { return Err(err(SplitMutError::SameValue)) };
+        Ok(unsafe { &mut *p })
+    }
+
+    /// Forgets which values have been returned, so all of them can be returned again.
+    pub fn reset(&mut self) {
+        let c = self.c.get_mut();
+        *self.claimed.get_mut() = Tracker::new::<V>(c.value_range());
+    }
+
+    /// Returns a mutable reference to the collection, and forgets which values have
+    /// been returned, since the collection may change.
+    pub fn get_mut(&mut self) -> &mut C {
+        self.reset();
+        self.c.get_mut()
+    }
+
+    /// Ends the session and returns the collection.
+    pub fn into_inner(self) -> C { self.c.into_inner() }
+}
+
+#[test]
+fn owned_session() {
+    use std::collections::HashMap;
+    let mut s: OwnedGetMuts<HashMap<&str, i32>, &&str, i32> = OwnedGetMuts::new(vec![("a", 1), ("b", 2)].into_iter().collect());
+    {
+        let (a, b) = (s.at(&"a").unwrap(), s.at(&"b").unwrap());
+        std::mem::swap(a, b);
+        assert_eq!(s.at(&"a"), Err(SplitMutError::SameValue));
+        assert_eq!(s.at(&"c"), Err(SplitMutError::NoValue));
+    }
+    s.get_mut().insert("c", 3);
+    assert_eq!(s.at(&"a"), Ok(&mut 2));
+    assert_eq!(s.at(&"c"), Ok(&mut 3));
+    s.reset();
+    assert_eq!(s.at(&"a"), Ok(&mut 2));
+    assert_eq!(s.into_inner()["b"], 1);
+}
@@ -0,0 +1,99 @@
+//! Resolving the same keys once, for repeated splits of a collection that rarely changes.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use {SplitMut, SplitMutError, R, to_r, from_r, dedup_r};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Owns a collection and counts the times it was structurally changed, for use with `AccessPlan`.
+///
+/// Values can be changed through an `AccessPlan`, while inserting, removing or anything
+/// else that might move values has to go through `modify`, which starts a new generation.
+#[derive(Debug)]
+pub struct Tracked<C> { c: C, id: usize, generation: usize }
+
+impl<C> Tracked<C> {
+    /// Wraps a collection.
+    pub fn new(c: C) -> Self { Tracked { c, id: NEXT_ID.fetch_add(1, Ordering::Relaxed), generation: 0 } }
+
+    /// Returns a shared reference to the collection.
+    ///
+    /// This does not start a new generation, which is sound since `SplitMutBase`
+    /// does not allow values to move through a shared reference.
+    pub fn get(&self) -> &C { &self.c }
+
+    /// Returns a mutable reference to the collection, and makes all plans resolve
+    /// their keys again the next time they are used.
+    pub fn modify(&mut self) -> &mut C { self.generation += 1; &mut self.c }
+
+    /// Returns the wrapped collection.
+    pub fn into_inner(self) -> C { self.c }
+}
+
+/// A fixed set of keys, together with the values they resolved to last time.
+///
+/// As long as the `Tracked` collection is the same, has not moved and has not been
+/// modified since, `get_mut` hands out the same values again without any lookups.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use splitmut::{AccessPlan, Tracked, SplitMutError};
+///
+/// let mut t = Tracked::new(HashMap::new());
+/// t.modify().insert("pos", 0);
+/// t.modify().insert("vel", 3);
+/// let mut plan = AccessPlan::new([&"pos", &"vel", &"acc"]);
+/// for _ in 0..10 {
+///     let [pos, vel, acc] = plan.get_mut(&mut t);
+///     assert_eq!(acc, Err(SplitMutError::NoValue));
+///     *pos.unwrap() += *vel.unwrap();
+/// }
+/// assert_eq!(t.get()["pos"], 30);
+/// ```
+#[derive(Debug)]
+pub struct AccessPlan<K, V, const N: usize> {
+    keys: [K; N],
+    resolved: Option<([R<V>; N], usize, usize, *const ())>,
+}
+
+impl<K: Clone, V, const N: usize> AccessPlan<K, V, N> {
+    /// Creates a plan for the keys. Nothing is looked up until the plan is used.
+    pub fn new(keys: [K; N]) -> Self { AccessPlan { keys, resolved: None } }
+
+    /// The keys of this plan.
+    pub fn keys(&self) -> &[K; N] { &self.keys }
+
+    /// Returns the values for the keys, in the same way as `get_tuple_mut` does.
+    /// The keys are only looked up the first time, and after `t` was modified or moved.
+    pub fn get_mut<'a, C: SplitMut<K, V>>(&mut self, t: &'a mut Tracked<C>) -> [Result<&'a mut V, SplitMutError>; N] {
+        trace_method!("get_mut");
+        let addr = t as *const Tracked<C> as *const ();
+        let p = match self.resolved {
+            Some((p, id, generation, a)) if id == t.id && generation == t.generation && a == addr => p,
+            _ => {
+                let mut p: [R<V>; N] = core::array::from_fn(|i| to_r(t.c.get1_mut(self.keys[i].clone())));
+                dedup_r(&mut p);
+                self.resolved = Some((p, t.id, t.generation, addr));
+                p
+            }
+        };
+        p.map(|r| unsafe { from_r(r) })
+    }
+}
+
+#[test]
+fn access_plan() {
+    let mut t = Tracked::new(vec![1, 2, 3]);
+    let mut plan = AccessPlan::new([2, 0, 2]);
+    let [a, b, c] = plan.get_mut(&mut t);
+    assert_eq!((a, b, c), (Ok(&mut 3), Ok(&mut 1), Err(SplitMutError::SameValue)));
+    let [a, _, _] = plan.get_mut(&mut t);
+    *a.unwrap() += 10;
+    // Moving to a new allocation must be noticed
+    t.modify().reserve(1000);
+    assert_eq!(plan.get_mut(&mut t)[0], Ok(&mut 13));
+    let mut other = Tracked::new(vec![0]);
+    assert_eq!(plan.get_mut(&mut other)[1], Ok(&mut 0));
+    assert_eq!(plan.get_mut(&mut other)[0], Err(SplitMutError::NoValue));
+}
@@ -0,0 +1,26 @@
+//! Everything needed to use the crate's methods, in one import.
+//!
+//! ```
+//! use splitmut::prelude::*;
+//!
+//! let mut v = vec![1, 2, 3];
+//! split!(let { a = 0, b = 2 } in v else unreachable!());
+//! std::mem::swap(a, b);
+//! assert_eq!(v, [3, 2, 1]);
+//! assert_eq!(v.get2_mut(1, 1).1, Err(SplitMutError::SameValue));
+//! ```
+
+pub use {SplitMut, SplitMutBase, SplitMutError, SplitMutResult, GetRef, SplitMutSlice, SplitMutBTree, SplitMutAny, SplitMutRc, SplitMutArc, SplitMutRetain, SplitMutRemove, SplitMutLend};
+#[cfg(feature = "std")]
+pub use {SplitMutInsert, SplitMutGetOrInsert, SplitMutLock};
+#[cfg(feature = "qcell")]
+pub use SplitCell;
+#[cfg(feature = "qp-trie")]
+pub use SplitMutTrie;
+#[cfg(feature = "rand")]
+pub use SplitMutSample;
+#[cfg(feature = "regex")]
+pub use SplitMutMatching;
+#[cfg(feature = "roaring")]
+pub use SplitMutBitmap;
+pub use {split, splitmut_fields};
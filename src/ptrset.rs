@@ -0,0 +1,150 @@
+//! Keeping track of the addresses of values already handed out.
+
+use std::collections::HashSet;
+use std::mem;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::vec::Vec;
+
+/// Hashes addresses by a single multiplication, instead of SipHash, which is
+/// not needed since the addresses are not chosen by an attacker.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct PtrHasher(u64);
+
+impl Hasher for PtrHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes { self.0 = (self.0.rotate_left(8) ^ u64::from(b)).wrapping_mul(K) }
+    }
+    #[inline]
+    fn write_usize(&mut self, i: usize) { self.0 = (self.0 ^ i as u64).wrapping_mul(K) }
+    // Addresses are aligned, so the low bits of the product are mostly zero; move the
+    // well-mixed high bits down, where the table takes its bucket index from.
+    #[inline]
+    fn finish(&self) -> u64 { self.0.rotate_left(26) }
+}
+
+const K: u64 = 0xf135_7aea_2e62_a9c5;
+
+pub(crate) type PtrSet<T> = HashSet<T, BuildHasherDefault<PtrHasher>>;
+
+/// The values handed out by a `GetMuts`.
+///
+/// Starts out as a `PtrSet`. If the collection keeps its values in a single
+/// allocation, the tracker switches to one bit per value once that needs less
+/// memory than the set.
+pub(crate) struct Tracker {
+    set: PtrSet<usize>,
+    // Start address, value size and number of values of the allocation
+    range: Option<(usize, usize, usize)>,
+    bits: Vec<u64>,
+}
+
+impl Tracker {
+    pub(crate) fn new<V>(range: Option<(usize, usize)>) -> Self {
+        let size = mem::size_of::<V>();
+        let range = range.and_then(|(lo, hi)| if size == 0 { None } else { Some((lo, size, (hi - lo) / size)) });
+        Tracker { set: PtrSet::default(), range, bits: Vec::new() }
+    }
+
+    // Addresses outside the allocation, or not at the start of a value, are kept in the set.
+    fn bit(&self, p: usize) -> Option<usize> {
+        let (lo, size, n) = self.range?;
+        let off = p.checked_sub(lo)?;
+        if off % size != 0 { return None }
+        let i = off / size;
+        if i < n { Some(i) } else { None }
+    }
+
+    /// Returns false if the address was already inserted.
+    pub(crate) fn insert(&mut self, p: usize) -> bool {
+        if !self.bits.is_empty() {
+            if let Some(i) = self.bit(p) {
+                let (w, m) = (i / 64, 1u64 << (i % 64));
+                if self.bits[w] & m != 0 { return false }
+                self.bits[w] |= m;
+                return true;
+            }
+        }
+        if !self.set.insert(p) { return false }
+        if let Some((_, _, n)) = self.range {
+            if self.bits.is_empty() && self.set.len() > n / 64 { self.switch_to_bits(n) }
+        }
+        true
+    }
+
+    /// Returns true if the address was inserted.
+    pub(crate) fn contains(&self, p: usize) -> bool {
+        if !self.bits.is_empty() {
+            if let Some(i) = self.bit(p) { return self.bits[i / 64] & (1u64 << (i % 64)) != 0 }
+        }
+        self.set.contains(&p)
+    }
+
+    /// Prepares for at least `n` more insertions.
+    pub(crate) fn reserve(&mut self, n: usize) {
+        match self.range {
+            Some((_, _, count)) if self.bits.is_empty() && self.set.len() + n > count / 64 => self.switch_to_bits(count),
+            Some(_) if !self.bits.is_empty() => {},
+            _ => self.set.reserve(n),
+        }
+    }
+
+    fn switch_to_bits(&mut self, n: usize) {
+        self.bits = vec![0; n / 64 + 1];
+        let set = mem::take(&mut self.set);
+        for p in set { self.insert(p); }
+    }
+}
+
+#[test]
+fn ptr_set() {
+    let v = vec![0u64; 1000];
+    let mut s = PtrSet::default();
+    for x in &v { assert!(s.insert(x as *const u64)) }
+    for x in &v { assert!(!s.insert(x as *const u64)) }
+    assert_eq!(s.len(), 1000);
+}
+
+#[test]
+fn tracker_switch() {
+    let v = vec![0u32; 200];
+    let r = v.as_ptr_range();
+    let mut t = Tracker::new::<u32>(Some((r.start as usize, r.end as usize)));
+    for x in &v[..10] { assert!(t.insert(x as *const u32 as usize)) }
+    assert!(!t.bits.is_empty());
+    assert!(t.set.is_empty());
+    for x in &v[..10] { assert!(!t.insert(x as *const u32 as usize)) }
+    assert!(t.contains(&v[9] as *const u32 as usize));
+    assert!(!t.contains(&v[10] as *const u32 as usize));
+    assert!(t.insert(r.end as usize));
+    assert!(!t.insert(r.end as usize));
+}
+
+#[test]
+fn tracker_unaligned() {
+    let v = vec![0u32; 200];
+    let lo = v.as_ptr() as usize;
+    let mut t = Tracker::new::<u32>(Some((lo, lo + 800)));
+    t.reserve(100);
+    assert!(!t.bits.is_empty());
+    assert!(t.insert(lo + 4));
+    assert!(t.insert(lo + 5));
+    assert!(t.insert(lo + 6));
+    assert!(!t.insert(lo + 5));
+    assert!(t.contains(lo + 6));
+    assert!(!t.contains(lo + 7));
+}
+
+#[test]
+fn tracker_reserve() {
+    let mut t = Tracker::new::<u8>(None);
+    t.reserve(100);
+    assert!(t.set.capacity() >= 100);
+    let v = [0u8; 640];
+    let r = v.as_ptr_range();
+    let mut t = Tracker::new::<u8>(Some((r.start as usize, r.end as usize)));
+    t.reserve(5);
+    assert!(t.bits.is_empty());
+    t.reserve(11);
+    assert_eq!(t.bits.len(), 11);
+}
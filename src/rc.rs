@@ -0,0 +1,135 @@
+//! Splitting collections of reference counted values.
+
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use {SplitMut, SplitMutError, err};
+
+#[inline]
+fn unique<V>(r: Result<&mut Rc<V>, SplitMutError>) -> Result<&mut V, SplitMutError> {
+    Rc::get_mut(r?).ok_or_else(|| err(SplitMutError::Shared))
+}
+
+/// Just add `use splitmut::SplitMutRc;` to have these methods working on
+/// all `SplitMut` collections with `Rc` values.
+///
+/// The values are reached through `Rc::get_mut`, so a value that has other `Rc`
+/// or `Weak` pointers to it gives `Err(SplitMutError::Shared)`.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use std::rc::Rc;
+/// use splitmut::{SplitMutRc, SplitMutError};
+///
+/// let mut h: HashMap<_, _> = vec![("a", Rc::new(1)), ("b", Rc::new(2))].into_iter().collect();
+/// let b2 = h["b"].clone();
+/// let (a, b) = h.get2_rc_mut(&"a", &"b");
+/// *a.unwrap() += 10;
+/// assert_eq!(b, Err(SplitMutError::Shared));
+/// drop(b2);
+/// assert_eq!(h.get2_rc_mut(&"b", &"a"), (Ok(&mut 2), Ok(&mut 11)));
+/// ```
+pub trait SplitMutRc<K, V> {
+    /// Returns two mutable references to two distinct values within
+    /// the same collection, inside their `Rc`s.
+    fn get2_rc_mut(&mut self, k1: K, k2: K) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>);
+
+    /// Returns three mutable references to three distinct values within
+    /// the same collection, inside their `Rc`s.
+    fn get3_rc_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>);
+}
+
+impl<K, V, S: SplitMut<K, Rc<V>> + ?Sized> SplitMutRc<K, V> for S {
+    fn get2_rc_mut(&mut self, k1: K, k2: K) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get2_rc_mut");
+        let (a, b) = self.get2_mut(k1, k2);
+        (unique(a), unique(b))
+    }
+
+    fn get3_rc_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get3_rc_mut");
+        let (a, b, c) = self.get3_mut(k1, k2, k3);
+        (unique(a), unique(b), unique(c))
+    }
+}
+
+#[inline]
+fn make_mut<V: Clone>(r: Result<&mut Arc<V>, SplitMutError>) -> Result<&mut V, SplitMutError> {
+    r.map(Arc::make_mut)
+}
+
+/// Just add `use splitmut::SplitMutArc;` to have these methods working on
+/// all `SplitMut` collections with `Arc` values.
+///
+/// The values are reached through `Arc::make_mut`, so a value with other `Arc`
+/// pointers to it is cloned first, and the collection gets its own copy.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use std::sync::Arc;
+/// use splitmut::SplitMutArc;
+///
+/// let mut config: HashMap<_, _> = vec![("a", Arc::new(1)), ("b", Arc::new(2))].into_iter().collect();
+/// let snapshot = config.clone();
+/// {
+///     let (a, b) = config.get2_arc_mut(&"a", &"b");
+///     std::mem::swap(a.unwrap(), b.unwrap());
+/// }
+/// assert_eq!((*config["a"], *config["b"]), (2, 1));
+/// assert_eq!((*snapshot["a"], *snapshot["b"]), (1, 2));
+/// ```
+pub trait SplitMutArc<K, V: Clone> {
+    /// Returns two mutable references to two distinct values within
+    /// the same collection, cloning them out of shared `Arc`s as needed.
+    fn get2_arc_mut(&mut self, k1: K, k2: K) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>);
+
+    /// Returns three mutable references to three distinct values within
+    /// the same collection, cloning them out of shared `Arc`s as needed.
+    fn get3_arc_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>);
+}
+
+impl<K, V: Clone, S: SplitMut<K, Arc<V>> + ?Sized> SplitMutArc<K, V> for S {
+    fn get2_arc_mut(&mut self, k1: K, k2: K) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get2_arc_mut");
+        let (a, b) = self.get2_mut(k1, k2);
+        (make_mut(a), make_mut(b))
+    }
+
+    fn get3_arc_mut(&mut self, k1: K, k2: K, k3: K) -> (Result<&mut V, SplitMutError>,
+        Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get3_arc_mut");
+        let (a, b, c) = self.get3_mut(k1, k2, k3);
+        (make_mut(a), make_mut(b), make_mut(c))
+    }
+}
+
+#[test]
+fn rc_vec() {
+    let mut v = vec![Rc::new(1), Rc::new(2), Rc::new(3)];
+    let w = Rc::downgrade(&v[2]);
+    assert_eq!(v.get3_rc_mut(0, 1, 2), (Ok(&mut 1), Ok(&mut 2), Err(SplitMutError::Shared)));
+    assert_eq!(v.get3_rc_mut(0, 0, 3), (Ok(&mut 1), Err(SplitMutError::SameValue), Err(SplitMutError::NoValue)));
+    drop(w);
+    assert_eq!(v.get2_rc_mut(2, 0), (Ok(&mut 3), Ok(&mut 1)));
+}
+
+#[test]
+fn arc_vec() {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    let a = Arc::new(String::from("x"));
+    let mut v = vec![a.clone(), a.clone(), Arc::new(String::from("y"))];
+    {
+        let (x, y, z) = v.get3_arc_mut(0, 1, 2);
+        x.unwrap().push('0');
+        y.unwrap().push('1');
+        z.unwrap().push('2');
+    }
+    assert_eq!(*a, "x");
+    assert_eq!(v.iter().map(|s| s.as_str()).collect::<Vec<_>>(), ["x0", "x1", "y2"]);
+    assert_eq!(v.get2_arc_mut(1, 1).1, Err(SplitMutError::SameValue));
+}
@@ -0,0 +1,170 @@
+//! Implementations without unsafe code, used when the `safe` feature is enabled.
+//!
+//! These replace `get2_mut`, `get3_mut` and `get4_mut` for slices, `Vec`, `VecDeque`,
+//! `HashMap` and `BTreeMap` by lookups that hand out the references safely: slices are split
+//! at the sorted indices, `HashMap` uses `get_disjoint_mut` and `BTreeMap` walks the range
+//! from the smallest key.
+//!
+//! All other methods, and the other collections, still go through the raw pointers of the
+//! `SplitMut` trait.
+
+#![forbid(unsafe_code)]
+
+use alloc::vec::Vec;
+use alloc::collections::{BTreeMap, VecDeque};
+use core::borrow::Borrow;
+use core::ops::Bound;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use core::hash::{Hash, BuildHasher};
+use {SplitMutError, err};
+
+type Res<'a, V> = Result<&'a mut V, SplitMutError>;
+
+macro_rules! safe_get_methods {
+    ($k: ty, $f: path) => {
+        fn split2_mut(&mut self, k1: $k, k2: $k) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+            let mut r = $f(self, &[k1, k2]).into_iter();
+            (r.next().unwrap(), r.next().unwrap())
+        }
+        fn split3_mut(&mut self, k1: $k, k2: $k, k3: $k) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+            let mut r = $f(self, &[k1, k2, k3]).into_iter();
+            (r.next().unwrap(), r.next().unwrap(), r.next().unwrap())
+        }
+        fn split4_mut(&mut self, k1: $k, k2: $k, k3: $k, k4: $k) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+            let mut r = $f(self, &[k1, k2, k3, k4]).into_iter();
+            (r.next().unwrap(), r.next().unwrap(), r.next().unwrap(), r.next().unwrap())
+        }
+    }
+}
+
+// Gives every key either an error, or the position among the unique present keys. `find`
+// returns what identifies the value of the key, so that duplicates are found the same way
+// the collection finds its values, whatever the `Eq` of the keys says.
+fn classify<K, T: PartialEq, F: Fn(&K) -> Option<T>>(keys: &[K], find: F) -> (Vec<Result<usize, SplitMutError>>, Vec<T>) {
+    let mut uniq: Vec<T> = Vec::with_capacity(keys.len());
+    let c = keys.iter().map(|k| {
+        let t = find(k).ok_or_else(|| err(SplitMutError::NoValue))?;
+        if uniq.contains(&t) { return Err(err(SplitMutError::SameValue)) }
+        uniq.push(t);
+        Ok(uniq.len() - 1)
+    }).collect();
+    (c, uniq)
+}
+
+fn assemble<'a, V>(c: Vec<Result<usize, SplitMutError>>, mut vals: Vec<Option<&'a mut V>>) -> Vec<Res<'a, V>> {
+    c.into_iter().map(|r| r.and_then(|i| vals[i].take().ok_or_else(|| err(SplitMutError::NoValue)))).collect()
+}
+
+fn found<'k, Q: ?Sized>(c: &[Result<usize, SplitMutError>], keys: &[&'k Q]) -> Vec<&'k Q> {
+    c.iter().zip(keys).filter(|x| x.0.is_ok()).map(|x| *x.1).collect()
+}
+
+// Splits the slice at each of the (unique, in range) indices, in sorted order.
+fn disjoint<'a, V>(mut s: &'a mut [V], idx: &[usize]) -> Vec<Option<&'a mut V>> {
+    let mut order: Vec<usize> = (0..idx.len()).collect();
+    order.sort_by_key(|&p| idx[p]);
+    let mut out: Vec<Option<&'a mut V>> = idx.iter().map(|_| None).collect();
+    let mut base = 0;
+    for p in order {
+        let (head, tail) = core::mem::take(&mut s).split_at_mut(idx[p] - base + 1);
+        out[p] = head.last_mut();
+        base = idx[p] + 1;
+        s = tail;
+    }
+    out
+}
+
+pub(crate) fn slice_get<'a, V>(s: &'a mut [V], keys: &[usize]) -> Vec<Res<'a, V>> {
+    let len = s.len();
+    let (c, idx) = classify(keys, |&k| if k < len { Some(k) } else { None });
+    assemble(c, disjoint(s, &idx))
+}
+
+pub(crate) fn deque_get<'a, V>(d: &'a mut VecDeque<V>, keys: &[usize]) -> Vec<Res<'a, V>> {
+    let len = d.len();
+    let (c, idx) = classify(keys, |&k| if k < len { Some(k) } else { None });
+    let (a, b) = d.as_mut_slices();
+    let alen = a.len();
+    let ia: Vec<usize> = idx.iter().cloned().filter(|&i| i < alen).collect();
+    let ib: Vec<usize> = idx.iter().filter(|&&i| i >= alen).map(|&i| i - alen).collect();
+    let (mut va, mut vb) = (disjoint(a, &ia).into_iter(), disjoint(b, &ib).into_iter());
+    let vals = idx.iter().map(|&i| if i < alen { va.next() } else { vb.next() }.and_then(|v| v)).collect();
+    assemble(c, vals)
+}
+
+// Keys are told apart by the entry the map finds for them, so keys that compare unequal but
+// find the same entry never reach `get_disjoint_mut`, which would panic on them.
+#[cfg(feature = "std")]
+pub(crate) fn hash_get<'a, K: Hash + Eq + Borrow<Q>, Q: Hash + Eq + ?Sized, V, S: BuildHasher>(h: &'a mut HashMap<K, V, S>, keys: &[&Q]) -> Vec<Res<'a, V>> {
+    let (c, _) = classify(keys, |k| h.get_key_value(*k).map(|(e, _)| e as *const K));
+    let u = found(&c, keys);
+    let vals: Vec<Option<&mut V>> = match u.len() {
+        0 => Vec::new(),
+        1 => vec![h.get_mut(u[0])],
+        2 => h.get_disjoint_mut([u[0], u[1]]).into(),
+        3 => h.get_disjoint_mut([u[0], u[1], u[2]]).into(),
+        _ => h.get_disjoint_mut([u[0], u[1], u[2], u[3]]).into(),
+    };
+    assemble(c, vals)
+}
+
+// Walks the map from the smallest key and picks the entries found for the keys.
+pub(crate) fn btree_get<'a, K: Ord + Borrow<Q>, Q: Ord + ?Sized, V>(h: &'a mut BTreeMap<K, V>, keys: &[&Q]) -> Vec<Res<'a, V>> {
+    let (c, ids) = classify(keys, |k| h.get_key_value(*k).map(|(e, _)| e as *const K));
+    let u = found(&c, keys);
+    let mut out: Vec<Option<&'a mut V>> = ids.iter().map(|_| None).collect();
+    if let Some(lo) = u.iter().min_by(|a, b| a.cmp(b)) {
+        let mut left = ids.len();
+        for (k, v) in h.range_mut::<Q, _>((Bound::Included(*lo), Bound::Unbounded)) {
+            if let Some(p) = ids.iter().position(|&e| core::ptr::eq(k, e)) {
+                out[p] = Some(v);
+                left -= 1;
+                if left == 0 { break }
+            }
+        }
+    }
+    assemble(c, out)
+}
+
+#[test]
+fn safe_paths() {
+    use SplitMut;
+    let mut v = vec![1, 2, 3, 4];
+    assert_eq!(v.get4_mut(3, 9, 1, 3), (Ok(&mut 4), Err(SplitMutError::NoValue), Ok(&mut 2), Err(SplitMutError::SameValue)));
+    let mut d: VecDeque<_> = v.iter().cloned().collect();
+    d.rotate_left(3);
+    assert_eq!(d.get3_mut(0, 3, 2), (Ok(&mut 4), Ok(&mut 3), Ok(&mut 2)));
+    let mut b: BTreeMap<_, _> = v.iter().map(|&x| (x, x * 10)).collect();
+    assert_eq!(b.get4_mut(&4, &1, &5, &1), (Ok(&mut 40), Ok(&mut 10), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue)));
+    #[cfg(feature = "std")]
+    {
+        let mut h: HashMap<_, _> = b.into_iter().collect();
+        assert_eq!(h.get3_mut(&2, &2, &3), (Ok(&mut 20), Err(SplitMutError::SameValue), Ok(&mut 30)));
+    }
+}
+
+#[test]
+fn safe_inconsistent_keys() {
+    use SplitMut;
+    use core::cmp::Ordering;
+    // Ordered by half the value, but only equal to itself.
+    #[derive(PartialEq, Eq, Debug)]
+    struct Half(i32);
+    impl PartialOrd for Half { fn partial_cmp(&self, o: &Self) -> Option<Ordering> { Some(self.cmp(o)) } }
+    impl Ord for Half { fn cmp(&self, o: &Self) -> Ordering { (self.0 / 2).cmp(&(o.0 / 2)) } }
+    let mut b: BTreeMap<_, _> = (0..4).map(|x| (Half(x * 2), x)).collect();
+    assert_eq!(b.get3_mut(&Half(2), &Half(3), &Half(6)), (Ok(&mut 1), Err(SplitMutError::SameValue), Ok(&mut 3)));
+    #[cfg(feature = "std")]
+    {
+        // Equal to values next to it, so neither transitive nor consistent with the map.
+        #[derive(Debug)]
+        struct Near(i32);
+        impl PartialEq for Near { fn eq(&self, o: &Self) -> bool { (self.0 - o.0).abs() <= 1 } }
+        impl Eq for Near {}
+        impl Hash for Near { fn hash<H: core::hash::Hasher>(&self, _: &mut H) {} }
+        let mut h: HashMap<_, _> = vec![(Near(1), 1), (Near(5), 5)].into_iter().collect();
+        assert_eq!(h.get3_mut(&Near(0), &Near(2), &Near(5)), (Ok(&mut 1), Err(SplitMutError::SameValue), Ok(&mut 5)));
+    }
+}
@@ -0,0 +1,131 @@
+//! Looking up values by an attribute of the value, instead of by key.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::vec::Vec;
+use {SplitMut, GetRef, SplitMutError, err};
+
+/// Owns a collection together with an index from an attribute of every value
+/// to the keys of the values having it.
+///
+/// Values returned mutably may get their attribute changed, so their keys are
+/// indexed again at the start of the next call. After changing the collection
+/// through `get_mut`, call `update` for every key that was added, removed or changed.
+///
+/// # Example
+/// ```
+/// use splitmut::{SecondaryIndex, SplitMutError};
+///
+/// struct Player { name: &'static str, score: u32 }
+/// let players = vec![Player { name: "ann", score: 3 }, Player { name: "bob", score: 5 }];
+/// let mut s = SecondaryIndex::new(players, 0..2, |p: &Player| p.name);
+/// {
+///     let (a, b) = s.get2_by_attr(&"bob", &"ann");
+///     let (a, b) = (a.unwrap(), b.unwrap());
+///     a.score += b.score;
+///     b.name = "bob";
+/// }
+/// assert_eq!(s.keys(&"bob"), [1, 0]);
+/// assert_eq!(s.get2_by_attr(&"bob", &"ann").0.err(), Some(SplitMutError::Ambiguous));
+/// assert_eq!(s.get_ref()[1].score, 8);
+/// ```
+pub struct SecondaryIndex<C, K, V, A, F> {
+    c: C,
+    f: F,
+    attrs: HashMap<K, A>,
+    keys: HashMap<A, Vec<K>>,
+    stale: Vec<K>,
+    _v: PhantomData<fn(&V)>,
+}
+
+impl<C: GetRef<K, V>, K: Clone + Hash + Eq, V, A: Clone + Hash + Eq, F: Fn(&V) -> A> SecondaryIndex<C, K, V, A, F> {
+    /// Wraps a collection, indexing the values of `keys` by the attribute `f` returns.
+    /// Keys without values are skipped.
+    pub fn new<I: IntoIterator<Item=K>>(c: C, keys: I, f: F) -> Self {
+        let mut s = SecondaryIndex { c, f, attrs: HashMap::new(), keys: HashMap::new(), stale: Vec::new(), _v: PhantomData };
+        for k in keys { s.update(k) }
+        s
+    }
+
+    /// Returns a shared reference to the wrapped collection.
+    pub fn get_ref(&self) -> &C { &self.c }
+
+    /// Returns a mutable reference to the wrapped collection. Changed keys must be
+    /// passed to `update` afterwards.
+    pub fn get_mut(&mut self) -> &mut C { &mut self.c }
+
+    /// Returns the wrapped collection.
+    pub fn into_inner(self) -> C { self.c }
+
+    /// Indexes the value of a key again, or removes the key from the index if it
+    /// no longer has a value.
+    pub fn update(&mut self, k: K) {
+        if let Some(a) = self.attrs.remove(&k) {
+            let ks = self.keys.get_mut(&a).unwrap();
+            ks.retain(|x| *x != k);
+            if ks.is_empty() { self.keys.remove(&a); }
+        }
+        if let Some(v) = self.c.get1_ref(k.clone()) {
+            let a = (self.f)(v);
+            self.keys.entry(a.clone()).or_default().push(k.clone());
+            self.attrs.insert(k, a);
+        }
+    }
+
+    fn refresh(&mut self) {
+        for k in std::mem::take(&mut self.stale) { self.update(k) }
+    }
+
+    fn key(&self, a: &A) -> Result<K, SplitMutError> {
+        match self.keys.get(a).map(|ks| &ks[..]) {
+            Some([k]) => Ok(k.clone()),
+            Some(_) => Err(err(SplitMutError::Ambiguous)),
+            None => Err(err(SplitMutError::NoValue)),
+        }
+    }
+
+    /// Returns the keys of all values with the attribute, in the order they were indexed.
+    pub fn keys(&mut self, a: &A) -> &[K] {
+        self.refresh();
+        self.keys.get(a).map_or(&[], |ks| &ks[..])
+    }
+
+    /// Returns mutable references to the values with attributes `a1` and `a2`.
+    ///
+    /// An attribute that no value has gives `Err(SplitMutError::NoValue)`, and one that
+    /// more than one value has gives `Err(SplitMutError::Ambiguous)`.
+    pub fn get2_by_attr(&mut self, a1: &A, a2: &A) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) where C: SplitMut<K, V> {
+        trace_method!("get2_by_attr");
+        self.refresh();
+        let (k1, k2) = (self.key(a1), self.key(a2));
+        self.stale.extend(k1.iter().chain(k2.iter()).cloned());
+        let r2 = if k2.is_ok() && k1 == k2 { Err(err(SplitMutError::SameValue)) } else { k2 };
+        match (k1, r2) {
+            (Ok(k1), Ok(k2)) => self.c.get2_mut(k1, k2),
+            (Ok(k1), Err(e)) => (self.c.get1_mut(k1).ok_or_else(|| err(SplitMutError::NoValue)), Err(e)),
+            (Err(e), Ok(k2)) => (Err(e), self.c.get1_mut(k2).ok_or_else(|| err(SplitMutError::NoValue))),
+            (Err(e1), Err(e2)) => (Err(e1), Err(e2)),
+        }
+    }
+}
+
+#[test]
+fn secondary_index() {
+    let mut s = SecondaryIndex::new(vec![1u32, 2, 3, 12], 0..5, |v: &u32| v % 10);
+    assert_eq!(s.keys(&2), [1, 3]);
+    assert_eq!(s.get2_by_attr(&1, &3), (Ok(&mut 1), Ok(&mut 3)));
+    assert_eq!(s.get2_by_attr(&1, &1), (Ok(&mut 1), Err(SplitMutError::SameValue)));
+    assert_eq!(s.get2_by_attr(&2, &5), (Err(SplitMutError::Ambiguous), Err(SplitMutError::NoValue)));
+    *s.get2_by_attr(&3, &0).0.unwrap() = 5;
+    assert_eq!(s.keys(&3), []);
+    assert_eq!(s.keys(&5), [2]);
+    s.get_mut().push(4);
+    s.update(4);
+    s.get_mut()[3] = 6;
+    s.update(3);
+    assert_eq!(s.get2_by_attr(&2, &4), (Ok(&mut 2), Ok(&mut 4)));
+    s.get_mut().pop();
+    s.update(4);
+    assert_eq!(s.keys(&4), []);
+}
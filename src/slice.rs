@@ -0,0 +1,420 @@
+//! Splitting helpers that only make sense for slices.
+
+use core::ops::Range;
+use alloc::vec::Vec;
+use {SplitMutError, err};
+
+/// Just add `use splitmut::SplitMutSlice;` to have these methods working on
+/// mutable slices (and through them, on `Vec` and arrays).
+pub trait SplitMutSlice<V> {
+    /// Returns a lending iterator over every unordered pair of distinct
+    /// elements, with both elements mutable.
+    ///
+    /// Pairs are visited in order `(0, 1), (0, 2), ... (1, 2), ...`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMutSlice;
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// let mut pairs = v.pairs_combinations_mut();
+    /// while let Some((a, b)) = pairs.next() {
+    ///     *a += 1;
+    ///     *b += 10;
+    /// }
+    /// assert_eq!(v, [3, 13, 23]);
+    /// ```
+    fn pairs_combinations_mut(&mut self) -> PairsCombinations<'_, V>;
+
+    /// Binary searches a sorted slice for two values, and returns mutable
+    /// references to the matching elements.
+    ///
+    /// If a value is found more than once, any one of the matches may be returned.
+    /// If both values resolve to the same element, the second one is
+    /// `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMutSlice, SplitMutError};
+    ///
+    /// let mut v = vec![(1, 'a'), (3, 'b'), (7, 'c')];
+    /// let (a, b) = v.get2_sorted_mut(&(7, 'c'), &(2, 'x'));
+    /// assert_eq!((a, b), (Ok(&mut (7, 'c')), Err(SplitMutError::NoValue)));
+    /// ```
+    fn get2_sorted_mut(&mut self, a: &V, b: &V) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) where V: Ord;
+
+    /// Like `get2_sorted_mut`, but searches using a key extraction function,
+    /// like `binary_search_by_key`.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMutSlice;
+    ///
+    /// let mut v = vec![(1, 'a'), (3, 'b'), (7, 'c')];
+    /// let (a, b) = v.get2_sorted_by_key_mut(&1, &7, |x| x.0);
+    /// std::mem::swap(&mut a.unwrap().1, &mut b.unwrap().1);
+    /// assert_eq!(v, [(1, 'c'), (3, 'b'), (7, 'a')]);
+    /// ```
+    fn get2_sorted_by_key_mut<B: Ord, F: FnMut(&V) -> B>(&mut self, a: &B, b: &B, f: F) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>);
+
+    /// Divides the slice into chunks of `n` elements, like `chunks_mut`, and returns
+    /// the chunks with index `a` and `b`. The last chunk may be shorter than `n`.
+    ///
+    /// If `a` and `b` are the same, the second one is `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Panics
+    /// Panics if `n` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMutSlice, SplitMutError};
+    ///
+    /// let mut v = vec![0, 1, 2, 3, 4, 5, 6];
+    /// let (a, b) = v.get2_chunks_mut(3, 2, 0);
+    /// let (a, b) = (a.unwrap(), b.unwrap());
+    /// a[0] += b[0] + b[1];
+    /// assert_eq!(v, [0, 1, 2, 3, 4, 5, 7]);
+    /// assert_eq!(v.get2_chunks_mut(3, 1, 3), (Ok(&mut [3, 4, 5][..]), Err(SplitMutError::NoValue)));
+    /// ```
+    fn get2_chunks_mut(&mut self, n: usize, a: usize, b: usize) -> (Result<&mut [V], SplitMutError>, Result<&mut [V], SplitMutError>);
+
+    /// Divides the slice into an unaligned head, a body that starts at an address
+    /// that is a multiple of `align` bytes, and an unaligned tail.
+    ///
+    /// The length of the body is a multiple of `align` bytes, so a SIMD kernel can
+    /// process it in whole vectors, while the head and tail are left for scalar code.
+    /// Unlike `align_to_mut`, the elements keep their type.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two, or not a multiple of the element size.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMutSlice;
+    ///
+    /// let mut v = vec![1u32; 19];
+    /// let s = v.split_aligned_mut(16);
+    /// assert_eq!(s.body.as_ptr() as usize % 16, 0);
+    /// assert_eq!(s.body.len() % 4, 0);
+    /// assert_eq!(s.head.len() + s.body.len() + s.tail.len(), 19);
+    /// for x in s.body.iter_mut() { *x = 2; }
+    /// ```
+    fn split_aligned_mut(&mut self, align: usize) -> AlignedMut<'_, V>;
+
+    /// Returns the head, aligned body and tail (as in `split_aligned_mut`) of each
+    /// of the `regions`, all mutable at the same time.
+    ///
+    /// A region that is out of range gives `Err(SplitMutError::NoValue)`, and one that
+    /// overlaps with a region returned before it gives `Err(SplitMutError::SameValue)`.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two, or not a multiple of the element size.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::{SplitMutSlice, SplitMutError};
+    ///
+    /// let mut v = vec![0u8; 256];
+    /// let mut r = v.split_aligned_regions_mut(32, &[3..100, 130..256, 90..140]).into_iter();
+    /// let (mut a, mut b) = (r.next().unwrap().unwrap(), r.next().unwrap().unwrap());
+    /// assert_eq!(r.next().unwrap().err(), Some(SplitMutError::SameValue));
+    /// for (x, y) in a.body.iter_mut().zip(b.body.iter()) { *x = *y }
+    /// a.head[0] = 1;
+    /// b.tail.fill(2);
+    /// ```
+    fn split_aligned_regions_mut(&mut self, align: usize, regions: &[Range<usize>]) -> Vec<Result<AlignedMut<'_, V>, SplitMutError>>;
+
+    /// Returns two cursors, one at the first and one at the last element, that
+    /// can be moved towards each other, for two-pointer algorithms.
+    ///
+    /// # Example
+    /// ```
+    /// use splitmut::SplitMutSlice;
+    ///
+    /// // Moves the even numbers to the front.
+    /// let mut v = vec![1, 2, 3, 4, 5, 6];
+    /// let mut c = v.two_pointer_mut();
+    /// while let (Ok(a), Ok(b)) = c.ends_mut() {
+    ///     if *a % 2 == 0 { c.advance() }
+    ///     else if *b % 2 != 0 { c.retreat() }
+    ///     else { std::mem::swap(a, b); c.advance(); c.retreat() }
+    /// }
+    /// assert_eq!(v, [6, 2, 4, 3, 5, 1]);
+    /// ```
+    fn two_pointer_mut(&mut self) -> TwoPointer<'_, V>;
+}
+
+/// Cursors returned from the two_pointer_mut function.
+///
+/// The head cursor starts at the first element and the tail cursor at the last one.
+/// The elements between them (both included) are the ones not yet visited.
+#[derive(Debug)]
+pub struct TwoPointer<'a, V: 'a> { s: &'a mut [V], lo: usize, end: usize }
+
+impl<'a, V: 'a> TwoPointer<'a, V> {
+    /// Returns the elements at the head and the tail cursor.
+    ///
+    /// When both cursors are at the same element, the second one is
+    /// `Err(SplitMutError::SameValue)`. When the cursors have crossed, both are
+    /// `Err(SplitMutError::NoValue)`.
+    pub fn ends_mut(&mut self) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        let (lo, end) = (self.lo, self.end);
+        match end - lo {
+            0 => (Err(err(SplitMutError::NoValue)), Err(err(SplitMutError::NoValue))),
+            1 => (Ok(&mut self.s[lo]), Err(err(SplitMutError::SameValue))),
+            _ => {
+                let (a, b) = self.s[lo..end].split_at_mut(end - lo - 1);
+                (Ok(&mut a[0]), Ok(&mut b[0]))
+            },
+        }
+    }
+
+    /// Returns the element at the head cursor, unless the cursors have crossed.
+    pub fn head_mut(&mut self) -> Option<&mut V> { self.s[..self.end].get_mut(self.lo) }
+
+    /// Returns the element at the tail cursor, unless the cursors have crossed.
+    pub fn tail_mut(&mut self) -> Option<&mut V> { if self.lo < self.end { self.s.get_mut(self.end - 1) } else { None } }
+
+    /// Moves the head cursor one element towards the tail. Does nothing if the cursors have crossed.
+    pub fn advance(&mut self) { if self.lo < self.end { self.lo += 1 } }
+
+    /// Moves the tail cursor one element towards the head. Does nothing if the cursors have crossed.
+    pub fn retreat(&mut self) { if self.lo < self.end { self.end -= 1 } }
+
+    /// The index of the head cursor. Once the cursors have crossed, all elements
+    /// before it have been passed by the head cursor.
+    pub fn head_index(&self) -> usize { self.lo }
+
+    /// The index of the element after the tail cursor. Once the cursors have crossed,
+    /// all elements from it have been passed by the tail cursor.
+    pub fn tail_end(&self) -> usize { self.end }
+
+    /// Returns true if both cursors are at the same element.
+    pub fn met(&self) -> bool { self.lo + 1 == self.end }
+
+    /// Returns true if the cursors have crossed, i e, there are no elements left between them.
+    pub fn crossed(&self) -> bool { self.lo == self.end }
+}
+
+/// The parts of a slice returned from the split_aligned_mut function.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AlignedMut<'a, V: 'a> {
+    /// Elements before the aligned body.
+    pub head: &'a mut [V],
+    /// Elements starting at an aligned address, a whole number of alignments long.
+    pub body: &'a mut [V],
+    /// Elements after the aligned body.
+    pub tail: &'a mut [V],
+}
+
+// Checks that the regions are in range, and that every region overlaps with
+// none of the regions returned before it.
+pub(crate) fn check_regions(len: usize, regions: &[Range<usize>]) -> Vec<Result<Range<usize>, SplitMutError>> {
+    let mut returned: Vec<&Range<usize>> = Vec::with_capacity(regions.len());
+    regions.iter().map(|r| {
+        if r.start > r.end || r.end > len { return Err(err(SplitMutError::NoValue)) };
+        if returned.iter().any(|q| q.start < r.end && r.start < q.end) { return Err(err(SplitMutError::SameValue)) };
+        returned.push(r);
+        Ok(r.clone())
+    }).collect()
+}
+
+fn split_aligned<V>(s: &mut [V], align: usize) -> AlignedMut<'_, V> {
+    let size = core::mem::size_of::<V>();
+    assert!(align.is_power_of_two() && (size == 0 || align.is_multiple_of(size)),
+        "alignment must be a power of two and a multiple of the element size");
+    let lanes = align / size.max(1);
+    let h = s.as_ptr().align_offset(align).min(s.len());
+    let (head, rest) = s.split_at_mut(h);
+    let n = rest.len() - rest.len() % lanes;
+    let (body, tail) = rest.split_at_mut(n);
+    AlignedMut { head, body, tail }
+}
+
+// Returns mutable references to two elements by index, checking for overlap.
+fn get2_idx<V>(s: &mut [V], a: Option<usize>, b: Option<usize>) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+    match (a, b) {
+        (Some(a), Some(b)) if a == b => (Ok(&mut s[a]), Err(err(SplitMutError::SameValue))),
+        (Some(a), Some(b)) => if a < b {
+            let (x, y) = s.split_at_mut(b);
+            (Ok(&mut x[a]), Ok(&mut y[0]))
+        } else {
+            let (x, y) = s.split_at_mut(a);
+            (Ok(&mut y[0]), Ok(&mut x[b]))
+        },
+        (Some(a), None) => (Ok(&mut s[a]), Err(err(SplitMutError::NoValue))),
+        (None, Some(b)) => (Err(err(SplitMutError::NoValue)), Ok(&mut s[b])),
+        (None, None) => (Err(err(SplitMutError::NoValue)), Err(err(SplitMutError::NoValue))),
+    }
+}
+
+impl<V> SplitMutSlice<V> for [V] {
+    fn pairs_combinations_mut(&mut self) -> PairsCombinations<'_, V> { PairsCombinations { s: self, i: 0, j: 1 } }
+
+    fn get2_sorted_mut(&mut self, a: &V, b: &V) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) where V: Ord {
+        trace_method!("get2_sorted_mut");
+        let (a, b) = (self.binary_search(a).ok(), self.binary_search(b).ok());
+        get2_idx(self, a, b)
+    }
+
+    fn get2_sorted_by_key_mut<B: Ord, F: FnMut(&V) -> B>(&mut self, a: &B, b: &B, mut f: F) -> (Result<&mut V, SplitMutError>, Result<&mut V, SplitMutError>) {
+        trace_method!("get2_sorted_by_key_mut");
+        let a = self.binary_search_by_key(a, &mut f).ok();
+        let b = self.binary_search_by_key(b, &mut f).ok();
+        get2_idx(self, a, b)
+    }
+
+    fn get2_chunks_mut(&mut self, n: usize, a: usize, b: usize) -> (Result<&mut [V], SplitMutError>, Result<&mut [V], SplitMutError>) {
+        trace_method!("get2_chunks_mut");
+        assert!(n != 0, "chunk size must be non-zero");
+        let count = self.len().div_ceil(n);
+        let len = self.len();
+        let range = |i: usize| i * n..len.min(i * n + n);
+        match (a < count, b < count) {
+            (true, true) if a == b => (Ok(&mut self[range(a)]), Err(err(SplitMutError::SameValue))),
+            (true, true) => {
+                let (lo, hi) = (a.min(b), a.max(b));
+                let (x, y) = self.split_at_mut(hi * n);
+                let (l, h) = (&mut x[range(lo)], &mut y[..range(hi).len()]);
+                if a < b { (Ok(l), Ok(h)) } else { (Ok(h), Ok(l)) }
+            },
+            (true, false) => (Ok(&mut self[range(a)]), Err(err(SplitMutError::NoValue))),
+            (false, true) => (Err(err(SplitMutError::NoValue)), Ok(&mut self[range(b)])),
+            (false, false) => (Err(err(SplitMutError::NoValue)), Err(err(SplitMutError::NoValue))),
+        }
+    }
+
+    fn split_aligned_mut(&mut self, align: usize) -> AlignedMut<'_, V> {
+        trace_method!("split_aligned_mut");
+        split_aligned(self, align)
+    }
+
+    fn split_aligned_regions_mut(&mut self, align: usize, regions: &[Range<usize>]) -> Vec<Result<AlignedMut<'_, V>, SplitMutError>> {
+        trace_method!("split_aligned_regions_mut");
+        let p = self.as_mut_ptr();
+        check_regions(self.len(), regions).into_iter().map(|r| r.map(|r| {
+            // The region is in range and does not overlap with any region returned before it.
+            let s = unsafe { core::slice::from_raw_parts_mut(p.add(r.start), r.len()) };
+            split_aligned(s, align)
+        })).collect()
+    }
+
+    fn two_pointer_mut(&mut self) -> TwoPointer<'_, V> {
+        let end = self.len();
+        TwoPointer { s: self, lo: 0, end }
+    }
+}
+
+/// Lending iterator returned from the pairs_combinations_mut function.
+///
+/// This cannot be a regular `Iterator`, because the returned references
+/// only live until the next call to `next`.
+pub struct PairsCombinations<'a, V: 'a> { s: &'a mut [V], i: usize, j: usize }
+
+impl<'a, V: 'a> PairsCombinations<'a, V> {
+    /// Returns the next pair, or `None` when all pairs have been visited.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&mut V, &mut V)> {
+        if self.j >= self.s.len() {
+            self.i += 1;
+            self.j = self.i + 1;
+            if self.j >= self.s.len() { return None };
+        }
+        let (i, j) = (self.i, self.j);
+        self.j += 1;
+        let (a, b) = self.s.split_at_mut(j);
+        Some((&mut a[i], &mut b[0]))
+    }
+
+    /// Returns the indices of the pair that the next call to `next` will return.
+    pub fn peek_indices(&self) -> Option<(usize, usize)> {
+        if self.j < self.s.len() { Some((self.i, self.j)) }
+        else if self.i + 2 < self.s.len() { Some((self.i + 1, self.i + 2)) }
+        else { None }
+    }
+
+    /// Calls `f` for every remaining pair, together with the indices of the pair.
+    pub fn for_each<F: FnMut(usize, usize, &mut V, &mut V)>(mut self, mut f: F) {
+        while let Some((i, j)) = self.peek_indices() {
+            let (a, b) = self.next().unwrap();
+            f(i, j, a, b);
+        }
+    }
+}
+
+#[test]
+fn pairs_all() {
+    let mut v = vec![0u32; 5];
+    let mut seen = vec![];
+    v.pairs_combinations_mut().for_each(|i, j, a, b| { *a += 1; *b += 1; seen.push((i, j)); });
+    assert_eq!(seen.len(), 10);
+    assert!(seen.iter().all(|&(i, j)| i < j));
+    assert_eq!(v, [4, 4, 4, 4, 4]);
+    let mut e: [u8; 1] = [1];
+    assert!(e.pairs_combinations_mut().next().is_none());
+    let mut e: [u8; 0] = [];
+    assert!(e.pairs_combinations_mut().next().is_none());
+}
+
+#[test]
+fn sorted2() {
+    let mut v = [1, 3, 5, 7];
+    assert_eq!(v.get2_sorted_mut(&7, &1), (Ok(&mut 7), Ok(&mut 1)));
+    assert_eq!(v.get2_sorted_mut(&3, &3), (Ok(&mut 3), Err(SplitMutError::SameValue)));
+    assert_eq!(v.get2_sorted_mut(&4, &0), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+}
+
+#[test]
+fn chunks2() {
+    let mut v: Vec<u8> = (0..10).collect();
+    assert_eq!(v.get2_chunks_mut(4, 2, 1), (Ok(&mut [8, 9][..]), Ok(&mut [4, 5, 6, 7][..])));
+    assert_eq!(v.get2_chunks_mut(4, 1, 1), (Ok(&mut [4, 5, 6, 7][..]), Err(SplitMutError::SameValue)));
+    assert_eq!(v.get2_chunks_mut(5, 2, 0).1, Ok(&mut [0, 1, 2, 3, 4][..]));
+    assert_eq!(v.get2_chunks_mut(5, 2, 0).0, Err(SplitMutError::NoValue));
+    let mut e: [u8; 0] = [];
+    assert_eq!(e.get2_chunks_mut(1, 0, 1), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+}
+
+#[test]
+fn aligned_split() {
+    let mut v = [0u64; 20];
+    for skip in 0..4 {
+        let s = v[skip..].split_aligned_mut(32);
+        assert_eq!(s.body.as_ptr() as usize % 32, 0);
+        assert_eq!(s.body.len() % 4, 0);
+        assert!(s.head.len() < 4);
+        assert!(s.tail.len() < 4);
+        assert_eq!(s.head.len() + s.body.len() + s.tail.len(), 20 - skip);
+    }
+    let mut e: [u16; 1] = [0];
+    let s = e.split_aligned_mut(64);
+    assert_eq!(s.head.len() + s.tail.len(), 1);
+    let r = v.split_aligned_regions_mut(8, &[0..4, 4..4, 17..25, 3..5, 19..21, 4..8]);
+    let lens: Vec<_> = r.iter().map(|x| x.as_ref().map(|a| a.body.len()).map_err(|e| *e)).collect();
+    assert_eq!(lens, [Ok(4), Ok(0), Err(SplitMutError::NoValue), Err(SplitMutError::SameValue), Err(SplitMutError::NoValue), Ok(4)]);
+}
+
+#[test]
+fn two_pointer() {
+    let mut v = [1, 2, 2, 3];
+    let mut c = v.two_pointer_mut();
+    assert!(!c.met() && !c.crossed());
+    c.advance();
+    c.retreat();
+    assert!(!c.met());
+    assert_eq!(c.ends_mut(), (Ok(&mut 2), Ok(&mut 2)));
+    c.retreat();
+    assert!(c.met());
+    assert_eq!(c.ends_mut(), (Ok(&mut 2), Err(SplitMutError::SameValue)));
+    assert_eq!(c.tail_mut(), Some(&mut 2));
+    c.advance();
+    assert!(c.crossed());
+    assert_eq!((c.head_index(), c.tail_end()), (2, 2));
+    assert_eq!(c.ends_mut(), (Err(SplitMutError::NoValue), Err(SplitMutError::NoValue)));
+    assert_eq!(c.head_mut(), None);
+    assert_eq!(c.tail_mut(), None);
+    c.retreat();
+    assert_eq!(c.tail_end(), 2);
+    let mut e: [u8; 0] = [];
+    assert!(e.two_pointer_mut().crossed());
+}
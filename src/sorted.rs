@@ -0,0 +1,142 @@
+//! A map stored as a sorted `Vec` of key-value pairs.
+
+use core::borrow::Borrow;
+use core::iter::FromIterator;
+use core::ops::{Index, IndexMut};
+use alloc::vec::Vec;
+use {SplitMutBase, SplitMutRemove, GetRef};
+
+/// A map stored as a `Vec` of key-value pairs sorted by key, with lookups by
+/// binary search.
+///
+/// Compared to `BTreeMap`, lookups and iteration are more cache friendly, while
+/// inserting and removing is slower for large maps.
+///
+/// # Example
+/// ```
+/// use splitmut::{SplitMut, SplitMutError, SortedVecMap};
+///
+/// let mut m: SortedVecMap<_, _> = vec![("b", 2), ("a", 1), ("c", 3)].into_iter().collect();
+/// let (a, c) = m.get2_mut("a", "c");
+/// std::mem::swap(a.unwrap(), c.unwrap());
+/// assert_eq!(m.as_slice(), [("a", 3), ("b", 2), ("c", 1)]);
+/// assert_eq!(m.get2_mut("b", "d").1, Err(SplitMutError::NoValue));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SortedVecMap<K, V>(Vec<(K, V)>);
+
+impl<K, V> Default for SortedVecMap<K, V> {
+    fn default() -> Self { SortedVecMap(Vec::new()) }
+}
+
+impl<K: Ord, V> SortedVecMap<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self { Self::default() }
+
+    fn find<Q: Ord + ?Sized>(&self, k: &Q) -> Result<usize, usize> where K: Borrow<Q> {
+        self.0.binary_search_by(|x| x.0.borrow().cmp(k))
+    }
+
+    /// Inserts a value, returning the previous value for the key.
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        match self.find(&k) {
+            Ok(i) => Some(core::mem::replace(&mut self.0[i].1, v)),
+            Err(i) => { self.0.insert(i, (k, v)); None },
+        }
+    }
+
+    /// Removes the value for a key and returns it.
+    pub fn remove<Q: Ord + ?Sized>(&mut self, k: &Q) -> Option<V> where K: Borrow<Q> {
+        let i = self.find(k).ok()?;
+        Some(self.0.remove(i).1)
+    }
+
+    /// Returns the value for a key.
+    pub fn get<Q: Ord + ?Sized>(&self, k: &Q) -> Option<&V> where K: Borrow<Q> {
+        self.find(k).ok().map(|i| &self.0[i].1)
+    }
+
+    /// Returns the value for a key, mutably.
+    pub fn get_mut<Q: Ord + ?Sized>(&mut self, k: &Q) -> Option<&mut V> where K: Borrow<Q> {
+        let i = self.find(k).ok()?;
+        Some(&mut self.0[i].1)
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Returns true if there are no values.
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Returns the key-value pairs, sorted by key.
+    pub fn as_slice(&self) -> &[(K, V)] { &self.0 }
+
+    /// Returns the key-value pairs, sorted by key.
+    pub fn into_inner(self) -> Vec<(K, V)> { self.0 }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SortedVecMap<K, V> {
+    /// Collects pairs into a map; for repeated keys, the last value is kept.
+    fn from_iter<I: IntoIterator<Item=(K, V)>>(i: I) -> Self {
+        let mut v: Vec<(K, V)> = i.into_iter().collect();
+        // Stable sort, so that the last pair of every key ends up last among its equals
+        v.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut out: Vec<(K, V)> = Vec::with_capacity(v.len());
+        for x in v {
+            match out.last_mut() {
+                Some(l) if l.0 == x.0 => *l = x,
+                _ => out.push(x),
+            }
+        }
+        SortedVecMap(out)
+    }
+}
+
+unsafe impl<'a, K: Ord + Borrow<Q>, Q: Ord + ?Sized, V> SplitMutBase<&'a Q, V> for SortedVecMap<K, V> {
+    #[inline]
+    fn get1_mut(&mut self, k: &'a Q) -> Option<&mut V> { self.get_mut(k) }
+    #[inline]
+    unsafe fn get1_unchecked_mut(&mut self, k: &'a Q) -> &mut V {
+        let i = self.find(k).unwrap_or(usize::MAX);
+        &mut self.0.get_unchecked_mut(i).1
+    }
+}
+
+impl<'a, K: Ord + Borrow<Q>, Q: Ord + ?Sized, V> GetRef<&'a Q, V> for SortedVecMap<K, V> {
+    #[inline]
+    fn get1_ref(&self, k: &'a Q) -> Option<&V> { self.get(k) }
+}
+
+impl<'a, K: Ord + Borrow<Q>, Q: Ord + ?Sized + 'a, V> SplitMutRemove<&'a Q, V> for SortedVecMap<K, V> {
+    fn remove_many<I: IntoIterator<Item=&'a Q>>(&mut self, keys: I) -> Vec<Option<V>> {
+        keys.into_iter().map(|k| self.remove(k)).collect()
+    }
+}
+
+impl<'a, K: Ord + Borrow<Q>, Q: Ord + ?Sized, V> Index<&'a Q> for SortedVecMap<K, V> {
+    type Output = V;
+    fn index(&self, k: &'a Q) -> &V { self.get(k).expect("no value for key") }
+}
+
+impl<'a, K: Ord + Borrow<Q>, Q: Ord + ?Sized, V> IndexMut<&'a Q> for SortedVecMap<K, V> {
+    fn index_mut(&mut self, k: &'a Q) -> &mut V { self.get_mut(k).expect("no value for key") }
+}
+
+#[test]
+fn sorted_vec_map() {
+    use {SplitMut, SplitMutError};
+    let mut m: SortedVecMap<u32, &str> = vec![(5, "x"), (1, "a"), (5, "e"), (3, "c")].into_iter().collect();
+    assert_eq!(m.as_slice(), [(1, "a"), (3, "c"), (5, "e")]);
+    assert_eq!(m.insert(2, "b"), None);
+    assert_eq!(m.insert(5, "f"), Some("e"));
+    assert_eq!(m.get3_mut(&5, &2, &5), (Ok(&mut "f"), Ok(&mut "b"), Err(SplitMutError::SameValue)));
+    assert_eq!(m.remove(&1), Some("a"));
+    assert_eq!(m.len(), 3);
+    let (taken, m) = m.partition_owned(vec![&3, &4]);
+    assert_eq!(taken, [Some("c"), None]);
+    assert_eq!(m.as_slice(), [(2, "b"), (5, "f")]);
+    let mut m = m;
+    m[&2] = "g";
+    assert_eq!(m[&2], "g");
+    ::testing::assert_splitmut_sound(&mut m, &[&2, &5], &[&1, &3]);
+}
@@ -0,0 +1,73 @@
+//! Counters for errors handed out by this crate.
+//!
+//! Every `NoValue` or `SameValue` error produced by any of the get*_mut functions
+//! is counted here, and every error is optionally passed to a user callback. This makes it
+//! possible to find places where key lists contain duplicates or stale keys.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use SplitMutError;
+
+static NO_VALUE: AtomicUsize = AtomicUsize::new(0);
+static SAME_VALUE: AtomicUsize = AtomicUsize::new(0);
+static CALLBACK: RwLock<Option<fn(SplitMutError)>> = RwLock::new(None);
+
+pub(crate) fn record(e: SplitMutError) {
+    match e {
+        SplitMutError::NoValue => NO_VALUE.fetch_add(1, Ordering::Relaxed),
+        SplitMutError::SameValue => SAME_VALUE.fetch_add(1, Ordering::Relaxed),
+        _ => 0,
+    };
+    if let Some(f) = *CALLBACK.read().unwrap_or_else(|e| e.into_inner()) { f(e) }
+}
+
+/// Number of `SplitMutError::NoValue` errors produced since the last reset.
+pub fn no_value_count() -> usize { NO_VALUE.load(Ordering::Relaxed) }
+
+/// Number of `SplitMutError::SameValue` errors produced since the last reset.
+pub fn same_value_count() -> usize { SAME_VALUE.load(Ordering::Relaxed) }
+
+/// Sets both counters to zero.
+pub fn reset() {
+    NO_VALUE.store(0, Ordering::Relaxed);
+    SAME_VALUE.store(0, Ordering::Relaxed);
+}
+
+/// Sets a function to be called for every error produced, or removes it if `None`.
+///
+/// The callback is called from whatever thread produced the error.
+///
+/// # Example
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use splitmut::{SplitMut, SplitMutError, stats};
+///
+/// static SAME: AtomicUsize = AtomicUsize::new(0);
+/// fn on_error(e: SplitMutError) {
+///     if e == SplitMutError::SameValue { SAME.fetch_add(1, Ordering::Relaxed); }
+/// }
+///
+/// stats::set_callback(Some(on_error));
+/// let mut v = vec![1, 2];
+/// let _ = v.get2_mut(0, 0);
+/// let _ = v.get3_mut(1, 5, 1);
+/// assert_eq!(SAME.load(Ordering::Relaxed), 2);
+/// stats::set_callback(None);
+/// let _ = v.get2_mut(0, 0);
+/// assert_eq!(SAME.load(Ordering::Relaxed), 2);
+/// ```
+pub fn set_callback(f: Option<fn(SplitMutError)>) {
+    *CALLBACK.write().unwrap_or_else(|e| e.into_inner()) = f;
+}
+
+#[test]
+fn stats_count() {
+    use SplitMut;
+    let (n, s) = (no_value_count(), same_value_count());
+    let mut v = vec![1, 2];
+    let _ = v.get3_mut(0, 0, 5);
+    let _ = v.get2_mut(0, 1);
+    // Other tests may run concurrently, so only check lower bounds.
+    assert!(no_value_count() > n);
+    assert!(same_value_count() > s);
+}
@@ -0,0 +1,263 @@
+//! Split sessions that can be shared between threads.
+
+use std::cell::UnsafeCell;
+use std::collections::{HashSet, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::boxed::Box;
+use std::vec::Vec;
+use {SharedLookup, SplitMut, SplitMutError, to_r, err};
+
+/// Wrapper struct for the get_muts_sync function.
+///
+/// Works like `GetMuts`, but `at` takes `&self`, so the session can be shared
+/// between scoped threads, each claiming its own keys.
+///
+/// Claimed keys are kept in 64 stripes, each behind its own mutex, and a key is
+/// claimed before its value is looked up. The lookups themselves are serialized
+/// through one more mutex, since they need the collection mutably.
+pub struct SyncGetMuts<'a, K, V, A: 'a + SyncLookup<K, V> + ?Sized> {
+    a: Mutex<&'a mut A>,
+    stripes: Box<[Mutex<HashSet<K>>]>,
+    _v: PhantomData<*mut V>,
+}
+
+unsafe impl<'a, K: Send, V: Send, A: 'a + SyncLookup<K, V> + ?Sized + Send> Send for SyncGetMuts<'a, K, V, A> {}
+unsafe impl<'a, K: Send, V: Send, A: 'a + SyncLookup<K, V> + ?Sized + Send> Sync for SyncGetMuts<'a, K, V, A> {}
+
+// The stripe a key belongs to.
+fn stripe_of<K: Hash>(k: &K, n: usize) -> usize {
+    let mut h = DefaultHasher::new();
+    k.hash(&mut h);
+    (h.finish() % n as u64) as usize
+}
+
+impl<'a, K: Hash + Eq + Clone, V, A: 'a + SyncLookup<K, V> + ?Sized> SyncGetMuts<'a, K, V, A> {
+    pub(crate) fn new(a: &'a mut A) -> Self {
+        SyncGetMuts { a: Mutex::new(a), stripes: (0..64).map(|_| Mutex::new(HashSet::new())).collect(), _v: PhantomData }
+    }
+
+    /// Returns a mutable reference with the same lifetime as the
+    /// original collection, i e, it allows several distinct mutable references to
+    /// be alive simultaneously, possibly on different threads.
+    ///
+    /// It returns an error if the value does not exist or has already been returned.
+    pub fn at(&self, k: K) -> Result<&'a mut V, SplitMutError> {
+        trace_method!("at");
+        let mut s = self.stripes[stripe_of(&k, self.stripes.len())].lock().unwrap_or_else(|e| e.into_inner());
+        if s.contains(&k) { return Err(err(SplitMutError::SameValue)) }
+        // SyncLookup promises that a value is only found by keys equal to its key,
+        // so no other thread holds this value, and the `&mut A` covers no values.
+        let p = to_r(self.a.lock().unwrap_or_else(|e| e.into_inner()).get1_mut(k.clone()))?;
+        s.insert(k);
+        Ok(unsafe { &mut *p })
+    }
+}
+
+/// Collections whose values can be looked up from several threads, while other
+/// threads write to values returned earlier, as `SplitRwLock` does.
+///
+/// # Safety
+///
+/// In addition to what `SharedLookup` requires, keys that give the same value must
+/// be equal, and hash the same. This is not the case for maps, whose keys are
+/// compared with the `Eq`, `Hash` or `Ord` of the key type, which safe code may
+/// implement inconsistently:
+///
+/// ```compile_fail
+/// use std::collections::HashMap;
+/// use splitmut::SplitMut;
+///
+/// let mut h: HashMap<u8, u8> = HashMap::new();
+/// let _ = h.get_muts_sync();
+/// ```
+pub unsafe trait SyncLookup<K, V>: SharedLookup<K, V> {}
+
+unsafe impl<V> SyncLookup<usize, V> for Vec<V> {}
+unsafe impl<V> SyncLookup<usize, V> for VecDeque<V> {}
+
+/// Owns a collection and lets several threads write to disjoint values at the same time.
+///
+/// Keys are locked with striped locks: every key hashes to one of a fixed number of
+/// mutexes, which `SplitWriteGuard` holds. `write` waits until the key's stripe is
+/// released by whoever holds it, while `try_write` returns `Err(SplitMutError::WouldBlock)`
+/// instead of waiting. Different keys in the same stripe also exclude each other.
+///
+/// Only the lookups themselves are serialized, which is why the collection must
+/// implement `SyncLookup`; the writes through guards run in parallel.
+///
+/// # Example
+/// ```
+/// use splitmut::SplitRwLock;
+///
+/// let l = SplitRwLock::new(vec![0; 4]);
+/// std::thread::scope(|s| {
+///     for t in 0..8 {
+///         let l = &l;
+///         s.spawn(move || *l.write(t % 4).unwrap() += 1);
+///     }
+/// });
+/// assert_eq!(l.into_inner(), [2, 2, 2, 2]);
+/// ```
+pub struct SplitRwLock<C> {
+    c: UnsafeCell<C>,
+    lookup: Mutex<()>,
+    stripes: Box<[Mutex<()>]>,
+}
+
+unsafe impl<C: Send> Sync for SplitRwLock<C> {}
+
+impl<C> SplitRwLock<C> {
+    /// Wraps a collection, with 64 stripes.
+    pub fn new(c: C) -> Self { Self::with_stripes(c, 64) }
+
+    /// Wraps a collection, with `n` stripes. More stripes make it less likely that writes
+    /// to different keys wait for each other.
+    ///
+    /// # Panics
+    /// Panics if `n` is zero.
+    pub fn with_stripes(c: C, n: usize) -> Self {
+        assert!(n != 0, "a SplitRwLock needs at least one stripe");
+        SplitRwLock { c: UnsafeCell::new(c), lookup: Mutex::new(()), stripes: (0..n).map(|_| Mutex::new(())).collect() }
+    }
+
+    /// Returns the wrapped collection.
+    pub fn into_inner(self) -> C { self.c.into_inner() }
+
+    /// Returns a mutable reference to the wrapped collection. Since this requires
+    /// `&mut self`, no guards can be alive.
+    pub fn get_mut(&mut self) -> &mut C { self.c.get_mut() }
+
+    fn stripe<K: Hash>(&self, k: &K) -> &Mutex<()> { &self.stripes[stripe_of(k, self.stripes.len())] }
+
+    // Called with the key's stripe locked, so no guard holds the value.
+    fn find<'a, K, V>(&'a self, k: K, s: MutexGuard<'a, ()>) -> Result<SplitWriteGuard<'a, C, V>, SplitMutError> where C: SyncLookup<K, V> {
+        let _g = self.lookup.lock().unwrap_or_else(|e| e.into_inner());
+        // SyncLookup promises that the `&mut C` covers no values held by other guards.
+        let p = to_r(unsafe { &mut *self.c.get() }.get1_mut(k))?;
+        Ok(SplitWriteGuard { p, _stripe: s, _l: PhantomData })
+    }
+
+    /// Returns a guard for the value of `k`, waiting for any other guard in the same
+    /// stripe to be dropped first. Returns an error if the value does not exist.
+    ///
+    /// Waiting for a stripe that the current thread already holds never finishes.
+    pub fn write<K: Hash, V>(&self, k: K) -> Result<SplitWriteGuard<'_, C, V>, SplitMutError> where C: SyncLookup<K, V> {
+        trace_method!("write");
+        let s = self.stripe(&k).lock().unwrap_or_else(|e| e.into_inner());
+        self.find(k, s)
+    }
+
+    /// Like `write`, but returns `Err(SplitMutError::WouldBlock)` if the stripe is held
+    /// by another guard.
+    pub fn try_write<K: Hash, V>(&self, k: K) -> Result<SplitWriteGuard<'_, C, V>, SplitMutError> where C: SyncLookup<K, V> {
+        trace_method!("try_write");
+        let s = match self.stripe(&k).try_lock() {
+            Ok(s) => s,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return Err(err(SplitMutError::WouldBlock)),
+        };
+        self.find(k, s)
+    }
+}
+
+/// A mutable handle to a value inside a `SplitRwLock`. The value is released when
+/// the guard is dropped.
+pub struct SplitWriteGuard<'a, C: 'a, V: 'a> { p: *mut V, _stripe: MutexGuard<'a, ()>, _l: PhantomData<&'a SplitRwLock<C>> }
+
+impl<'a, C: 'a, V: 'a> Deref for SplitWriteGuard<'a, C, V> {
+    type Target = V;
+    fn deref(&self) -> &V { unsafe { &*self.p } }
+}
+
+impl<'a, C: 'a, V: 'a> DerefMut for SplitWriteGuard<'a, C, V> {
+    fn deref_mut(&mut self) -> &mut V { unsafe { &mut *self.p } }
+}
+
+pub(crate) fn scope_split<K, V: Send, A: SplitMut<K, V> + ?Sized, P, I, T, F>(a: &mut A, parts: I, f: F) -> Result<Vec<T>, SplitMutError>
+where I: IntoIterator<Item=P>, P: IntoIterator<Item=K>, T: Send, F: Fn(usize, Vec<&mut V>) -> T + Sync {
+    let mut z = a.get_muts();
+    let mut shards = Vec::new();
+    for p in parts {
+        let s: Result<Vec<&mut V>, SplitMutError> = p.into_iter().map(|k| z.at(k)).collect();
+        shards.push(s?);
+    }
+    let f = &f;
+    Ok(std::thread::scope(|s| {
+        let h: Vec<_> = shards.into_iter().enumerate().map(|(i, shard)| s.spawn(move || f(i, shard))).collect();
+        h.into_iter().map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+    }))
+}
+
+#[test]
+fn sync_threads() {
+    use SplitMut;
+    let mut v: Vec<u32> = (0..100).collect();
+    {
+        let z = v.get_muts_sync();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let z = &z;
+                s.spawn(move || for i in (t..100).step_by(4) { *z.at(i).unwrap() += 1 });
+            }
+        });
+        assert_eq!(z.at(5), Err(SplitMutError::SameValue));
+        assert_eq!(z.at(100), Err(SplitMutError::NoValue));
+    }
+    assert_eq!(v, (1..101).collect::<Vec<_>>());
+}
+
+#[test]
+fn split_rwlock() {
+    let l = SplitRwLock::new(vec![0u32; 3]);
+    {
+        let a = l.try_write(1).unwrap();
+        assert_eq!(l.try_write(1).err(), Some(SplitMutError::WouldBlock));
+        assert_eq!(l.write(3).err(), Some(SplitMutError::NoValue));
+        std::thread::scope(|s| {
+            let w = s.spawn(|| *l.write(1).unwrap() += 5);
+            *l.write(0).unwrap() += 1;
+            drop(a);
+            w.join().unwrap();
+        });
+    }
+    assert_eq!(l.into_inner(), [1, 5, 0]);
+}
+
+#[test]
+fn scope_split_parts() {
+    use std::collections::HashMap;
+    let mut h: HashMap<u32, Vec<u32>> = (0..6).map(|i| (i, vec![])).collect();
+    let k: Vec<u32> = (0..6).collect();
+    let r = h.scope_split((0..3).map(|t| vec![&k[t], &k[t + 3]]), |t, part| {
+        for v in part { v.push(t as u32) }
+        std::thread::current().id()
+    });
+    assert_eq!(r.unwrap().len(), 3);
+    assert_eq!(h[&4], [1]);
+    assert_eq!(h.scope_split(vec![vec![&1, &9]], |t, _| t), Err(SplitMutError::NoValue));
+    assert_eq!(h.scope_split(Vec::<Vec<&u32>>::new(), |t, _| t), Ok(vec![]));
+    assert!(h.values().all(|v| v.len() == 1));
+}
+
+#[test]
+fn split_rwlock_stripes() {
+    let l = SplitRwLock::with_stripes(std::collections::VecDeque::from(vec![0u32; 8]), 1);
+    {
+        let _a = l.write(0).unwrap();
+        // With one stripe, every key excludes every other.
+        assert_eq!(l.try_write(5).err(), Some(SplitMutError::WouldBlock));
+    }
+    *l.try_write(5).unwrap() = 3;
+    let l = SplitRwLock::with_stripes(l.into_inner(), 1000);
+    std::thread::scope(|s| {
+        for t in 0..8 {
+            let l = &l;
+            s.spawn(move || for _ in 0..100 { *l.write(t).unwrap() += 1 });
+        }
+    });
+    assert_eq!(l.into_inner(), [100, 100, 100, 100, 100, 103, 100, 100]);
+}
@@ -0,0 +1,249 @@
+//! Helpers for testing your own `SplitMutBase` implementations.
+
+use core::fmt::Debug;
+use core::mem;
+use alloc::vec::Vec;
+use {SplitMut, SplitMutError};
+
+/// Exercises a `SplitMutBase` implementation and panics if it violates the contract.
+///
+/// `present` must be keys that have a value in `a`, and `absent` keys that don't.
+/// The following is checked:
+///
+///  * `get1_mut` returns `None` for all absent keys, and `Some` for all present keys.
+///  * Repeated calls to `get1_mut` for the same key return the same value.
+///  * Looking up other keys does not move previously returned values.
+///  * `get1_unchecked_mut` agrees with `get1_mut`.
+///  * `get2_mut` succeeds for every pair of present keys resolving to different values.
+///
+/// For zero-sized values, all values may share one address, so only the first
+/// two checks are done.
+///
+/// The collection is not modified, except through what your implementation does.
+///
+/// # Example
+/// ```
+/// use splitmut::testing::assert_splitmut_sound;
+///
+/// let mut v = vec![1, 2, 3];
+/// assert_splitmut_sound(&mut v, &[0, 1, 2], &[3, 100]);
+/// ```
+pub fn assert_splitmut_sound<K: Clone + Debug, V, A: SplitMut<K, V> + ?Sized>(a: &mut A, present: &[K], absent: &[K]) {
+    for k in absent {
+        assert!(a.get1_mut(k.clone()).is_none(), "get1_mut({:?}) returned a value for an absent key", k);
+    }
+    let ptrs: Vec<*mut V> = present.iter().map(|k| match a.get1_mut(k.clone()) {
+        Some(v) => v as *mut V,
+        None => panic!("get1_mut({:?}) returned None for a present key", k),
+    }).collect();
+    for (k, &p) in present.iter().zip(&ptrs) {
+        let p2 = a.get1_mut(k.clone()).unwrap() as *mut V;
+        if mem::size_of::<V>() == 0 { continue };
+        assert_eq!(p, p2, "get1_mut({:?}) is not stable: value moved between calls, or was moved by other lookups", k);
+        let p3 = unsafe { a.get1_unchecked_mut(k.clone()) } as *mut V;
+        assert_eq!(p, p3, "get1_unchecked_mut({:?}) does not agree with get1_mut", k);
+    }
+    if mem::size_of::<V>() == 0 { return };
+    for (i, k1) in present.iter().enumerate() {
+        for (j, k2) in present.iter().enumerate() {
+            let (r1, r2) = a.get2_mut(k1.clone(), k2.clone());
+            let (r1, r2) = (r1.map(|v| v as *mut V), r2.map(|v| v as *mut V));
+            assert_eq!(r1, Ok(ptrs[i]), "get2_mut({:?}, {:?}) did not return the value of get1_mut for the first key", k1, k2);
+            if ptrs[i] != ptrs[j] {
+                assert_eq!(r2, Ok(ptrs[j]), "get2_mut({:?}, {:?}) did not return the value of get1_mut for the second key", k1, k2);
+            } else {
+                assert!(r2.is_err(), "get2_mut({:?}, {:?}) returned the same value twice", k1, k2);
+            }
+        }
+    }
+}
+
+/// Checks that the getters for several keys reject duplicate and absent keys.
+///
+/// `present` and `absent` are like for `assert_splitmut_sound`. The following is checked,
+/// also for zero-sized values:
+///
+///  * Passing a present key several times to `get2_mut`, `get3_mut` or `get4_mut` gives
+///    the value for the first one, and `Err(SplitMutError::SameValue)` for the rest.
+///  * An absent key gives `Err(SplitMutError::NoValue)`, both before and after a present key,
+///    without affecting the present one.
+pub fn assert_splitmut_errors<K: Clone + Debug, V, A: SplitMut<K, V> + ?Sized>(a: &mut A, present: &[K], absent: &[K]) {
+    let same = Err(SplitMutError::SameValue);
+    for k in present {
+        let r = a.get2_mut(k.clone(), k.clone());
+        assert!(r.0.is_ok() && r.1.map(|_| ()) == same, "get2_mut({:?}, {:?}) did not give SameValue for the second key", k, k);
+        let r = a.get3_mut(k.clone(), k.clone(), k.clone());
+        assert!(r.0.is_ok() && r.1.map(|_| ()) == same && r.2.map(|_| ()) == same,
+            "get3_mut({:?}, {:?}, {:?}) did not give SameValue for the repeated keys", k, k, k);
+        let r = a.get4_mut(k.clone(), k.clone(), k.clone(), k.clone());
+        assert!(r.0.is_ok() && r.1.map(|_| ()) == same && r.2.map(|_| ()) == same && r.3.map(|_| ()) == same,
+            "get4_mut({:?}, {:?}, {:?}, {:?}) did not give SameValue for the repeated keys", k, k, k, k);
+    }
+    let p = match present.first() { Some(p) => p, None => return };
+    for k in absent {
+        let (r1, r2) = a.get2_mut(k.clone(), p.clone());
+        assert!(r1.map(|_| ()) == Err(SplitMutError::NoValue) && r2.is_ok(), "get2_mut({:?}, {:?}) did not give NoValue for the absent key only", k, p);
+        let (r1, r2) = a.get2_mut(p.clone(), k.clone());
+        assert!(r1.is_ok() && r2.map(|_| ()) == Err(SplitMutError::NoValue), "get2_mut({:?}, {:?}) did not give NoValue for the absent key only", p, k);
+    }
+}
+
+/// Checks that `get_mut_iter` agrees with `get1_mut`.
+///
+/// The keys `present`, then `absent`, then `present` again are passed to `get_mut_iter`.
+/// The first round must give the values of `get1_mut`, except for keys resolving to a value
+/// already returned, the absent keys must give `Err(SplitMutError::NoValue)`, and the second
+/// round of present keys must give `Err(SplitMutError::SameValue)` throughout.
+///
+/// For zero-sized values, only the errors are checked.
+#[cfg(feature = "std")]
+pub fn assert_iter_sound<K: Clone + Debug, V, A: SplitMut<K, V> + ?Sized>(a: &mut A, present: &[K], absent: &[K]) {
+    let zst = mem::size_of::<V>() == 0;
+    let ptrs: Vec<Option<*mut V>> = present.iter().map(|k| a.get1_mut(k.clone()).map(|v| v as *mut V)).collect();
+    let keys = present.iter().chain(absent).chain(present).cloned();
+    let got: Vec<Result<*mut V, SplitMutError>> = a.get_mut_iter(keys).map(|r| r.map(|v| v as *mut V)).collect();
+    assert_eq!(got.len(), 2 * present.len() + absent.len(), "get_mut_iter did not give one item per key");
+    let (first, rest) = got.split_at(present.len());
+    let (missing, again) = rest.split_at(absent.len());
+    for (i, (k, r)) in present.iter().zip(first).enumerate() {
+        let seen = ptrs[..i].contains(&ptrs[i]);
+        match *r {
+            Ok(p) => assert!(zst || (!seen && Some(p) == ptrs[i]), "get_mut_iter gave a different value than get1_mut for {:?}", k),
+            Err(e) => assert!(e == SplitMutError::SameValue && (seen || zst), "get_mut_iter gave {:?} for the present key {:?}", e, k),
+        }
+    }
+    for (k, r) in absent.iter().zip(missing) {
+        assert!(r.is_err() && r.err() == Some(SplitMutError::NoValue), "get_mut_iter did not give NoValue for the absent key {:?}", k);
+    }
+    for (k, r) in present.iter().zip(again) {
+        assert!(r.is_err() && r.err() == Some(SplitMutError::SameValue), "get_mut_iter did not give SameValue for the repeated key {:?}", k);
+    }
+}
+
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub fn assert_iter_sound<K, V, A: SplitMut<K, V> + ?Sized>(_: &mut A, _: &[K], _: &[K]) {}
+
+/// Generates a module of tests for a `SplitMutBase` implementation.
+///
+/// Give the name of the module, a function (or closure) creating the collection,
+/// keys that have a value in it and keys that don't. The tests call
+/// `assert_splitmut_sound`, `assert_splitmut_errors` and `assert_iter_sound`
+/// (the latter only with the `std` feature of splitmut) on a fresh collection each.
+///
+/// If your collection is generic over its values, add a function creating one with
+/// zero-sized values, and the same keys, after `zst:`, to run the tests for it too.
+///
+/// # Example
+/// ```
+/// #[macro_use] extern crate splitmut;
+///
+/// struct Evens(Vec<u32>);
+///
+/// unsafe impl splitmut::SplitMutBase<u32, u32> for Evens {
+///     fn get1_mut(&mut self, k: u32) -> Option<&mut u32> {
+///         if k % 2 == 0 { self.0.get_mut(k as usize / 2) } else { None }
+///     }
+///     unsafe fn get1_unchecked_mut(&mut self, k: u32) -> &mut u32 { self.0.get_unchecked_mut(k as usize / 2) }
+/// }
+///
+/// splitmut_impl_tests!(evens_tests {
+///     new: || Evens(vec![5, 6, 7]),
+///     present: [0, 2, 4],
+///     absent: [1, 6],
+/// });
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! splitmut_impl_tests {
+    ($name: ident { new: $new: expr, present: $present: expr, absent: $absent: expr $(, zst: $zst: expr)* $(,)* }) => {
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[test]
+            fn sound() {
+                $crate::testing::assert_splitmut_sound(&mut ($new)(), &$present, &$absent);
+                $($crate::testing::assert_splitmut_sound(&mut ($zst)(), &$present, &$absent);)*
+            }
+
+            #[test]
+            fn errors() {
+                $crate::testing::assert_splitmut_errors(&mut ($new)(), &$present, &$absent);
+                $($crate::testing::assert_splitmut_errors(&mut ($zst)(), &$present, &$absent);)*
+            }
+
+            #[test]
+            fn iter() {
+                $crate::testing::assert_iter_sound(&mut ($new)(), &$present, &$absent);
+                $($crate::testing::assert_iter_sound(&mut ($zst)(), &$present, &$absent);)*
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sound_std() {
+    use std::collections::{HashMap, BTreeMap, VecDeque};
+    let mut h: HashMap<_, _> = (0..10).map(|i| (i, i * 2)).collect();
+    let keys: Vec<_> = (0..10).collect();
+    let kr: Vec<&i32> = keys.iter().collect();
+    assert_splitmut_sound(&mut h, &kr, &[&10, &-1]);
+    let mut b: BTreeMap<_, _> = (0..10).map(|i| (i, i * 2)).collect();
+    assert_splitmut_sound(&mut b, &kr, &[&10]);
+    let mut d: VecDeque<_> = (0..5).collect();
+    assert_splitmut_sound(&mut d, &[0, 1, 2, 3, 4], &[5]);
+    let mut z = vec![(); 4];
+    assert_splitmut_sound(&mut z, &[0, 1, 2, 3], &[4]);
+}
+
+#[test]
+#[should_panic(expected = "not stable")]
+fn sound_detects_unstable() {
+    // Returns a different element on every call.
+    struct Bad(Vec<u8>, usize);
+    use SplitMutBase;
+    unsafe impl SplitMutBase<usize, u8> for Bad {
+        fn get1_mut(&mut self, k: usize) -> Option<&mut u8> {
+            self.1 += 1;
+            let n = self.0.len();
+            if k < n { self.0.get_mut((k + self.1) % n) } else { None }
+        }
+        unsafe fn get1_unchecked_mut(&mut self, k: usize) -> &mut u8 { self.0.get_unchecked_mut(k) }
+    }
+    let mut b = Bad(vec![1, 2, 3], 0);
+    assert_splitmut_sound(&mut b, &[0, 1], &[]);
+}
+
+#[cfg(test)]
+splitmut_impl_tests!(impl_tests_vec {
+    new: || vec![1, 2, 3],
+    present: [0, 1, 2],
+    absent: [3, 100],
+    zst: || vec![(); 3],
+});
+
+#[cfg(test)]
+splitmut_impl_tests!(impl_tests_btree {
+    new: || (0..5).map(|i| (i, i * 2)).collect::<alloc::collections::BTreeMap<_, _>>(),
+    present: [&0, &3, &4],
+    absent: [&5, &-1],
+});
+
+#[test]
+#[should_panic(expected = "did not give SameValue")]
+fn errors_detects_duplicates() {
+    // Forgets to check for duplicates in split2_mut.
+    struct Bad(Vec<u8>);
+    use SplitMutBase;
+    unsafe impl SplitMutBase<usize, u8> for Bad {
+        fn get1_mut(&mut self, k: usize) -> Option<&mut u8> { self.0.get_mut(k) }
+        unsafe fn get1_unchecked_mut(&mut self, k: usize) -> &mut u8 { self.0.get_unchecked_mut(k) }
+        fn split2_mut(&mut self, k1: usize, k2: usize) -> (Result<&mut u8, SplitMutError>, Result<&mut u8, SplitMutError>) {
+            let p = self.0.as_mut_ptr();
+            unsafe { (Ok(&mut *p.add(k1)), Ok(&mut *p.add(k2))) }
+        }
+    }
+    assert_splitmut_errors(&mut Bad(vec![1, 2]), &[1], &[]);
+}
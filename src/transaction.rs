@@ -0,0 +1,89 @@
+//! Staging writes to several values, and applying them all at once.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use {SplitMut, SplitMutBase};
+
+/// Stages writes to values of a collection, which are only applied on `commit`.
+///
+/// The transaction implements `SplitMut` itself, so values are reached through
+/// `get2_mut` and friends. The first time a value is asked for, it is cloned, and
+/// that copy is handed out from then on. `commit` writes all copies back to the
+/// collection, while `rollback` (or dropping the transaction) discards them.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use splitmut::{SplitMut, Transaction};
+///
+/// let mut accounts: HashMap<_, _> = vec![("a", 100), ("b", 0)].into_iter().collect();
+/// let mut t = Transaction::new(&mut accounts);
+/// {
+///     let (from, to) = t.get2_mut(&"a", &"b");
+///     let (from, to) = (from.unwrap(), to.unwrap());
+///     *from -= 30;
+///     *to += 30;
+/// }
+/// t.commit();
+/// assert_eq!(accounts[&"a"], 70);
+///
+/// let mut t = Transaction::new(&mut accounts);
+/// *t.get_muts().at(&"b").unwrap() += 1000;
+/// t.rollback();
+/// assert_eq!(accounts[&"b"], 30);
+/// ```
+pub struct Transaction<'a, C: 'a + ?Sized, K, V> {
+    c: &'a mut C,
+    staged: BTreeMap<usize, (K, Box<V>)>,
+}
+
+impl<'a, C: 'a + SplitMut<K, V> + ?Sized, K: Clone, V: Clone> Transaction<'a, C, K, V> {
+    /// Starts a transaction on a collection.
+    pub fn new(c: &'a mut C) -> Self { Transaction { c, staged: BTreeMap::new() } }
+
+    /// The number of values that have been staged.
+    pub fn touched(&self) -> usize { self.staged.len() }
+
+    /// Writes all staged values back to the collection.
+    pub fn commit(self) {
+        trace_method!("commit");
+        let Transaction { c, staged } = self;
+        for (_, (k, v)) in staged {
+            if let Some(t) = c.get1_mut(k) { *t = *v }
+        }
+    }
+
+    /// Discards all staged values, leaving the collection as it was.
+    pub fn rollback(self) {}
+}
+
+unsafe impl<'a, C: 'a + SplitMut<K, V> + ?Sized, K: Clone, V: Clone> SplitMutBase<K, V> for Transaction<'a, C, K, V> {
+    fn get1_mut(&mut self, k: K) -> Option<&mut V> {
+        let kk = k.clone();
+        let orig = self.c.get1_mut(k)?;
+        let p = orig as *mut V as usize;
+        // The copy is boxed, so it stays in place while other values are staged.
+        let e = self.staged.entry(p).or_insert_with(|| (kk, Box::new(orig.clone())));
+        Some(&mut *e.1)
+    }
+    unsafe fn get1_unchecked_mut(&mut self, k: K) -> &mut V { self.get1_mut(k).unwrap() }
+}
+
+#[test]
+fn transaction() {
+    use alloc::vec::Vec;
+    use SplitMutError;
+    let mut v: Vec<u8> = vec![1, 2, 3];
+    {
+        let mut t = Transaction::new(&mut v);
+        assert_eq!(t.get3_mut(0, 0, 5), (Ok(&mut 1), Err(SplitMutError::SameValue), Err(SplitMutError::NoValue)));
+        *t.get1_mut(2).unwrap() = 30;
+        assert_eq!(t.touched(), 2);
+        ::testing::assert_splitmut_sound(&mut t, &[0, 1, 2], &[3]);
+    }
+    assert_eq!(v, [1, 2, 3]);
+    let mut t = Transaction::new(&mut v);
+    *t.get1_mut(1).unwrap() = 20;
+    t.commit();
+    assert_eq!(v, [1, 20, 3]);
+}
@@ -0,0 +1,83 @@
+//! Tuples of keys, for the get_tuple_mut function.
+
+use {SplitMut, SplitMutError, to_r, from_r, dedup_r};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A tuple of keys, which can be resolved to an equally-shaped tuple of results.
+///
+/// Implemented for tuples of up to 12 keys of the same type. This trait is
+/// sealed and cannot be implemented outside this crate.
+///
+/// Use it as a bound to pass a bundle of keys around as one value, without
+/// committing to a number of keys:
+///
+/// ```
+/// use splitmut::{SplitMut, KeyTuple};
+///
+/// fn lookup<'a, T: KeyTuple<'a, usize, u8>>(v: &'a mut Vec<u8>, keys: T) -> T::Output {
+///     v.get_tuple_mut(keys)
+/// }
+///
+/// let mut v = vec![1, 2, 3];
+/// let (a, b) = lookup(&mut v, (0, 2));
+/// std::mem::swap(a.unwrap(), b.unwrap());
+/// assert_eq!(lookup(&mut v, (0,)).0, Ok(&mut 3));
+/// ```
+pub trait KeyTuple<'a, K, V: 'a>: sealed::Sealed {
+    /// A tuple of `Result<&'a mut V, SplitMutError>`, one for every key.
+    type Output;
+
+    /// Resolves the keys against a collection, used internally.
+    fn get_tuple_mut<A: SplitMut<K, V> + ?Sized>(self, a: &'a mut A) -> Self::Output;
+}
+
+macro_rules! key_ty { ($x: ident) => { K } }
+macro_rules! out_ty { ($x: ident) => { Result<&'a mut V, SplitMutError> } }
+
+macro_rules! key_tuple {
+    ($($k: ident $i: tt),+) => {
+        impl<K> sealed::Sealed for ($(key_ty!($k),)+) {}
+
+        impl<'a, K, V: 'a> KeyTuple<'a, K, V> for ($(key_ty!($k),)+) {
+            type Output = ($(out_ty!($k),)+);
+
+            #[inline]
+            fn get_tuple_mut<A: SplitMut<K, V> + ?Sized>(self, a: &'a mut A) -> Self::Output {
+                let mut p = [$(to_r(a.get1_mut(self.$i))),+];
+                dedup_r(&mut p);
+                unsafe { ($(from_r(p[$i]),)+) }
+            }
+        }
+    }
+}
+
+key_tuple!(k0 0);
+key_tuple!(k0 0, k1 1);
+key_tuple!(k0 0, k1 1, k2 2);
+key_tuple!(k0 0, k1 1, k2 2, k3 3);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4, k5 5);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4, k5 5, k6 6);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4, k5 5, k6 6, k7 7);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4, k5 5, k6 6, k7 7, k8 8);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4, k5 5, k6 6, k7 7, k8 8, k9 9);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4, k5 5, k6 6, k7 7, k8 8, k9 9, k10 10);
+key_tuple!(k0 0, k1 1, k2 2, k3 3, k4 4, k5 5, k6 6, k7 7, k8 8, k9 9, k10 10, k11 11);
+
+#[test]
+fn tuple_vec() {
+    use alloc::vec::Vec;
+    let mut v: Vec<u8> = (0..12).collect();
+    {
+        let t = v.get_tuple_mut((11, 0, 5, 0, 12, 1, 2, 3, 4, 6, 7, 8));
+        assert_eq!(t.0, Ok(&mut 11));
+        assert_eq!(t.3, Err(SplitMutError::SameValue));
+        assert_eq!(t.4, Err(SplitMutError::NoValue));
+        *t.2.unwrap() = 50;
+    }
+    assert_eq!(v[5], 50);
+    assert_eq!(v.get_tuple_mut((3,)), (Ok(&mut 3),));
+}
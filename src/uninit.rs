@@ -0,0 +1,197 @@
+//! Initializing a buffer in disjoint regions, e g from several threads.
+
+use core::mem::{self, MaybeUninit};
+use core::ops::{Index, IndexMut, Range};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use slice::check_regions;
+use SplitMutError;
+
+/// A buffer of possibly uninitialized slots, that keeps track of which slots
+/// have been written.
+///
+/// Disjoint regions of the buffer can be written at the same time with `regions_mut`.
+/// Once the needed slots are written, `get_range` and `into_vec` give the values,
+/// checking that they are all initialized. Values still in the buffer are dropped
+/// with it.
+///
+/// # Example
+/// ```
+/// use splitmut::InitBuffer;
+///
+/// let mut b = InitBuffer::new(6);
+/// std::thread::scope(|s| {
+///     for (t, r) in b.regions_mut(&[0..3, 3..6]).into_iter().enumerate() {
+///         let mut r = r.unwrap();
+///         s.spawn(move || for i in 0..r.len() { r.write(i, format!("{}.{}", t, i)); });
+///     }
+/// });
+/// assert_eq!(b.get_range(2..4).unwrap(), ["0.2", "1.0"]);
+/// assert_eq!(b.into_vec().ok().unwrap().len(), 6);
+/// ```
+pub struct InitBuffer<V> { data: Box<[MaybeUninit<V>]>, init: Box<[bool]> }
+
+/// A region of an `InitBuffer`, returned from the regions_mut function.
+pub struct InitRegion<'a, V: 'a> { data: &'a mut [MaybeUninit<V>], init: &'a mut [bool] }
+
+impl<V> InitBuffer<V> {
+    /// Creates a buffer of `len` uninitialized slots.
+    pub fn new(len: usize) -> Self {
+        let data: Vec<MaybeUninit<V>> = (0..len).map(|_| MaybeUninit::uninit()).collect();
+        InitBuffer::from_uninit(data.into_boxed_slice())
+    }
+
+    /// Wraps existing slots. All of them are considered uninitialized, so whatever
+    /// they contain is never read or dropped.
+    pub fn from_uninit(data: Box<[MaybeUninit<V>]>) -> Self {
+        let init = alloc::vec![false; data.len()].into_boxed_slice();
+        InitBuffer { data, init }
+    }
+
+    /// Number of slots.
+    pub fn len(&self) -> usize { self.data.len() }
+
+    /// Returns true if there are no slots.
+    pub fn is_empty(&self) -> bool { self.data.is_empty() }
+
+    /// Returns true if slot `i` has been written.
+    pub fn is_init(&self, i: usize) -> bool { self.init.get(i) == Some(&true) }
+
+    /// Returns the values of a range of slots, or `None` if the range is out of
+    /// bounds or any slot in it has not been written.
+    pub fn get_range(&self, r: Range<usize>) -> Option<&[V]> {
+        if !self.init.get(r.clone())?.iter().all(|&x| x) { return None }
+        // All slots of the range are initialized, and MaybeUninit<V> has the layout of V.
+        Some(unsafe { &*(&self.data[r] as *const [MaybeUninit<V>] as *const [V]) })
+    }
+
+    /// Like `get_range`, but the values are mutable.
+    pub fn get_range_mut(&mut self, r: Range<usize>) -> Option<&mut [V]> {
+        if !self.init.get(r.clone())?.iter().all(|&x| x) { return None }
+        Some(unsafe { &mut *(&mut self.data[r] as *mut [MaybeUninit<V>] as *mut [V]) })
+    }
+
+    /// Returns the regions, all writable at the same time. A region that is out of range
+    /// gives `Err(SplitMutError::NoValue)`, and one that overlaps with a region
+    /// returned before it gives `Err(SplitMutError::SameValue)`.
+    pub fn regions_mut(&mut self, regions: &[Range<usize>]) -> Vec<Result<InitRegion<'_, V>, SplitMutError>> {
+        trace_method!("regions_mut");
+        let (d, i) = (self.data.as_mut_ptr(), self.init.as_mut_ptr());
+        check_regions(self.len(), regions).into_iter().map(|r| r.map(|r| unsafe {
+            // The region is in range and does not overlap with any region returned before it.
+            InitRegion {
+                data: core::slice::from_raw_parts_mut(d.add(r.start), r.len()),
+                init: core::slice::from_raw_parts_mut(i.add(r.start), r.len()),
+            }
+        })).collect()
+    }
+
+    /// Returns the values, if all slots have been written, or else the buffer itself.
+    pub fn into_vec(mut self) -> Result<Vec<V>, Self> {
+        if !self.init.iter().all(|&x| x) { return Err(self) }
+        let data = mem::take(&mut self.data).into_vec();
+        self.init = Box::new([]);
+        // All slots are initialized.
+        Ok(data.into_iter().map(|x| unsafe { x.assume_init() }).collect())
+    }
+}
+
+impl<V> Index<usize> for InitBuffer<V> {
+    type Output = V;
+    fn index(&self, i: usize) -> &V { &self.get_range(i..i.wrapping_add(1)).expect("slot is out of range or not written")[0] }
+}
+
+impl<V> IndexMut<usize> for InitBuffer<V> {
+    fn index_mut(&mut self, i: usize) -> &mut V { &mut self.get_range_mut(i..i.wrapping_add(1)).expect("slot is out of range or not written")[0] }
+}
+
+impl<V> Drop for InitBuffer<V> {
+    fn drop(&mut self) {
+        for (x, _) in self.data.iter_mut().zip(self.init.iter()).filter(|x| *x.1) {
+            unsafe { x.assume_init_drop() }
+        }
+    }
+}
+
+impl<'a, V: 'a> InitRegion<'a, V> {
+    /// Number of slots in the region.
+    pub fn len(&self) -> usize { self.data.len() }
+
+    /// Returns true if the region has no slots.
+    pub fn is_empty(&self) -> bool { self.data.is_empty() }
+
+    /// Returns true if slot `i` of the region has been written.
+    pub fn is_init(&self, i: usize) -> bool { self.init.get(i) == Some(&true) }
+
+    /// Writes a value to slot `i` of the region, dropping the value it had, if any,
+    /// and returns a mutable reference to it.
+    ///
+    /// # Panics
+    /// Panics if `i` is out of range.
+    pub fn write(&mut self, i: usize, v: V) -> &mut V {
+        // Marked as not written while the old value is dropped, so that a panic
+        // in its drop does not make the buffer drop it again.
+        if mem::replace(&mut self.init[i], false) {
+            unsafe { self.data[i].assume_init_drop() }
+        }
+        let r = self.data[i].write(v);
+        self.init[i] = true;
+        r
+    }
+
+    /// Returns the value of slot `i` of the region, if it has been written.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut V> {
+        if self.is_init(i) { Some(unsafe { self.data[i].assume_init_mut() }) } else { None }
+    }
+}
+
+#[test]
+fn init_buffer() {
+    use alloc::rc::Rc;
+    let counted = Rc::new(());
+    let mut b = InitBuffer::from_uninit(Box::new([MaybeUninit::uninit(), MaybeUninit::uninit(), MaybeUninit::uninit()]));
+    {
+        let mut r = b.regions_mut(&[1..3, 0..2, 3..4]);
+        assert_eq!(r[1].as_ref().err(), Some(&SplitMutError::SameValue));
+        assert_eq!(r[2].as_ref().err(), Some(&SplitMutError::NoValue));
+        let r = r[0].as_mut().unwrap();
+        r.write(0, counted.clone());
+        r.write(0, counted.clone());
+        assert!(r.get_mut(1).is_none());
+        assert!(r.is_init(0) && !r.is_init(1));
+    }
+    assert_eq!(Rc::strong_count(&counted), 2);
+    assert!(b.is_init(1) && !b.is_init(0));
+    assert!(b.get_range(1..2).is_some());
+    assert!(b.get_range(1..3).is_none());
+    assert!(b.get_range(3..5).is_none());
+    let b = b.into_vec().err().unwrap();
+    drop(b);
+    assert_eq!(Rc::strong_count(&counted), 1);
+    let mut b = InitBuffer::new(2);
+    for (i, r) in b.regions_mut(&[1..2, 0..1]).into_iter().enumerate() { r.unwrap().write(0, i); }
+    *b.get_range_mut(0..2).unwrap().first_mut().unwrap() += 5;
+    b[1] += b[0];
+    assert_eq!(b.into_vec().ok(), Some(vec![6, 6]));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn init_panicking_drop() {
+    use std::cell::Cell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    struct Bomb<'a>(&'a Cell<u32>, bool);
+    impl<'a> Drop for Bomb<'a> {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1); if self.1 { panic!("boom") } }
+    }
+    let drops = Cell::new(0);
+    let mut b = InitBuffer::new(2);
+    b.regions_mut(&[0..1, 1..2])[0].as_mut().unwrap().write(0, Bomb(&drops, true));
+    let r = catch_unwind(AssertUnwindSafe(|| { b.regions_mut(&[0..1, 1..2])[0].as_mut().unwrap().write(0, Bomb(&drops, false)); }));
+    assert!(r.is_err());
+    // The old value, and the new one, which was dropped while unwinding.
+    assert_eq!(drops.get(), 2);
+    assert!(!b.is_init(0));
+    drop(b);
+    assert_eq!(drops.get(), 2);
+}
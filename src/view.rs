@@ -0,0 +1,104 @@
+//! Read-only access to a collection while values are split out of it.
+
+use std::collections::{HashMap, BTreeMap, VecDeque};
+use std::hash::{Hash, BuildHasher};
+use std::ops::Index;
+use std::vec::Vec;
+use {GetMuts, GetRef, SplitMut, SplitMutError, err};
+
+/// Collections that can tell their number of values without looking at the values.
+///
+/// Used by `ReadView::len`, which may be called while values are mutably borrowed.
+pub trait CollectionLen {
+    /// Number of values in the collection.
+    fn len(&self) -> usize;
+
+    /// Returns true if the collection has no values.
+    fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+impl<V> CollectionLen for [V] { fn len(&self) -> usize { <[V]>::len(self) } }
+impl<V> CollectionLen for Vec<V> { fn len(&self) -> usize { Vec::len(self) } }
+impl<V> CollectionLen for VecDeque<V> { fn len(&self) -> usize { VecDeque::len(self) } }
+impl<K, V, S: BuildHasher> CollectionLen for HashMap<K, V, S> where K: Hash + Eq { fn len(&self) -> usize { HashMap::len(self) } }
+impl<K, V> CollectionLen for BTreeMap<K, V> { fn len(&self) -> usize { BTreeMap::len(self) } }
+
+/// A read-only view of the collection behind a `GetMuts`, returned from `GetMuts::view`.
+///
+/// Shared lookups of values that the session has already returned mutably give
+/// `Err(SplitMutError::SameValue)`. The view borrows the session, so no new
+/// values can be split out while it is alive.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use splitmut::{SplitMut, SplitMutError};
+///
+/// let mut h: HashMap<_, _> = vec![("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+/// let mut z = h.get_muts();
+/// let a = z.at(&"a").unwrap();
+/// let v = z.view();
+/// assert_eq!(v.len(), 3);
+/// assert!(v.contains_key(&"a"));
+/// *a += v.get(&"b").unwrap() + v.get(&"c").unwrap();
+/// assert_eq!(v.get(&"a"), Err(SplitMutError::SameValue));
+/// # drop(v);
+/// assert_eq!(h["a"], 6);
+/// ```
+pub struct ReadView<'s, 'a: 's, K: 's, V: 's, A: 'a + SplitMut<K, V> + ?Sized>(&'s GetMuts<'a, K, V, A>);
+
+impl<'a, K, V, A: 'a + SplitMut<K, V> + ?Sized> GetMuts<'a, K, V, A> {
+    /// Returns a read-only view of the collection, for lookups of values not
+    /// returned by this session.
+    pub fn view(&self) -> ReadView<'_, 'a, K, V, A> { ReadView(self) }
+}
+
+impl<'s, 'a: 's, K: 's, V: 's, A: 'a + SplitMut<K, V> + ?Sized> ReadView<'s, 'a, K, V, A> {
+    /// Number of values in the collection, including those returned mutably.
+    pub fn len(&self) -> usize where A: CollectionLen { (*self.0 .1).len() }
+
+    /// Returns true if the collection has no values.
+    pub fn is_empty(&self) -> bool where A: CollectionLen { (*self.0 .1).is_empty() }
+
+    /// Returns true if there is a value for the key, whether or not it has
+    /// been returned mutably.
+    pub fn contains_key(&self, k: K) -> bool where A: GetRef<K, V> { self.0 .1.get1_ref(k).is_some() }
+
+    /// Returns a shared reference to the value of a key, or an error if there is no
+    /// value, or the value has been returned mutably by the session.
+    pub fn get(&self, k: K) -> Result<&'s V, SplitMutError> where A: GetRef<K, V> {
+        trace_method!("view_get");
+        let v = self.0 .1.get1_ref(k).ok_or_else(|| err(SplitMutError::NoValue))?;
+        if self.0 .0.contains(v as *const V as usize) { return Err(err(SplitMutError::SameValue)) }
+        Ok(v)
+    }
+}
+
+impl<'s, 'a: 's, K: 's, V: 's, A: 'a + SplitMut<K, V> + GetRef<K, V> + ?Sized> Index<K> for ReadView<'s, 'a, K, V, A> {
+    type Output = V;
+    fn index(&self, k: K) -> &V {
+        match self.get(k) {
+            Ok(v) => v,
+            Err(e) => panic!("cannot index view: {}", e),
+        }
+    }
+}
+
+#[test]
+fn read_view() {
+    let mut v = vec![1, 2, 3];
+    let mut z = v.get_muts();
+    let a = z.at(0).unwrap();
+    {
+        let r = z.view();
+        assert_eq!(r.len(), 3);
+        assert!(!r.is_empty());
+        assert!(r.contains_key(0));
+        assert!(!r.contains_key(3));
+        assert_eq!(r.get(0), Err(SplitMutError::SameValue));
+        assert_eq!(r.get(3), Err(SplitMutError::NoValue));
+        *a += r.get(2).unwrap() + r[1];
+    }
+    assert_eq!(z.at(0), Err(SplitMutError::SameValue));
+    assert_eq!(v, [6, 2, 3]);
+}